@@ -0,0 +1,62 @@
+use chrono::NaiveDate;
+
+use crate::data_traits::Close;
+use crate::errors::OHLCVDataError;
+use crate::OHLCVData;
+
+/// Daily bars of a single security, kept in ascending date order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BarSeries {
+    symbol: String,
+    dates: Vec<NaiveDate>,
+    bars: Vec<OHLCVData>,
+}
+
+impl BarSeries {
+    pub fn new<S: Into<String>>(symbol: S) -> Self {
+        Self {
+            symbol: symbol.into(),
+            dates: Vec::new(),
+            bars: Vec::new(),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn push(&mut self, date: NaiveDate, bar: OHLCVData) -> Result<(), OHLCVDataError> {
+        match self.dates.last() {
+            Some(last) if *last >= date => Err(OHLCVDataError::OutOfOrder),
+            _ => {
+                self.dates.push(date);
+                self.bars.push(bar);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.dates
+    }
+
+    pub fn bars(&self) -> &[OHLCVData] {
+        &self.bars
+    }
+
+    pub fn closes(&self) -> Vec<f64> {
+        self.bars.iter().map(|bar| bar.close()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NaiveDate, &OHLCVData)> {
+        self.dates.iter().cloned().zip(self.bars.iter())
+    }
+}
@@ -0,0 +1,183 @@
+//! Whole-series indicator computation. Each function produces exactly the
+//! values the streaming indicators would return when fed the same inputs one
+//! `next()` call at a time.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::errors::CommonError;
+use crate::BarSeries;
+
+pub fn ema(values: &[f64], period: usize) -> Result<Vec<f64>, CommonError> {
+    if period == 0 {
+        return Err(CommonError::InvalidArgument);
+    }
+
+    let mut output = Vec::with_capacity(values.len());
+    if let Some((first, rest)) = values.split_first() {
+        let k = 2.0 / (period + 1) as f64;
+        let decay = 1.0 - k;
+        let mut current = *first;
+        output.push(current);
+        for input in rest {
+            current = k * input + decay * current;
+            output.push(current);
+        }
+    }
+    Ok(output)
+}
+
+/// As in `RelativeStrengthIndex`, no movement either way is neutral.
+fn relative_strength(up_ema: f64, down_ema: f64) -> f64 {
+    if up_ema + down_ema == 0.0 {
+        return 50.0;
+    }
+    100.0 * up_ema / (up_ema + down_ema)
+}
+
+pub fn rsi(values: &[f64], period: usize) -> Result<Vec<f64>, CommonError> {
+    if period == 0 {
+        return Err(CommonError::InvalidArgument);
+    }
+
+    let mut output = Vec::with_capacity(values.len());
+    if let Some((first, rest)) = values.split_first() {
+        let k = 2.0 / (period + 1) as f64;
+        let decay = 1.0 - k;
+        let mut up_ema = 0.1;
+        let mut down_ema = 0.1;
+        let mut prev = *first;
+        output.push(relative_strength(up_ema, down_ema));
+        for input in rest {
+            let (up, down) = if *input > prev {
+                (input - prev, 0.0)
+            } else {
+                (0.0, prev - input)
+            };
+            prev = *input;
+            up_ema = k * up + decay * up_ema;
+            down_ema = k * down + decay * down_ema;
+            output.push(relative_strength(up_ema, down_ema));
+        }
+    }
+    Ok(output)
+}
+
+pub fn ema_series(series: &BarSeries, period: usize) -> Result<Vec<f64>, CommonError> {
+    ema(&series.closes(), period)
+}
+
+pub fn rsi_series(series: &BarSeries, period: usize) -> Result<Vec<f64>, CommonError> {
+    rsi(&series.closes(), period)
+}
+
+/// Applies `f` to every series, in parallel when the `rayon` feature is on.
+fn map_series<F>(series: &[BarSeries], f: F) -> Result<Vec<Vec<f64>>, CommonError>
+where
+    F: Fn(&BarSeries) -> Result<Vec<f64>, CommonError> + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    let result = series.par_iter().map(f).collect();
+    #[cfg(not(feature = "rayon"))]
+    let result = series.iter().map(f).collect();
+    result
+}
+
+pub fn ema_many(series: &[BarSeries], period: usize) -> Result<Vec<Vec<f64>>, CommonError> {
+    map_series(series, |s| ema_series(s, period))
+}
+
+pub fn rsi_many(series: &[BarSeries], period: usize) -> Result<Vec<Vec<f64>>, CommonError> {
+    map_series(series, |s| rsi_series(s, period))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_traits::Next;
+    use crate::indicators::{ExponentialMovingAverage, RelativeStrengthIndex};
+    use crate::OHLCVData;
+    use chrono::NaiveDate;
+
+    fn closes() -> Vec<f64> {
+        let mut seed = 7u64;
+        (0..500)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                10.0 + (seed >> 33) as f64 / (1u64 << 31) as f64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ema_matches_streaming() {
+        let values = closes();
+        for period in &[1, 9, 26] {
+            let mut indicator = ExponentialMovingAverage::new(*period).unwrap();
+            let expected: Vec<f64> = values.iter().map(|v| indicator.next(*v)).collect();
+            assert_eq!(ema(&values, *period).unwrap(), expected);
+        }
+        assert!(ema(&values, 0).is_err());
+        assert!(ema(&[], 9).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rsi_matches_streaming() {
+        let values = closes();
+        for period in &[2, 14] {
+            let mut indicator = RelativeStrengthIndex::new(*period).unwrap();
+            let expected: Vec<f64> = values.iter().map(|v| indicator.next(*v)).collect();
+            assert_eq!(rsi(&values, *period).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rsi_matches_streaming_on_flat_series() {
+        let values = vec![10.0; 200];
+        for period in &[1, 14] {
+            let mut indicator = RelativeStrengthIndex::new(*period).unwrap();
+            let expected: Vec<f64> = values.iter().map(|v| indicator.next(*v)).collect();
+            let output = rsi(&values, *period).unwrap();
+            assert!(output.iter().all(|value| value.is_finite()));
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn many_series_match_streaming() {
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let series: Vec<BarSeries> = ["SM", "ALI", "BDO"]
+            .iter()
+            .enumerate()
+            .map(|(offset, symbol)| {
+                let mut series = BarSeries::new(*symbol);
+                for (i, close) in closes().iter().skip(offset * 10).enumerate() {
+                    let bar = OHLCVData::builder()
+                        .open(*close)
+                        .high(*close)
+                        .low(*close)
+                        .close(*close)
+                        .volume(0.0)
+                        .build()
+                        .unwrap();
+                    series
+                        .push(start + chrono::Duration::days(i as i64), bar)
+                        .unwrap();
+                }
+                series
+            })
+            .collect();
+
+        let result = ema_many(&series, 12).unwrap();
+        for (s, values) in series.iter().zip(result.iter()) {
+            let mut indicator = ExponentialMovingAverage::new(12).unwrap();
+            let expected: Vec<f64> = s.bars().iter().map(|bar| indicator.next(bar)).collect();
+            assert_eq!(*values, expected);
+        }
+
+        let result = rsi_many(&series, 14).unwrap();
+        assert_eq!(result[1], rsi_series(&series[1], 14).unwrap());
+    }
+}
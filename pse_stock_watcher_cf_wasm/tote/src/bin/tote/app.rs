@@ -0,0 +1,360 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use tote::client::{Stock, Stocks};
+use tote::data_traits::Next;
+use tote::indicators::{ExponentialMovingAverage, RelativeStrengthIndex};
+use tote::BarSeries;
+
+pub const EMA_PERIOD: usize = 9;
+pub const RSI_PERIOD: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Change,
+    Volume,
+    Symbol,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Change => SortKey::Volume,
+            SortKey::Volume => SortKey::Symbol,
+            SortKey::Symbol => SortKey::Change,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Change => "change",
+            SortKey::Volume => "volume",
+            SortKey::Symbol => "symbol",
+        }
+    }
+
+    /// Biggest gainers and most traded first, symbols alphabetically.
+    fn compare(self, a: &Stock, b: &Stock) -> Ordering {
+        let ordering = match self {
+            SortKey::Change => b
+                .perc_change_close
+                .partial_cmp(&a.perc_change_close)
+                .unwrap_or(Ordering::Equal),
+            SortKey::Volume => b.total_volume.cmp(&a.total_volume),
+            SortKey::Symbol => Ordering::Equal,
+        };
+        ordering.then_with(|| a.security_symbol.cmp(&b.security_symbol))
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "change" => Ok(SortKey::Change),
+            "volume" => Ok(SortKey::Volume),
+            "symbol" => Ok(SortKey::Symbol),
+            _ => Err(format!(
+                "unknown sort key `{}`, expected change, volume or symbol",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchlist {
+    pub name: String,
+    pub symbols: Vec<String>,
+}
+
+/// Quotes of `symbols`, or of everything listed, in `sort` order.
+pub fn sorted<'a>(
+    securities: &'a Stocks,
+    symbols: Option<&[String]>,
+    sort: SortKey,
+    reversed: bool,
+) -> Vec<&'a Stock> {
+    let mut quotes: Vec<&Stock> = match symbols {
+        Some(symbols) => symbols
+            .iter()
+            .filter_map(|symbol| securities.get(symbol))
+            .collect(),
+        None => securities.stocks.iter().collect(),
+    };
+    quotes.sort_by(|a, b| sort.compare(a, b));
+    if reversed {
+        quotes.reverse();
+    }
+    quotes
+}
+
+/// The recent closes of a symbol and its latest indicator values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    pub closes: Vec<f64>,
+    pub ema: Option<f64>,
+    pub rsi: Option<f64>,
+}
+
+impl Trend {
+    pub fn new(series: &BarSeries) -> Self {
+        let closes = series.closes();
+        let last = |values: Vec<f64>, period: usize| {
+            Some(values.len())
+                .filter(|len| *len >= period)
+                .and_then(|_| values.last().copied())
+        };
+
+        let mut ema = ExponentialMovingAverage::new(EMA_PERIOD).unwrap();
+        let mut rsi = RelativeStrengthIndex::new(RSI_PERIOD).unwrap();
+        let emas = closes.iter().map(|close| ema.next(*close)).collect();
+        let rsis = closes.iter().map(|close| rsi.next(*close)).collect();
+
+        Self {
+            ema: last(emas, EMA_PERIOD),
+            rsi: last(rsis, RSI_PERIOD),
+            closes,
+        }
+    }
+}
+
+/// What the dashboard shows. The first tab lists every security and the
+/// rest are watchlists.
+#[derive(Debug, Default)]
+pub struct App {
+    pub securities: Option<Stocks>,
+    pub watchlists: Vec<Watchlist>,
+    pub tab: usize,
+    pub sort: SortKey,
+    pub reversed: bool,
+    pub selected: usize,
+    /// Trends by symbol, or why one could not be loaded.
+    pub trends: HashMap<String, Result<Trend, String>>,
+    pub error: Option<String>,
+}
+
+impl App {
+    pub fn new(watchlists: Vec<Watchlist>, sort: SortKey) -> Self {
+        Self {
+            watchlists,
+            sort,
+            ..Self::default()
+        }
+    }
+
+    pub fn tab_names(&self) -> Vec<&str> {
+        std::iter::once("All")
+            .chain(self.watchlists.iter().map(|list| list.name.as_str()))
+            .collect()
+    }
+
+    pub fn rows(&self) -> Vec<&Stock> {
+        let symbols = match self.tab {
+            0 => None,
+            tab => self
+                .watchlists
+                .get(tab - 1)
+                .map(|list| list.symbols.as_slice()),
+        };
+        match &self.securities {
+            Some(securities) => sorted(securities, symbols, self.sort, self.reversed),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn selected_symbol(&self) -> Option<String> {
+        self.rows()
+            .get(self.selected)
+            .map(|quote| quote.security_symbol.clone())
+    }
+
+    /// Replaces the quotes, keeping the same symbol selected. Trends are
+    /// dropped so they are loaded again with the day's latest quotes.
+    pub fn update(&mut self, securities: Stocks) {
+        let selected = self.selected_symbol();
+        self.securities = Some(securities);
+        self.trends.clear();
+        self.error = None;
+        self.reselect(selected);
+    }
+
+    fn reselect(&mut self, symbol: Option<String>) {
+        let rows = self.rows();
+        self.selected = symbol
+            .and_then(|symbol| {
+                rows.iter()
+                    .position(|quote| quote.security_symbol == symbol)
+            })
+            .unwrap_or(0)
+            .min(rows.len().saturating_sub(1));
+    }
+
+    pub fn next_tab(&mut self) {
+        self.tab = (self.tab + 1) % (self.watchlists.len() + 1);
+        self.selected = 0;
+    }
+
+    pub fn previous_tab(&mut self) {
+        self.tab = match self.tab {
+            0 => self.watchlists.len(),
+            tab => tab - 1,
+        };
+        self.selected = 0;
+    }
+
+    pub fn select_tab(&mut self, tab: usize) {
+        if tab <= self.watchlists.len() {
+            self.tab = tab;
+            self.selected = 0;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.rows().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn cycle_sort(&mut self) {
+        let selected = self.selected_symbol();
+        self.sort = self.sort.next();
+        self.reversed = false;
+        self.reselect(selected);
+    }
+
+    pub fn reverse(&mut self) {
+        let selected = self.selected_symbol();
+        self.reversed = !self.reversed;
+        self.reselect(selected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use tote::client::Indicator;
+    use tote::OHLCVData;
+
+    fn quote(symbol: &str, percent_change: f64, volume: u64) -> Stock {
+        Stock {
+            security_symbol: symbol.to_string(),
+            security_alias: symbol.to_string(),
+            last_traded_price: 10.0,
+            perc_change_close: percent_change,
+            total_volume: volume,
+            indicator: Indicator::Unchanged,
+        }
+    }
+
+    fn securities() -> Stocks {
+        Stocks {
+            as_of: None,
+            stocks: vec![
+                quote("SM", 1.5, 100),
+                quote("ALI", -2.0, 900),
+                quote("BDO", 3.0, 50),
+                quote("BPI", 0.0, 400),
+            ],
+        }
+    }
+
+    fn symbols(quotes: Vec<&Stock>) -> Vec<&str> {
+        quotes
+            .iter()
+            .map(|quote| quote.security_symbol.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_quotes() {
+        let securities = securities();
+        let by = |sort, reversed| symbols(sorted(&securities, None, sort, reversed));
+        assert_eq!(by(SortKey::Change, false), vec!["BDO", "SM", "BPI", "ALI"]);
+        assert_eq!(by(SortKey::Volume, false), vec!["ALI", "BPI", "SM", "BDO"]);
+        assert_eq!(by(SortKey::Symbol, false), vec!["ALI", "BDO", "BPI", "SM"]);
+        assert_eq!(by(SortKey::Change, true), vec!["ALI", "BPI", "SM", "BDO"]);
+
+        let banks = vec!["BPI".to_string(), "GONE".to_string(), "BDO".to_string()];
+        assert_eq!(
+            symbols(sorted(&securities, Some(&banks), SortKey::Change, false)),
+            vec!["BDO", "BPI"]
+        );
+    }
+
+    #[test]
+    fn keeps_selection_across_updates() {
+        let banks = Watchlist {
+            name: "Banks".to_string(),
+            symbols: vec!["BDO".to_string(), "BPI".to_string()],
+        };
+        let mut app = App::new(vec![banks], SortKey::Change);
+        assert_eq!(app.tab_names(), vec!["All", "Banks"]);
+        assert_eq!(app.selected_symbol(), None);
+
+        app.update(securities());
+        app.select_next();
+        assert_eq!(app.selected_symbol().as_deref(), Some("SM"));
+        app.cycle_sort();
+        assert_eq!(app.sort, SortKey::Volume);
+        assert_eq!(app.selected_symbol().as_deref(), Some("SM"));
+        app.reverse();
+        assert_eq!(app.selected_symbol().as_deref(), Some("SM"));
+
+        app.trends
+            .insert("SM".to_string(), Err("timed out".to_string()));
+        let mut moved = securities();
+        moved.stocks.retain(|quote| quote.security_symbol != "SM");
+        app.update(moved);
+        assert_eq!(app.selected, 0);
+        assert!(app.trends.is_empty());
+
+        app.previous_tab();
+        assert_eq!(app.tab, 1);
+        // Still by volume, reversed.
+        assert_eq!(symbols(app.rows()), vec!["BDO", "BPI"]);
+        app.select_next();
+        app.select_next();
+        assert_eq!(app.selected_symbol().as_deref(), Some("BPI"));
+        app.next_tab();
+        assert_eq!((app.tab, app.selected), (0, 0));
+        app.select_tab(5);
+        assert_eq!(app.tab, 0);
+    }
+
+    #[test]
+    fn summarizes_trends() {
+        let start = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let mut series = BarSeries::new("SM");
+        for day in 0..RSI_PERIOD {
+            let close = 100.0 + day as f64;
+            let bar = OHLCVData::builder()
+                .open(close)
+                .high(close)
+                .low(close)
+                .close(close)
+                .volume(1000.0)
+                .build()
+                .unwrap();
+            series
+                .push(start + chrono::Duration::days(day as i64), bar)
+                .unwrap();
+        }
+
+        let trend = Trend::new(&series);
+        assert_eq!(trend.closes.len(), RSI_PERIOD);
+        assert!(trend.ema.unwrap() < 113.0);
+        assert!(trend.rsi.unwrap() > 90.0);
+
+        let short = Trend::new(&BarSeries::new("SM"));
+        assert_eq!((short.ema, short.rsi), (None, None));
+    }
+}
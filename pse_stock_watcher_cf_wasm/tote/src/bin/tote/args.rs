@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::app::{SortKey, Watchlist};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+/// PSE updates its list about once a minute, so polling faster than this
+/// only adds load.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+pub const USAGE: &str = "\
+Usage: tote [OPTIONS]
+
+Shows live PSE quotes. Opens a dashboard when attached to a terminal and
+prints a quote table on every refresh otherwise.
+
+Options:
+  --interval <SECS>          Seconds between refreshes [default: 30, min: 5]
+  --watchlist <NAME=SYMBOLS> A tab of comma separated symbols, e.g.
+                             Banks=BDO,BPI,MBT. May be repeated
+  --sort <KEY>               change, volume or symbol [default: change]
+  --plain                    Print quote tables even on a terminal
+  --once                     Print one quote table and exit
+  --base-url <URL>           PSE stockMarket URL [env: PSE_BASE_URL]
+  -h, --help                 Print this help";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub interval: Duration,
+    pub watchlists: Vec<Watchlist>,
+    pub sort: SortKey,
+    pub plain: bool,
+    pub once: bool,
+    pub base_url: Option<String>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            watchlists: Vec::new(),
+            sort: SortKey::default(),
+            plain: false,
+            once: false,
+            base_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+fn watchlist(value: &str) -> Result<Watchlist, String> {
+    let (name, symbols) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=SYMBOLS, got `{}`", value))?;
+    let name = name.trim();
+    let symbols: Vec<String> = symbols
+        .split(',')
+        .map(|symbol| symbol.trim().to_uppercase())
+        .filter(|symbol| !symbol.is_empty())
+        .collect();
+
+    if name.is_empty() || symbols.is_empty() {
+        return Err(format!("watchlist `{}` needs a name and symbols", value));
+    }
+    Ok(Watchlist {
+        name: name.to_string(),
+        symbols,
+    })
+}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--plain" => parsed.plain = true,
+            "--once" => parsed.once = true,
+            "--interval" => {
+                let value = value("--interval")?;
+                let seconds: u64 = value
+                    .parse()
+                    .map_err(|_| format!("invalid interval `{}`", value))?;
+                parsed.interval = Duration::from_secs(seconds).max(MIN_INTERVAL);
+            }
+            "--watchlist" => parsed.watchlists.push(watchlist(&value("--watchlist")?)?),
+            "--sort" => parsed.sort = value("--sort")?.parse()?,
+            "--base-url" => parsed.base_url = Some(value("--base-url")?),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    Ok(Command::Run(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let command = parse_all(&[
+            "--interval",
+            "1",
+            "--watchlist",
+            "Banks=bdo, BPI,,mbt",
+            "--sort",
+            "volume",
+            "--once",
+        ])
+        .unwrap();
+
+        let args = match command {
+            Command::Run(args) => args,
+            Command::Help => panic!("expected run"),
+        };
+        assert_eq!(args.interval, MIN_INTERVAL);
+        assert_eq!(args.watchlists[0].name, "Banks");
+        assert_eq!(args.watchlists[0].symbols, vec!["BDO", "BPI", "MBT"]);
+        assert_eq!(args.sort, SortKey::Volume);
+        assert!(args.once && !args.plain);
+        assert_eq!(parse_all(&[]).unwrap(), Command::Run(Args::default()));
+        assert_eq!(parse_all(&["--plain", "-h"]).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(parse_all(&["--interval"]).is_err());
+        assert!(parse_all(&["--interval", "soon"]).is_err());
+        assert!(parse_all(&["--watchlist", "Banks"]).is_err());
+        assert!(parse_all(&["--watchlist", "Banks=,"]).is_err());
+        assert!(parse_all(&["--sort", "price"]).is_err());
+        assert!(parse_all(&["--verbose"]).is_err());
+    }
+}
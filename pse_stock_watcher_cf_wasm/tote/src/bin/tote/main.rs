@@ -0,0 +1,55 @@
+mod app;
+mod args;
+mod plain;
+mod tui;
+
+use std::io::{self, IsTerminal};
+use std::process;
+
+use tote::client::Client;
+
+use crate::args::{Command, USAGE};
+
+/// Environment variable overriding the PSE base URL, named like the
+/// worker's binding.
+const BASE_URL_VAR: &str = "PSE_BASE_URL";
+
+#[tokio::main]
+async fn main() {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("tote: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut client = match Client::new() {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("tote: {}", error);
+            process::exit(1);
+        }
+    };
+    if let Some(base_url) = args
+        .base_url
+        .clone()
+        .or_else(|| std::env::var(BASE_URL_VAR).ok())
+    {
+        client = client.with_base_url(base_url);
+    }
+
+    let result = if args.plain || args.once || !io::stdout().is_terminal() {
+        plain::run(&client, &args).await
+    } else {
+        tui::run(&client, &args).await
+    };
+    if let Err(error) = result {
+        eprintln!("tote: {}", error);
+        process::exit(1);
+    }
+}
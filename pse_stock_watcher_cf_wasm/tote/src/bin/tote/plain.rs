@@ -0,0 +1,151 @@
+use std::error::Error;
+
+use tote::client::{Client, Stock, Stocks};
+
+use crate::app::sorted;
+use crate::args::Args;
+
+/// `1234567.5` as `1,234,567.50`.
+pub fn thousands(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = match formatted.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut grouped = String::with_capacity(formatted.len() + whole.len() / 3);
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    if value < 0.0 {
+        grouped.insert(0, '-');
+    }
+    grouped
+}
+
+pub fn percent(value: f64) -> String {
+    format!("{:+.2}%", value)
+}
+
+fn row(quote: &Stock) -> String {
+    format!(
+        "{:<8} {:>12} {:>8} {:>15}",
+        quote.security_symbol,
+        thousands(quote.last_traded_price, 2),
+        percent(quote.perc_change_close),
+        thousands(quote.total_volume as f64, 0)
+    )
+}
+
+/// The quotes of the watchlist symbols, or of everything when there are no
+/// watchlists, as a plain text table.
+pub fn table(securities: &Stocks, args: &Args) -> String {
+    let symbols: Vec<String> = args
+        .watchlists
+        .iter()
+        .flat_map(|list| list.symbols.iter().cloned())
+        .fold(Vec::new(), |mut symbols, symbol| {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+            symbols
+        });
+    let symbols = Some(symbols.as_slice()).filter(|symbols| !symbols.is_empty());
+
+    let mut lines = vec![
+        match securities.as_of {
+            Some(as_of) => format!("As of {}", as_of),
+            None => "As of unknown".to_string(),
+        },
+        format!(
+            "{:<8} {:>12} {:>8} {:>15}",
+            "SYMBOL", "LAST", "CHANGE", "VOLUME"
+        ),
+    ];
+    lines.extend(
+        sorted(securities, symbols, args.sort, false)
+            .into_iter()
+            .map(row),
+    );
+    lines.join("\n")
+}
+
+/// Prints a quote table every interval. Failed refreshes are reported and
+/// retried, except with `--once`.
+pub async fn run(client: &Client, args: &Args) -> Result<(), Box<dyn Error>> {
+    loop {
+        match client.securities().await {
+            Ok(securities) => println!("{}\n", table(&securities, args)),
+            Err(error) if args.once => return Err(error.into()),
+            Err(error) => eprintln!("tote: {}", error),
+        }
+
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::delay_for(args.interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{SortKey, Watchlist};
+    use chrono::NaiveDate;
+    use tote::client::Indicator;
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(thousands(1_234_567.5, 2), "1,234,567.50");
+        assert_eq!(thousands(999.0, 0), "999");
+        assert_eq!(thousands(-1000.0, 1), "-1,000.0");
+        assert_eq!(percent(1.5), "+1.50%");
+        assert_eq!(percent(-0.25), "-0.25%");
+    }
+
+    #[test]
+    fn prints_watchlist_quotes() {
+        let quote = |symbol: &str, percent_change| Stock {
+            security_symbol: symbol.to_string(),
+            security_alias: symbol.to_string(),
+            last_traded_price: 1010.0,
+            perc_change_close: percent_change,
+            total_volume: 1_234_500,
+            indicator: Indicator::Up,
+        };
+        let securities = Stocks {
+            as_of: NaiveDate::from_ymd_opt(2020, 10, 16)
+                .unwrap()
+                .and_hms_opt(15, 0, 0),
+            stocks: vec![quote("SM", 1.25), quote("ALI", 2.0), quote("BDO", 0.5)],
+        };
+        let args = Args {
+            watchlists: vec![
+                Watchlist {
+                    name: "Malls".to_string(),
+                    symbols: vec!["SM".to_string(), "ALI".to_string()],
+                },
+                Watchlist {
+                    name: "Mine".to_string(),
+                    symbols: vec!["SM".to_string()],
+                },
+            ],
+            sort: SortKey::Change,
+            ..Args::default()
+        };
+
+        let table = table(&securities, &args);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "As of 2020-10-16 15:00:00");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].starts_with("ALI"));
+        assert_eq!(lines[3], "SM           1,010.00   +1.25%       1,234,500");
+    }
+}
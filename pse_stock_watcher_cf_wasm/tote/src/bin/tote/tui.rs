@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io;
+use std::panic;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Alignment, Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Sparkline, Table, TableState, Tabs};
+use ratatui::{Frame, Terminal};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tote::client::{Client, Indicator, Stock, Stocks};
+
+use crate::app::{App, Trend, EMA_PERIOD, RSI_PERIOD};
+use crate::args::Args;
+use crate::plain::{percent, thousands};
+
+/// How long to wait for a key before redrawing.
+const TICK: Duration = Duration::from_millis(250);
+
+const KEYS: &str = "←/→ tabs  ↑/↓ select  s sort  r reverse  u refresh  q quit";
+
+fn color(indicator: Indicator) -> Color {
+    match indicator {
+        Indicator::Up => Color::Green,
+        Indicator::Down => Color::Red,
+        Indicator::Unchanged => Color::Reset,
+    }
+}
+
+fn right(text: String) -> Cell<'static> {
+    Cell::from(Line::from(text).alignment(Alignment::Right))
+}
+
+fn quote_row(quote: &Stock) -> Row<'static> {
+    Row::new(vec![
+        Cell::from(quote.security_symbol.clone()),
+        Cell::from(quote.security_alias.clone()),
+        right(thousands(quote.last_traded_price, 2)),
+        right(percent(quote.perc_change_close)),
+        right(thousands(quote.total_volume as f64, 0)),
+    ])
+    .style(Style::default().fg(color(quote.indicator)))
+}
+
+/// Closes shifted so the sparkline shows movement rather than the distance
+/// from zero.
+fn sparkline_data(closes: &[f64]) -> Vec<u64> {
+    let min = closes.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = closes.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = (max - min).max(f64::EPSILON);
+    closes
+        .iter()
+        .map(|close| ((close - min) / range * 100.0) as u64 + 1)
+        .collect()
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let areas = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(5),
+        Constraint::Length(8),
+        Constraint::Length(1),
+    ])
+    .split(frame.size());
+
+    let tabs = Tabs::new(app.tab_names())
+        .select(app.tab)
+        .block(Block::default().borders(Borders::ALL).title(" tote "))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_widget(tabs, areas[0]);
+
+    let rows = app.rows();
+    let header = Row::new(vec![
+        Cell::from("SYMBOL"),
+        Cell::from("NAME"),
+        right("LAST".to_string()),
+        right("CHANGE".to_string()),
+        right("VOLUME".to_string()),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows.iter().map(|quote| quote_row(quote)),
+        [
+            Constraint::Length(8),
+            Constraint::Min(16),
+            Constraint::Length(12),
+            Constraint::Length(8),
+            Constraint::Length(15),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL))
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state =
+        TableState::default().with_selected(Some(app.selected).filter(|_| !rows.is_empty()));
+    frame.render_stateful_widget(table, areas[1], &mut state);
+
+    let block = Block::default().borders(Borders::ALL);
+    match app.selected_symbol() {
+        Some(symbol) => match app.trends.get(&symbol) {
+            Some(Ok(trend)) => {
+                let value = |value: Option<f64>, decimals| {
+                    value.map_or_else(|| "-".to_string(), |value| thousands(value, decimals))
+                };
+                let title = format!(
+                    " {}  EMA({}) {}  RSI({}) {} ",
+                    symbol,
+                    EMA_PERIOD,
+                    value(trend.ema, 2),
+                    RSI_PERIOD,
+                    value(trend.rsi, 1)
+                );
+                let data = sparkline_data(&trend.closes);
+                let sparkline = Sparkline::default()
+                    .block(block.title(title))
+                    .data(&data)
+                    .style(Style::default().fg(Color::Cyan));
+                frame.render_widget(sparkline, areas[2]);
+            }
+            Some(Err(error)) => frame.render_widget(
+                Paragraph::new(error.as_str()).block(block.title(format!(" {} ", symbol))),
+                areas[2],
+            ),
+            None => frame.render_widget(
+                Paragraph::new("Loading recent quotes…")
+                    .block(block.title(format!(" {} ", symbol))),
+                areas[2],
+            ),
+        },
+        None => frame.render_widget(block, areas[2]),
+    }
+
+    let footer = match &app.error {
+        Some(error) => Line::styled(
+            format!("refresh failed: {}", error),
+            Style::default().fg(Color::Red),
+        ),
+        None => {
+            let as_of = app
+                .securities
+                .as_ref()
+                .and_then(|securities| securities.as_of)
+                .map_or_else(
+                    || "loading…".to_string(),
+                    |as_of| format!("as of {}", as_of),
+                );
+            let order = if app.reversed { ", reversed" } else { "" };
+            Line::from(format!(
+                "{} | sort: {}{} | {}",
+                as_of,
+                app.sort.label(),
+                order,
+                KEYS
+            ))
+        }
+    };
+    frame.render_widget(Paragraph::new(footer), areas[3]);
+}
+
+/// Results of the requests the dashboard spawns.
+enum Fetched {
+    Securities(Result<Stocks, String>),
+    /// A trend loaded for the given refresh.
+    Trend(u64, String, Result<Trend, String>),
+}
+
+fn fetch_securities(client: &Client, sender: &UnboundedSender<Fetched>) {
+    let (client, sender) = (client.clone(), sender.clone());
+    tokio::spawn(async move {
+        let securities = client.securities().await.map_err(|error| error.to_string());
+        let _ = sender.send(Fetched::Securities(securities));
+    });
+}
+
+fn fetch_trend(client: &Client, sender: &UnboundedSender<Fetched>, refresh: u64, symbol: String) {
+    let (client, sender) = (client.clone(), sender.clone());
+    tokio::spawn(async move {
+        let trend = client
+            .history(&symbol)
+            .await
+            .map(|series| Trend::new(&series))
+            .map_err(|error| error.to_string());
+        let _ = sender.send(Fetched::Trend(refresh, symbol, trend));
+    });
+}
+
+/// Handles keys while requests run on their own tasks, so a slow PSE never
+/// holds up the screen.
+async fn dashboard(client: &Client, args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut app = App::new(args.watchlists.clone(), args.sort);
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut refreshed: Option<Instant> = None;
+    let mut refreshing = false;
+    let mut refresh = 0;
+    let mut loading: HashSet<String> = HashSet::new();
+    let mut idle = false;
+
+    loop {
+        let due = match refreshed {
+            Some(at) => at.elapsed() >= args.interval,
+            None => true,
+        };
+        if due && !refreshing {
+            fetch_securities(client, &sender);
+            refreshing = true;
+            refreshed = Some(Instant::now());
+        }
+
+        while let Ok(fetched) = receiver.try_recv() {
+            match fetched {
+                Fetched::Securities(Ok(securities)) => {
+                    app.update(securities);
+                    refresh += 1;
+                    loading.clear();
+                    refreshing = false;
+                }
+                Fetched::Securities(Err(error)) => {
+                    app.error = Some(error);
+                    refreshing = false;
+                }
+                // Trends loaded before the latest refresh are dropped.
+                Fetched::Trend(loaded, symbol, trend) if loaded == refresh => {
+                    loading.remove(&symbol);
+                    app.trends.insert(symbol, trend);
+                }
+                Fetched::Trend(..) => {}
+            }
+        }
+
+        // Recent quotes are only fetched once the selection settles, so
+        // scrolling through the table does not start a request per row.
+        if idle {
+            if let Some(symbol) = app.selected_symbol() {
+                if !app.trends.contains_key(&symbol) && loading.insert(symbol.clone()) {
+                    fetch_trend(client, &sender, refresh, symbol);
+                }
+            }
+        }
+
+        terminal.draw(|frame| draw(frame, &app))?;
+
+        idle = !event::poll(TICK)?;
+        if idle {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Tab | KeyCode::Right => app.next_tab(),
+            KeyCode::BackTab | KeyCode::Left => app.previous_tab(),
+            KeyCode::Down | KeyCode::Char('j') => app.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => app.select_previous(),
+            KeyCode::Char('s') => app.cycle_sort(),
+            KeyCode::Char('r') => app.reverse(),
+            KeyCode::Char('u') => refreshed = None,
+            KeyCode::Char(digit @ '1'..='9') => app.select_tab(digit as usize - '1' as usize),
+            _ => {}
+        }
+    }
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+/// Runs the dashboard on the alternate screen, restoring the terminal
+/// however it ends, panics included.
+pub async fn run(client: &Client, args: &Args) -> Result<(), Box<dyn Error>> {
+    let report = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        report(info);
+    }));
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = dashboard(client, args).await;
+    restore_terminal()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use tote::client::Stocks;
+
+    #[test]
+    fn scales_sparkline_to_range() {
+        assert_eq!(sparkline_data(&[10.0, 15.0, 20.0]), vec![1, 51, 101]);
+        assert_eq!(sparkline_data(&[5.0, 5.0]), vec![1, 1]);
+    }
+
+    #[test]
+    fn draws_quotes_and_trend() {
+        let mut app = App::default();
+        app.update(Stocks {
+            as_of: None,
+            stocks: vec![Stock {
+                security_symbol: "SM".to_string(),
+                security_alias: "SM Investments Corporation".to_string(),
+                last_traded_price: 1010.0,
+                perc_change_close: 1.25,
+                total_volume: 1_234_500,
+                indicator: Indicator::Up,
+            }],
+        });
+        app.trends.insert(
+            "SM".to_string(),
+            Ok(Trend {
+                closes: vec![1000.0, 1010.0],
+                ema: Some(1005.0),
+                rsi: None,
+            }),
+        );
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("SM Investments Corporation"));
+        assert!(screen.contains("1,234,500"));
+        assert!(screen.contains("EMA(9) 1,005.00  RSI(14) -"));
+        assert!(screen.contains("sort: change"));
+    }
+}
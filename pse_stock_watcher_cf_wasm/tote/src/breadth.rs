@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use crate::data_traits::{Next, Period, Reset};
+use crate::errors::CommonError;
+use crate::indicators::ExponentialMovingAverage as Ema;
+
+/// A single security's end of day quote taken from an archived market snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityQuote {
+    pub symbol: String,
+    pub sector: Option<String>,
+    pub close: f64,
+    pub percent_change: f64,
+}
+
+/// Every security traded on a given day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketSnapshot {
+    pub date: NaiveDate,
+    pub quotes: Vec<SecurityQuote>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdvanceDecline {
+    pub advances: usize,
+    pub declines: usize,
+    pub unchanged: usize,
+}
+
+impl AdvanceDecline {
+    pub fn from_quotes<'a, I>(quotes: I) -> Self
+    where
+        I: IntoIterator<Item = &'a SecurityQuote>,
+    {
+        let mut item = Self::default();
+        for quote in quotes {
+            if quote.percent_change > 0.0 {
+                item.advances += 1;
+            } else if quote.percent_change < 0.0 {
+                item.declines += 1;
+            } else {
+                item.unchanged += 1;
+            }
+        }
+        item
+    }
+
+    pub fn net(&self) -> f64 {
+        self.advances as f64 - self.declines as f64
+    }
+
+    pub fn total(&self) -> usize {
+        self.advances + self.declines + self.unchanged
+    }
+
+    /// Percentage of securities that closed higher, `0.0` when nothing traded.
+    pub fn percent_advancing(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => 100.0 * self.advances as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreadthPoint {
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewHighsLows {
+    pub date: NaiveDate,
+    pub new_highs: Vec<String>,
+    pub new_lows: Vec<String>,
+}
+
+fn sorted(snapshots: &[MarketSnapshot]) -> Vec<&MarketSnapshot> {
+    let mut items: Vec<&MarketSnapshot> = snapshots.iter().collect();
+    items.sort_by_key(|snapshot| snapshot.date);
+    items
+}
+
+pub fn advance_decline(snapshot: &MarketSnapshot) -> AdvanceDecline {
+    AdvanceDecline::from_quotes(&snapshot.quotes)
+}
+
+/// Cumulative sum of daily net advances, oldest snapshot first.
+pub fn advance_decline_line(snapshots: &[MarketSnapshot]) -> Vec<BreadthPoint> {
+    let mut total = 0.0;
+    sorted(snapshots)
+        .into_iter()
+        .map(|snapshot| {
+            total += advance_decline(snapshot).net();
+            BreadthPoint {
+                date: snapshot.date,
+                value: total,
+            }
+        })
+        .collect()
+}
+
+pub fn mcclellan_oscillator(snapshots: &[MarketSnapshot]) -> Vec<BreadthPoint> {
+    let mut oscillator = McClellanOscillator::default();
+    sorted(snapshots)
+        .into_iter()
+        .map(|snapshot| BreadthPoint {
+            date: snapshot.date,
+            value: oscillator.next(&advance_decline(snapshot)),
+        })
+        .collect()
+}
+
+/// Securities whose close exceeds the highest (or undercuts the lowest) close
+/// of the previous `lookback` snapshots. A security needs a full lookback
+/// window of history before it can register a new high or low.
+pub fn new_highs_lows(
+    snapshots: &[MarketSnapshot],
+    lookback: usize,
+) -> Result<Vec<NewHighsLows>, CommonError> {
+    if lookback == 0 {
+        return Err(CommonError::InvalidArgument);
+    }
+
+    let mut history: HashMap<&str, Vec<f64>> = HashMap::new();
+    let mut result = Vec::with_capacity(snapshots.len());
+
+    for snapshot in sorted(snapshots) {
+        let mut item = NewHighsLows {
+            date: snapshot.date,
+            new_highs: Vec::new(),
+            new_lows: Vec::new(),
+        };
+
+        for quote in snapshot.quotes.iter() {
+            let closes = history.entry(quote.symbol.as_str()).or_default();
+            if closes.len() >= lookback {
+                let window = &closes[closes.len() - lookback..];
+                let high = window.iter().cloned().fold(f64::MIN, f64::max);
+                let low = window.iter().cloned().fold(f64::MAX, f64::min);
+                if quote.close > high {
+                    item.new_highs.push(quote.symbol.clone());
+                } else if quote.close < low {
+                    item.new_lows.push(quote.symbol.clone());
+                }
+            }
+            closes.push(quote.close);
+        }
+
+        item.new_highs.sort();
+        item.new_lows.sort();
+        result.push(item);
+    }
+
+    Ok(result)
+}
+
+/// Advance/decline counts grouped by sector. Quotes without a sector are
+/// grouped under `"Unclassified"`.
+pub fn sector_breadth(snapshot: &MarketSnapshot) -> BTreeMap<String, AdvanceDecline> {
+    let mut sectors: BTreeMap<String, Vec<&SecurityQuote>> = BTreeMap::new();
+    for quote in snapshot.quotes.iter() {
+        let sector = quote
+            .sector
+            .clone()
+            .unwrap_or_else(|| "Unclassified".to_string());
+        sectors.entry(sector).or_default().push(quote);
+    }
+
+    sectors
+        .into_iter()
+        .map(|(sector, quotes)| (sector, AdvanceDecline::from_quotes(quotes)))
+        .collect()
+}
+
+/// Difference between a fast and a slow EMA of daily net advances.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct McClellanOscillator {
+    fast_ema_indicator: Ema,
+    slow_ema_indicator: Ema,
+}
+
+impl McClellanOscillator {
+    pub fn new(fast_period: usize, slow_period: usize) -> Result<Self, CommonError> {
+        if fast_period == 0 || fast_period >= slow_period {
+            return Err(CommonError::InvalidArgument);
+        }
+
+        Ok(Self {
+            fast_ema_indicator: Ema::new(fast_period)?,
+            slow_ema_indicator: Ema::new(slow_period)?,
+        })
+    }
+}
+
+impl Next<f64> for McClellanOscillator {
+    type Output = f64;
+
+    fn next(&mut self, input: f64) -> Self::Output {
+        self.fast_ema_indicator.next(input) - self.slow_ema_indicator.next(input)
+    }
+}
+
+impl Next<&AdvanceDecline> for McClellanOscillator {
+    type Output = f64;
+
+    fn next(&mut self, input: &AdvanceDecline) -> Self::Output {
+        self.next(input.net())
+    }
+}
+
+impl Reset for McClellanOscillator {
+    fn reset(&mut self) {
+        self.fast_ema_indicator.reset();
+        self.slow_ema_indicator.reset();
+    }
+}
+
+impl Default for McClellanOscillator {
+    fn default() -> Self {
+        Self::new(19, 39).unwrap()
+    }
+}
+
+impl fmt::Display for McClellanOscillator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MCO({}, {})",
+            self.fast_ema_indicator.period(),
+            self.slow_ema_indicator.period()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol: &str, sector: Option<&str>, close: f64, percent_change: f64) -> SecurityQuote {
+        SecurityQuote {
+            symbol: symbol.to_string(),
+            sector: sector.map(|s| s.to_string()),
+            close,
+            percent_change,
+        }
+    }
+
+    fn snapshot(day: u32, quotes: Vec<SecurityQuote>) -> MarketSnapshot {
+        MarketSnapshot {
+            date: NaiveDate::from_ymd_opt(2020, 10, day).unwrap(),
+            quotes,
+        }
+    }
+
+    #[test]
+    fn advance_decline_line_accumulates_in_date_order() {
+        let snapshots = vec![
+            snapshot(
+                13,
+                vec![quote("SM", None, 1.0, -1.0), quote("ALI", None, 1.0, -2.0)],
+            ),
+            snapshot(
+                12,
+                vec![quote("SM", None, 1.0, 1.0), quote("ALI", None, 1.0, 0.0)],
+            ),
+        ];
+
+        let line = advance_decline_line(&snapshots);
+        assert_eq!(line[0].date, NaiveDate::from_ymd_opt(2020, 10, 12).unwrap());
+        assert_eq!(line[0].value, 1.0);
+        assert_eq!(line[1].value, -1.0);
+    }
+
+    #[test]
+    fn mcclellan_oscillator_starts_at_zero() {
+        let snapshots = vec![
+            snapshot(12, vec![quote("SM", None, 1.0, 1.0)]),
+            snapshot(13, vec![quote("SM", None, 1.0, -1.0)]),
+        ];
+
+        let oscillator = mcclellan_oscillator(&snapshots);
+        assert_eq!(oscillator[0].value, 0.0);
+        assert!(oscillator[1].value < 0.0);
+        assert!(McClellanOscillator::new(39, 19).is_err());
+    }
+
+    #[test]
+    fn new_highs_lows_require_full_lookback() {
+        let snapshots = vec![
+            snapshot(
+                12,
+                vec![quote("SM", None, 10.0, 0.0), quote("ALI", None, 5.0, 0.0)],
+            ),
+            snapshot(
+                13,
+                vec![quote("SM", None, 11.0, 1.0), quote("ALI", None, 4.0, -1.0)],
+            ),
+            snapshot(
+                14,
+                vec![quote("SM", None, 12.0, 1.0), quote("ALI", None, 4.5, 1.0)],
+            ),
+        ];
+
+        let result = new_highs_lows(&snapshots, 2).unwrap();
+        assert!(result[1].new_highs.is_empty());
+        assert_eq!(result[2].new_highs, vec!["SM".to_string()]);
+        assert!(result[2].new_lows.is_empty());
+        assert!(new_highs_lows(&snapshots, 0).is_err());
+    }
+
+    #[test]
+    fn sector_breadth_groups_quotes() {
+        let item = snapshot(
+            12,
+            vec![
+                quote("BDO", Some("Financials"), 1.0, 1.0),
+                quote("BPI", Some("Financials"), 1.0, -1.0),
+                quote("XYZ", None, 1.0, 0.0),
+            ],
+        );
+
+        let sectors = sector_breadth(&item);
+        assert_eq!(sectors["Financials"].advances, 1);
+        assert_eq!(sectors["Financials"].declines, 1);
+        assert_eq!(sectors["Unclassified"].unchanged, 1);
+        assert_eq!(sectors["Financials"].percent_advancing(), 50.0);
+    }
+}
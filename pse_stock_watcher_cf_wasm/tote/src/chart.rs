@@ -0,0 +1,719 @@
+//! Candlestick charts of a [`BarSeries`] with indicator overlays and
+//! sub-panels, rendered to SVG or PNG.
+//!
+//! Both renderers draw the same list of shapes. PNG text uses a built-in
+//! bitmap font, so rendering needs no system fonts. SVG text is left to the
+//! viewer's monospace font.
+
+mod font;
+mod png;
+mod svg;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::data_traits::{Close, High, Low, Next, Open, Volume};
+use crate::errors::ChartError;
+use crate::indicators::{BollingerBands, ExponentialMovingAverage, RelativeStrengthIndex};
+use crate::BarSeries;
+
+pub const DEFAULT_WIDTH: u32 = 960;
+pub const DEFAULT_HEIGHT: u32 = 640;
+pub const MIN_WIDTH: u32 = 240;
+pub const MIN_HEIGHT: u32 = 160;
+
+const MARGIN_LEFT: f64 = 8.0;
+const MARGIN_RIGHT: f64 = 64.0;
+const MARGIN_TOP: f64 = 22.0;
+const MARGIN_BOTTOM: f64 = 20.0;
+const PANEL_GAP: f64 = 10.0;
+/// Height of the price panel relative to each sub-panel.
+const MAIN_PANEL_WEIGHT: f64 = 3.0;
+
+const BACKGROUND: Color = Color(255, 255, 255);
+const BORDER: Color = Color(200, 200, 200);
+const GRID: Color = Color(235, 235, 235);
+const TEXT: Color = Color(80, 80, 80);
+const UP: Color = Color(38, 166, 154);
+const DOWN: Color = Color(239, 83, 80);
+const BANDS: Color = Color(120, 144, 156);
+const RSI: Color = Color(126, 87, 194);
+const EMA_COLORS: [Color; 4] = [
+    Color(33, 150, 243),
+    Color(255, 152, 0),
+    Color(233, 30, 99),
+    Color(0, 150, 136),
+];
+
+/// Indicators drawn over the candles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlay {
+    Ema(usize),
+    Bollinger { period: usize, multiplier: f64 },
+}
+
+/// Indicators drawn in their own panel below the candles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Volume,
+    Rsi(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    Png,
+}
+
+impl Format {
+    /// The format named by the extension of `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ChartError> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+
+        match extension.to_ascii_lowercase().as_str() {
+            "svg" => Ok(Self::Svg),
+            "png" => Ok(Self::Png),
+            _ => Err(ChartError::UnsupportedFormat(extension.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Color(pub u8, pub u8, pub u8);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    Start,
+    Middle,
+}
+
+/// What the renderers draw, in order. Text is placed by the top of its
+/// glyphs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Color,
+    },
+    Line {
+        points: Vec<(f64, f64)>,
+        color: Color,
+        width: f64,
+    },
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        color: Color,
+        anchor: Anchor,
+    },
+}
+
+/// Maps values onto the vertical pixels of a panel.
+#[derive(Debug, Clone, Copy)]
+struct Scale {
+    min: f64,
+    max: f64,
+    top: f64,
+    height: f64,
+}
+
+impl Scale {
+    fn new(min: f64, max: f64, top: f64, height: f64) -> Self {
+        let (min, max) = if max > min {
+            (min, max)
+        } else {
+            (min - 1.0, max + 1.0)
+        };
+        Self {
+            min,
+            max,
+            top,
+            height,
+        }
+    }
+
+    /// A scale fitting `values` with some room above and below.
+    fn padded(values: impl Iterator<Item = f64>, top: f64, height: f64) -> Self {
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+        let padding = (max - min) * 0.05;
+        Self::new(min - padding, max + padding, top, height)
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        self.top + (self.max - value) / (self.max - self.min) * self.height
+    }
+
+    /// Round values between `min` and `max`, about `count` of them.
+    fn ticks(&self, count: usize) -> (f64, Vec<f64>) {
+        let step = nice_step((self.max - self.min) / count as f64);
+        let first = (self.min / step).ceil() as i64;
+        let last = (self.max / step).floor() as i64;
+        let ticks = (first..=last).map(|index| index as f64 * step).collect();
+        (step, ticks)
+    }
+}
+
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction < 1.5 {
+        1.0
+    } else if fraction < 3.5 {
+        2.0
+    } else if fraction < 7.5 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+fn price_label(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).clamp(0.0, 4.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+fn volume_label(value: f64) -> String {
+    let (value, suffix) = if value >= 1e9 {
+        (value / 1e9, "B")
+    } else if value >= 1e6 {
+        (value / 1e6, "M")
+    } else if value >= 1e3 {
+        (value / 1e3, "K")
+    } else {
+        return format!("{:.0}", value);
+    };
+    let digits = format!("{:.1}", value);
+    format!("{}{}", digits.trim_end_matches(".0"), suffix)
+}
+
+/// Values of an indicator per bar, `None` while it warms up.
+type Values = Vec<Option<f64>>;
+
+fn warmed_up(values: Vec<f64>, period: usize) -> Values {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| Some(value).filter(|_| index + 1 >= period))
+        .collect()
+}
+
+struct Line {
+    label: String,
+    values: Values,
+    color: Color,
+}
+
+/// A candlestick chart of a bar series, built up with `with_*` calls.
+#[derive(Debug, Clone)]
+pub struct Chart<'a> {
+    series: &'a BarSeries,
+    width: u32,
+    height: u32,
+    overlays: Vec<Overlay>,
+    panels: Vec<Panel>,
+}
+
+impl<'a> Chart<'a> {
+    pub fn new(series: &'a BarSeries) -> Self {
+        Self {
+            series,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            overlays: Vec::new(),
+            panels: Vec::new(),
+        }
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_overlay(mut self, overlay: Overlay) -> Self {
+        self.overlays.push(overlay);
+        self
+    }
+
+    pub fn with_panel(mut self, panel: Panel) -> Self {
+        self.panels.push(panel);
+        self
+    }
+
+    pub fn to_svg(&self) -> Result<String, ChartError> {
+        Ok(svg::render(&self.shapes()?, self.width, self.height))
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, ChartError> {
+        png::render(&self.shapes()?, self.width, self.height)
+    }
+
+    pub fn render(&self, format: Format) -> Result<Vec<u8>, ChartError> {
+        match format {
+            Format::Svg => self.to_svg().map(String::into_bytes),
+            Format::Png => self.to_png(),
+        }
+    }
+
+    /// Writes the chart to `path`, in the format named by its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ChartError> {
+        let data = self.render(Format::from_path(&path)?)?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    fn overlay_lines(&self) -> Result<Vec<Line>, ChartError> {
+        let closes = self.series.closes();
+        let mut lines = Vec::new();
+        let mut emas = EMA_COLORS.iter().cycle();
+
+        for overlay in &self.overlays {
+            match *overlay {
+                Overlay::Ema(period) => {
+                    let mut ema = ExponentialMovingAverage::new(period)?;
+                    let values = closes.iter().map(|close| ema.next(*close)).collect();
+                    lines.push(Line {
+                        label: ema.to_string(),
+                        values: warmed_up(values, period),
+                        color: *emas.next().unwrap_or(&EMA_COLORS[0]),
+                    });
+                }
+                Overlay::Bollinger { period, multiplier } => {
+                    let mut bands = BollingerBands::new(period, multiplier)?;
+                    let outputs: Vec<_> = closes.iter().map(|close| bands.next(*close)).collect();
+                    let band =
+                        |pick: fn(&_) -> f64| warmed_up(outputs.iter().map(pick).collect(), period);
+                    lines.push(Line {
+                        label: bands.to_string(),
+                        values: band(|output| output.upper),
+                        color: BANDS,
+                    });
+                    lines.push(Line {
+                        label: String::new(),
+                        values: band(|output| output.average),
+                        color: BANDS,
+                    });
+                    lines.push(Line {
+                        label: String::new(),
+                        values: band(|output| output.lower),
+                        color: BANDS,
+                    });
+                }
+            }
+        }
+        Ok(lines)
+    }
+
+    pub(crate) fn shapes(&self) -> Result<Vec<Shape>, ChartError> {
+        if self.series.is_empty() {
+            return Err(ChartError::Empty);
+        }
+        if self.width < MIN_WIDTH || self.height < MIN_HEIGHT {
+            return Err(ChartError::InvalidSize {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        let overlays = self.overlay_lines()?;
+        let bars = self.series.bars();
+        let (width, height) = (f64::from(self.width), f64::from(self.height));
+        let right = width - MARGIN_RIGHT;
+        let plot_width = right - MARGIN_LEFT;
+        let slot = plot_width / bars.len() as f64;
+        let body_width = (slot * 0.7).max(1.0);
+        let x = |index: usize| MARGIN_LEFT + slot * (index as f64 + 0.5);
+
+        let sub_panels = self.panels.len() as f64;
+        let panels_height = height - MARGIN_TOP - MARGIN_BOTTOM - PANEL_GAP * sub_panels;
+        let unit = panels_height / (MAIN_PANEL_WEIGHT + sub_panels);
+
+        let mut shapes = vec![Shape::Rect {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+            fill: BACKGROUND,
+        }];
+
+        // Title and legend.
+        let mut legend = vec![(self.series.symbol().to_uppercase(), TEXT)];
+        let dates = self.series.dates();
+        legend.push((format!("{} - {}", dates[0], dates[dates.len() - 1]), TEXT));
+        legend.extend(
+            overlays
+                .iter()
+                .filter(|line| !line.label.is_empty())
+                .map(|line| (line.label.clone(), line.color)),
+        );
+        let mut legend_x = MARGIN_LEFT;
+        for (text, color) in legend {
+            let advance = f64::from(font::text_width(&text)) + 12.0;
+            shapes.push(Shape::Text {
+                x: legend_x,
+                y: 8.0,
+                text,
+                color,
+                anchor: Anchor::Start,
+            });
+            legend_x += advance;
+        }
+
+        // Price panel.
+        let main = Scale::padded(
+            bars.iter()
+                .flat_map(|bar| vec![bar.high(), bar.low()])
+                .chain(
+                    overlays
+                        .iter()
+                        .flat_map(|line| line.values.iter().flatten().copied()),
+                ),
+            MARGIN_TOP,
+            unit * MAIN_PANEL_WEIGHT,
+        );
+        let (step, ticks) = main.ticks(5);
+        grid(&mut shapes, &main, right, &ticks, |value| {
+            price_label(value, step)
+        });
+        for (index, bar) in bars.iter().enumerate() {
+            let color = if bar.close() >= bar.open() { UP } else { DOWN };
+            shapes.push(Shape::Line {
+                points: vec![
+                    (x(index), main.y(bar.high())),
+                    (x(index), main.y(bar.low())),
+                ],
+                color,
+                width: 1.0,
+            });
+            let top = main.y(bar.open().max(bar.close()));
+            let bottom = main.y(bar.open().min(bar.close()));
+            shapes.push(Shape::Rect {
+                x: x(index) - body_width / 2.0,
+                y: top,
+                width: body_width,
+                height: (bottom - top).max(1.0),
+                fill: color,
+            });
+        }
+        for line in &overlays {
+            polylines(&mut shapes, &line.values, &x, &main, line.color);
+        }
+        frame(&mut shapes, &main, right);
+
+        // Sub-panels.
+        let mut top = main.top + main.height + PANEL_GAP;
+        for panel in &self.panels {
+            match *panel {
+                Panel::Volume => {
+                    let max = bars.iter().map(|bar| bar.volume()).fold(0.0, f64::max);
+                    let scale = Scale::new(0.0, max * 1.1, top, unit);
+                    let (_, ticks) = scale.ticks(2);
+                    grid(&mut shapes, &scale, right, &ticks, volume_label);
+                    for (index, bar) in bars.iter().enumerate() {
+                        let y = scale.y(bar.volume());
+                        shapes.push(Shape::Rect {
+                            x: x(index) - body_width / 2.0,
+                            y,
+                            width: body_width,
+                            height: scale.y(0.0) - y,
+                            fill: if bar.close() >= bar.open() { UP } else { DOWN },
+                        });
+                    }
+                    label(&mut shapes, &scale, "VOLUME".to_string());
+                    frame(&mut shapes, &scale, right);
+                }
+                Panel::Rsi(period) => {
+                    let mut rsi = RelativeStrengthIndex::new(period)?;
+                    let values = bars.iter().map(|bar| rsi.next(bar)).collect();
+                    let scale = Scale::new(0.0, 100.0, top, unit);
+                    grid(&mut shapes, &scale, right, &[30.0, 70.0], |value| {
+                        format!("{:.0}", value)
+                    });
+                    polylines(&mut shapes, &warmed_up(values, period), &x, &scale, RSI);
+                    label(&mut shapes, &scale, rsi.to_string());
+                    frame(&mut shapes, &scale, right);
+                }
+            }
+            top += unit + PANEL_GAP;
+        }
+
+        // Dates along the bottom, kept inside the plot.
+        let count = ((plot_width / 110.0) as usize).clamp(1, bars.len());
+        for label_index in 0..count {
+            let index = match count {
+                1 => bars.len() - 1,
+                _ => label_index * (bars.len() - 1) / (count - 1),
+            };
+            let text = dates[index].to_string();
+            let half = f64::from(font::text_width(&text)) / 2.0;
+            shapes.push(Shape::Text {
+                x: x(index).clamp(MARGIN_LEFT + half, right - half),
+                y: height - MARGIN_BOTTOM + 6.0,
+                text,
+                color: TEXT,
+                anchor: Anchor::Middle,
+            });
+        }
+
+        Ok(shapes)
+    }
+}
+
+fn grid(
+    shapes: &mut Vec<Shape>,
+    scale: &Scale,
+    right: f64,
+    ticks: &[f64],
+    label: impl Fn(f64) -> String,
+) {
+    for tick in ticks {
+        let y = scale.y(*tick);
+        shapes.push(Shape::Line {
+            points: vec![(MARGIN_LEFT, y), (right, y)],
+            color: GRID,
+            width: 1.0,
+        });
+        shapes.push(Shape::Text {
+            x: right + 6.0,
+            y: y - f64::from(font::GLYPH_HEIGHT) / 2.0,
+            text: label(*tick),
+            color: TEXT,
+            anchor: Anchor::Start,
+        });
+    }
+}
+
+fn frame(shapes: &mut Vec<Shape>, scale: &Scale, right: f64) {
+    let (top, bottom) = (scale.top, scale.top + scale.height);
+    shapes.push(Shape::Line {
+        points: vec![
+            (MARGIN_LEFT, top),
+            (right, top),
+            (right, bottom),
+            (MARGIN_LEFT, bottom),
+            (MARGIN_LEFT, top),
+        ],
+        color: BORDER,
+        width: 1.0,
+    });
+}
+
+fn label(shapes: &mut Vec<Shape>, scale: &Scale, text: String) {
+    shapes.push(Shape::Text {
+        x: MARGIN_LEFT + 4.0,
+        y: scale.top + 4.0,
+        text,
+        color: TEXT,
+        anchor: Anchor::Start,
+    });
+}
+
+/// One polyline per unbroken run of values.
+fn polylines(
+    shapes: &mut Vec<Shape>,
+    values: &[Option<f64>],
+    x: &impl Fn(usize) -> f64,
+    scale: &Scale,
+    color: Color,
+) {
+    let mut points = Vec::new();
+    for (index, value) in values.iter().enumerate() {
+        match value {
+            Some(value) => points.push((x(index), scale.y(*value))),
+            None if points.is_empty() => {}
+            None => shapes.push(Shape::Line {
+                points: std::mem::take(&mut points),
+                color,
+                width: 1.5,
+            }),
+        }
+    }
+    if !points.is_empty() {
+        shapes.push(Shape::Line {
+            points,
+            color,
+            width: 1.5,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OHLCVData;
+    use chrono::{Duration, NaiveDate};
+
+    fn series(len: usize) -> BarSeries {
+        let start = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+        let mut series = BarSeries::new("SM");
+        let mut close = 900.0;
+        for day in 0..len {
+            let open = close;
+            close += (day as f64 * 0.7).sin() * 12.0;
+            let bar = OHLCVData::builder()
+                .open(open)
+                .high(open.max(close) + 4.0)
+                .low(open.min(close) - 4.0)
+                .close(close)
+                .volume(100_000.0 + (day % 7) as f64 * 25_000.0)
+                .build()
+                .unwrap();
+            series
+                .push(start + Duration::days(day as i64), bar)
+                .unwrap();
+        }
+        series
+    }
+
+    fn chart(series: &BarSeries) -> Chart<'_> {
+        Chart::new(series)
+            .with_overlay(Overlay::Ema(9))
+            .with_overlay(Overlay::Bollinger {
+                period: 20,
+                multiplier: 2.0,
+            })
+            .with_panel(Panel::Volume)
+            .with_panel(Panel::Rsi(14))
+    }
+
+    #[test]
+    fn lays_out_candles_and_panels() {
+        let series = series(60);
+        let shapes = chart(&series).shapes().unwrap();
+
+        let texts: Vec<&str> = shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                Shape::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        for expected in &[
+            "SM",
+            "EMA(9)",
+            "BB(20, 2)",
+            "VOLUME",
+            "RSI(14)",
+            "2020-01-02",
+        ] {
+            assert!(texts.contains(expected), "missing {}", expected);
+        }
+        assert!(texts.contains(&"2020-03-01"));
+
+        // Every shape stays on the canvas.
+        let inside = |x: f64, y: f64| {
+            (0.0..=f64::from(DEFAULT_WIDTH)).contains(&x)
+                && (0.0..=f64::from(DEFAULT_HEIGHT)).contains(&y)
+        };
+        for shape in &shapes {
+            match shape {
+                Shape::Rect { x, y, .. } | Shape::Text { x, y, .. } => assert!(inside(*x, *y)),
+                Shape::Line { points, .. } => {
+                    assert!(points.iter().all(|(x, y)| inside(*x, *y)))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skips_indicator_warm_up() {
+        assert_eq!(
+            warmed_up(vec![1.0, 2.0, 3.0], 2),
+            vec![None, Some(2.0), Some(3.0)]
+        );
+
+        let mut shapes = Vec::new();
+        let scale = Scale::new(0.0, 10.0, 0.0, 100.0);
+        let values = [None, Some(1.0), Some(2.0), None, Some(3.0)];
+        polylines(&mut shapes, &values, &|index| index as f64, &scale, RSI);
+        assert_eq!(shapes.len(), 2);
+    }
+
+    #[test]
+    fn picks_round_ticks() {
+        let scale = Scale::new(812.3, 1004.9, 0.0, 300.0);
+        let (step, ticks) = scale.ticks(5);
+        assert_eq!(step, 50.0);
+        assert_eq!(ticks, vec![850.0, 900.0, 950.0, 1000.0]);
+        assert_eq!(price_label(2.5, 0.5), "2.5");
+        assert_eq!(price_label(900.0, 50.0), "900");
+        assert_eq!(volume_label(1_250_000.0), "1.2M");
+        assert_eq!(volume_label(200_000.0), "200K");
+    }
+
+    #[test]
+    fn renders_svg_and_png() {
+        let series = series(40);
+        let chart = chart(&series).with_size(480, 320);
+
+        let svg = chart.to_svg().unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"width="480" height="320""#));
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let png = chart.to_png().unwrap();
+        let pixmap = tiny_skia::Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (480, 320));
+        assert!(pixmap
+            .pixels()
+            .iter()
+            .any(|pixel| (pixel.red(), pixel.green(), pixel.blue()) == (UP.0, UP.1, UP.2)));
+
+        let path = std::env::temp_dir().join(format!("tote-chart-{}.png", std::process::id()));
+        chart.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), png);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_unchartable_input() {
+        let empty = BarSeries::new("SM");
+        assert!(matches!(
+            Chart::new(&empty).to_svg(),
+            Err(ChartError::Empty)
+        ));
+
+        let series = series(5);
+        assert!(matches!(
+            Chart::new(&series).with_size(100, 100).to_png(),
+            Err(ChartError::InvalidSize { .. })
+        ));
+        assert!(matches!(
+            Chart::new(&series).with_overlay(Overlay::Ema(0)).to_svg(),
+            Err(ChartError::Indicator(_))
+        ));
+        assert!(matches!(
+            Format::from_path("chart.jpg"),
+            Err(ChartError::UnsupportedFormat(extension)) if extension == "jpg"
+        ));
+        assert_eq!(Format::from_path("CHART.PNG").unwrap(), Format::Png);
+    }
+
+    #[test]
+    fn draws_every_label_character() {
+        let unknown = font::glyph('~');
+        for c in "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,-:()%/".chars() {
+            assert_ne!(font::glyph(c), unknown, "no glyph for {}", c);
+        }
+        assert_eq!(font::text_width("RSI(14)"), 41);
+        assert_eq!(font::pixels("-").count(), 5);
+    }
+}
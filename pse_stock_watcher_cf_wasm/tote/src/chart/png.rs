@@ -0,0 +1,100 @@
+use tiny_skia::{Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use super::font;
+use super::{Anchor, Color, Shape};
+use crate::errors::ChartError;
+
+fn paint(color: Color, anti_alias: bool) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(color.0, color.1, color.2, 255);
+    paint.anti_alias = anti_alias;
+    paint
+}
+
+fn fill_rect(pixmap: &mut Pixmap, x: f64, y: f64, width: f64, height: f64, color: Color) {
+    // Rects snap to whole pixels so candle bodies stay sharp.
+    let (left, top) = (x.round(), y.round());
+    let right = (x + width).round().max(left + 1.0);
+    let bottom = (y + height).round().max(top + 1.0);
+    if let Some(rect) = Rect::from_ltrb(left as f32, top as f32, right as f32, bottom as f32) {
+        pixmap.fill_rect(rect, &paint(color, false), Transform::identity(), None);
+    }
+}
+
+fn stroke_line(pixmap: &mut Pixmap, points: &[(f64, f64)], color: Color, width: f64) {
+    let mut builder = PathBuilder::new();
+    for (index, (x, y)) in points.iter().enumerate() {
+        // Centering on a pixel keeps one pixel wide lines from blurring
+        // across two.
+        let (x, y) = (x.floor() as f32 + 0.5, y.floor() as f32 + 0.5);
+        if index == 0 {
+            builder.move_to(x, y);
+        } else {
+            builder.line_to(x, y);
+        }
+    }
+
+    if let Some(path) = builder.finish() {
+        let stroke = Stroke {
+            width: width as f32,
+            ..Stroke::default()
+        };
+        pixmap.stroke_path(
+            &path,
+            &paint(color, true),
+            &stroke,
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+fn draw_text(pixmap: &mut Pixmap, x: f64, y: f64, text: &str, color: Color, anchor: Anchor) {
+    let width = f64::from(font::text_width(text));
+    let left = match anchor {
+        Anchor::Start => x,
+        Anchor::Middle => x - width / 2.0,
+    }
+    .round() as i64;
+    let top = y.round() as i64;
+
+    let paint = paint(color, false);
+    for (column, row) in font::pixels(text) {
+        let (px, py) = (left + i64::from(column), top + i64::from(row));
+        if let Some(rect) = Rect::from_xywh(px as f32, py as f32, 1.0, 1.0) {
+            pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+        }
+    }
+}
+
+pub(super) fn render(shapes: &[Shape], width: u32, height: u32) -> Result<Vec<u8>, ChartError> {
+    let mut pixmap = Pixmap::new(width, height).ok_or(ChartError::InvalidSize { width, height })?;
+
+    for shape in shapes {
+        match shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+            } => fill_rect(&mut pixmap, *x, *y, *width, *height, *fill),
+            Shape::Line {
+                points,
+                color,
+                width,
+            } => stroke_line(&mut pixmap, points, *color, *width),
+            Shape::Text {
+                x,
+                y,
+                text,
+                color,
+                anchor,
+            } => draw_text(&mut pixmap, *x, *y, text, *color, *anchor),
+        }
+    }
+
+    pixmap
+        .encode_png()
+        .map_err(|error| ChartError::Png(error.to_string()))
+}
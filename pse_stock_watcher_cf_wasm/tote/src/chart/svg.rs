@@ -0,0 +1,87 @@
+use std::fmt::{self, Write};
+
+use super::font::GLYPH_HEIGHT;
+use super::{Anchor, Shape};
+
+/// Font size at which a monospace font has glyphs about as large as the
+/// bitmap font's.
+const FONT_SIZE: u32 = 10;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn write_shapes(out: &mut String, shapes: &[Shape], width: u32, height: u32) -> fmt::Result {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="{size}">"#,
+        w = width,
+        h = height,
+        size = FONT_SIZE,
+    )?;
+
+    for shape in shapes {
+        match shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+            } => writeln!(
+                out,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                x, y, width, height, fill
+            )?,
+            Shape::Line {
+                points,
+                color,
+                width,
+            } => {
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                    .collect();
+                writeln!(
+                    out,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                    points.join(" "),
+                    color,
+                    width
+                )?;
+            }
+            Shape::Text {
+                x,
+                y,
+                text,
+                color,
+                anchor,
+            } => {
+                let anchor = match anchor {
+                    Anchor::Start => "start",
+                    Anchor::Middle => "middle",
+                };
+                // SVG places text by its baseline.
+                writeln!(
+                    out,
+                    r#"<text x="{:.1}" y="{:.1}" fill="{}" text-anchor="{}">{}</text>"#,
+                    x,
+                    y + f64::from(GLYPH_HEIGHT),
+                    color,
+                    anchor,
+                    escape(text)
+                )?;
+            }
+        }
+    }
+
+    writeln!(out, "</svg>")
+}
+
+pub(super) fn render(shapes: &[Shape], width: u32, height: u32) -> String {
+    let mut out = String::new();
+    write_shapes(&mut out, shapes, width, height).expect("writing to a String cannot fail");
+    out
+}
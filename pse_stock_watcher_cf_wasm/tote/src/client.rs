@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::Deserialize;
+
+use crate::errors::ClientError;
+use crate::stock::{de_number, de_whole};
+pub use crate::stock::{Indicator, Stock, Stocks};
+use crate::{BarSeries, OHLCVData};
+
+/// Where PSE's `stockMarket` pages live unless told otherwise.
+pub const DEFAULT_BASE_URL: &str = "https://www.pse.com.ph/stockMarket";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Formats PSE has used for `tradingDate` in quote history records.
+const TRADING_DATE_FORMATS: [&str; 4] = [
+    "%b %d, %Y %H:%M:%S",
+    "%b %d, %Y %I:%M:%S %p",
+    "%Y-%m-%d %H:%M:%S",
+    "%m/%d/%Y %H:%M:%S",
+];
+
+fn construct_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::REFERER,
+        HeaderValue::from_static("http://www.pse.com.ph/stockMarket/home.html"),
+    );
+    headers.insert(
+        "X-Requested-With",
+        HeaderValue::from_static("XMLHttpRequest"),
+    );
+    headers
+}
+
+/// A match from `findSecurityOrCompany`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityMatch {
+    #[serde(deserialize_with = "de_whole")]
+    pub security_id: u64,
+    #[serde(deserialize_with = "de_whole")]
+    pub company_id: u64,
+    #[serde(rename = "securitySymbol")]
+    pub symbol: String,
+    #[serde(rename = "securityName")]
+    pub name: String,
+}
+
+/// A daily bar from `getRecentSecurityQuoteData`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DailyQuote {
+    #[serde(rename = "tradingDate", deserialize_with = "de_trading_date")]
+    pub date: NaiveDate,
+    #[serde(rename = "sqOpen", deserialize_with = "de_number")]
+    pub open: f64,
+    #[serde(rename = "sqHigh", deserialize_with = "de_number")]
+    pub high: f64,
+    #[serde(rename = "sqLow", deserialize_with = "de_number")]
+    pub low: f64,
+    #[serde(rename = "sqClose", deserialize_with = "de_number")]
+    pub close: f64,
+    #[serde(rename = "totalVolume", deserialize_with = "de_number")]
+    pub volume: f64,
+}
+
+#[derive(Deserialize)]
+struct Records<T> {
+    #[serde(default = "Vec::new")]
+    records: Vec<T>,
+}
+
+fn de_trading_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let value = value.trim();
+    TRADING_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|instant| instant.date())
+        .ok_or_else(|| de::Error::custom(format!("unrecognized trading date `{}`", value)))
+}
+
+/// A typed client for the PSE `method=` calls.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl Client {
+    pub fn new() -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .default_headers(construct_headers())
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: TIMEOUT,
+        })
+    }
+
+    /// Points the client somewhere else, e.g. a proxy or a local mock.
+    pub fn with_base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// How long a call may take, from connecting to reading the body.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        page: &str,
+        method: &str,
+        args: &[(&str, String)],
+    ) -> Result<T, ClientError> {
+        let url = format!("{}/{}", self.base_url, page);
+        let response = self
+            .http
+            .get(&url)
+            .query(&[("method", method), ("ajax", "true")])
+            .query(args)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ClientError::Status {
+                status: status.as_u16(),
+                url: response.url().to_string(),
+            });
+        }
+
+        let body = response.text().await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    pub async fn securities(&self) -> Result<Stocks, ClientError> {
+        self.get("home.html", "getSecuritiesAndIndicesForPublic", &[])
+            .await
+    }
+
+    pub async fn find_security(
+        &self,
+        query: &str,
+        start: usize,
+        limit: usize,
+    ) -> Result<Vec<SecurityMatch>, ClientError> {
+        let args = [
+            ("start", start.to_string()),
+            ("limit", limit.to_string()),
+            ("query", query.to_string()),
+        ];
+        let matches: Records<SecurityMatch> = self
+            .get("home.html", "findSecurityOrCompany", &args)
+            .await?;
+        Ok(matches.records)
+    }
+
+    /// Recent daily bars of a security, oldest first.
+    pub async fn recent_quotes(&self, security_id: u64) -> Result<Vec<DailyQuote>, ClientError> {
+        let args = [("security", security_id.to_string())];
+        let mut quotes: Records<DailyQuote> = self
+            .get(
+                "companyInfoHistoricalData.html",
+                "getRecentSecurityQuoteData",
+                &args,
+            )
+            .await?;
+        quotes.records.sort_by_key(|quote| quote.date);
+        quotes.records.dedup_by_key(|quote| quote.date);
+        Ok(quotes.records)
+    }
+
+    /// Recent daily bars of `symbol`, looked up by exact symbol.
+    pub async fn history(&self, symbol: &str) -> Result<BarSeries, ClientError> {
+        let security = self
+            .find_security(symbol, 0, 10)
+            .await?
+            .into_iter()
+            .find(|security| security.symbol.eq_ignore_ascii_case(symbol))
+            .ok_or_else(|| ClientError::UnknownSymbol(symbol.to_string()))?;
+
+        let mut series = BarSeries::new(security.symbol.as_str());
+        for quote in self.recent_quotes(security.security_id).await? {
+            let bar = OHLCVData::builder()
+                .open(quote.open)
+                .high(quote.high)
+                .low(quote.low)
+                .close(quote.close)
+                .volume(quote.volume)
+                .build()?;
+            series.push(quote.date, bar)?;
+        }
+        Ok(series)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_matches_and_daily_quotes() {
+        let matches: Records<SecurityMatch> = serde_json::from_str(
+            r#"{"count":1,"records":[{"securityStatus":"O","securityId":"146","companyId":112,"securitySymbol":"SMPH","securityName":"SM Prime Holdings, Inc."}]}"#,
+        )
+        .unwrap();
+        assert_eq!(matches.records[0].security_id, 146);
+        assert_eq!(matches.records[0].company_id, 112);
+
+        let quotes: Records<DailyQuote> = serde_json::from_str(
+            r#"{"count":1,"records":[{"tradingDate":"Oct 15, 2020 12:00:00 AM","sqOpen":"1,001.00","sqHigh":1005,"sqLow":"990.00","sqClose":"998.00","totalVolume":"401,330"}]}"#,
+        )
+        .unwrap();
+        let quote = &quotes.records[0];
+        assert_eq!(quote.date, NaiveDate::from_ymd_opt(2020, 10, 15).unwrap());
+        assert_eq!(
+            (quote.open, quote.high, quote.volume),
+            (1001.0, 1005.0, 401_330.0)
+        );
+
+        let empty: Records<DailyQuote> = serde_json::from_str(r#"{"count":0}"#).unwrap();
+        assert!(empty.records.is_empty());
+        assert!(serde_json::from_str::<Records<DailyQuote>>(
+            r#"{"records":[{"tradingDate":"someday","sqOpen":1,"sqHigh":1,"sqLow":1,"sqClose":1,"totalVolume":1}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn trims_base_url() {
+        let client = Client::new()
+            .unwrap()
+            .with_base_url("http://127.0.0.1:9000/");
+        assert_eq!(client.base_url(), "http://127.0.0.1:9000");
+    }
+}
@@ -0,0 +1,442 @@
+//! Corporate actions and the back-adjustment of daily bars around them.
+//!
+//! Actions are kept one per line, as comma separated fields:
+//!
+//! ```text
+//! # symbol,ex_date,kind,value[,price]
+//! SM,2020-06-15,split,2
+//! BDO,2020-05-04,stock_dividend,0.1
+//! JFC,2020-04-20,cash_dividend,1.2
+//! ALI,2020-01-10,rights,0.25,28.5
+//! ```
+//!
+//! Blank lines and lines starting with `#` are skipped. `value` is the new
+//! shares per held share for a `split` (`0.1` for a 1-for-10 reverse split),
+//! the dividend rate for a `stock_dividend`, the amount per share for a
+//! `cash_dividend` and the offered shares per held share for `rights`, whose
+//! subscription `price` follows.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::data_traits::{Close, High, Low, Open, Volume};
+use crate::errors::CorporateActionError;
+use crate::{BarSeries, OHLCVData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionKind {
+    Split { ratio: f64 },
+    StockDividend { rate: f64 },
+    CashDividend { amount: f64 },
+    RightsOffering { ratio: f64, price: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorporateAction {
+    pub symbol: String,
+    /// First day the shares trade without the entitlement.
+    pub ex_date: NaiveDate,
+    pub kind: ActionKind,
+}
+
+/// How the bars before an action's ex-date are scaled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub ex_date: NaiveDate,
+    pub price_factor: f64,
+    pub volume_factor: f64,
+}
+
+impl ActionKind {
+    fn validate(self) -> Result<Self, CorporateActionError> {
+        let valid = match self {
+            ActionKind::Split { ratio } => ratio > 0.0,
+            ActionKind::StockDividend { rate } => rate > 0.0,
+            ActionKind::CashDividend { amount } => amount > 0.0,
+            ActionKind::RightsOffering { ratio, price } => ratio > 0.0 && price >= 0.0,
+        };
+        if valid && self.values().iter().all(|value| value.is_finite()) {
+            Ok(self)
+        } else {
+            Err(CorporateActionError::InvalidAction(self.to_string()))
+        }
+    }
+
+    fn values(self) -> Vec<f64> {
+        match self {
+            ActionKind::Split { ratio } => vec![ratio],
+            ActionKind::StockDividend { rate } => vec![rate],
+            ActionKind::CashDividend { amount } => vec![amount],
+            ActionKind::RightsOffering { ratio, price } => vec![ratio, price],
+        }
+    }
+
+    /// Whether the action changes the number of shares held.
+    fn changes_shares(self) -> bool {
+        matches!(
+            self,
+            ActionKind::Split { .. } | ActionKind::StockDividend { .. }
+        )
+    }
+
+    /// Factors for the bars before the ex-date, given the last close before
+    /// it. Splits and stock dividends change the share count, so volume is
+    /// scaled too; cash dividends and rights only move the price.
+    pub fn factors(self, prev_close: f64) -> Result<(f64, f64), CorporateActionError> {
+        let factors = match self.validate()? {
+            ActionKind::Split { ratio } => (1.0 / ratio, ratio),
+            ActionKind::StockDividend { rate } => (1.0 / (1.0 + rate), 1.0 + rate),
+            ActionKind::CashDividend { amount } => ((prev_close - amount) / prev_close, 1.0),
+            ActionKind::RightsOffering { ratio, price } => {
+                let ex_rights = (prev_close + ratio * price) / (1.0 + ratio);
+                (ex_rights / prev_close, 1.0)
+            }
+        };
+
+        if factors.0 > 0.0 && factors.0.is_finite() {
+            Ok(factors)
+        } else {
+            Err(CorporateActionError::InvalidAction(format!(
+                "{} against a close of {}",
+                self, prev_close
+            )))
+        }
+    }
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionKind::Split { ratio } => write!(f, "split,{}", ratio),
+            ActionKind::StockDividend { rate } => write!(f, "stock_dividend,{}", rate),
+            ActionKind::CashDividend { amount } => write!(f, "cash_dividend,{}", amount),
+            ActionKind::RightsOffering { ratio, price } => write!(f, "rights,{},{}", ratio, price),
+        }
+    }
+}
+
+impl fmt::Display for CorporateAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.symbol, self.ex_date, self.kind)
+    }
+}
+
+impl FromStr for CorporateAction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let number = |index: usize| -> Result<f64, String> {
+            let field = fields.get(index).ok_or("missing value")?;
+            field
+                .parse()
+                .map_err(|_| format!("invalid number `{}`", field))
+        };
+
+        let (symbol, ex_date, kind) = match fields.as_slice() {
+            [symbol, ex_date, kind, ..] if !symbol.is_empty() => (symbol, ex_date, kind),
+            _ => return Err("expected symbol,ex_date,kind,value".to_string()),
+        };
+        let ex_date = NaiveDate::parse_from_str(ex_date, "%Y-%m-%d")
+            .map_err(|_| format!("invalid ex-date `{}`", ex_date))?;
+        let (kind, len) = match *kind {
+            "split" => (ActionKind::Split { ratio: number(3)? }, 4),
+            "stock_dividend" => (ActionKind::StockDividend { rate: number(3)? }, 4),
+            "cash_dividend" => (ActionKind::CashDividend { amount: number(3)? }, 4),
+            "rights" => (
+                ActionKind::RightsOffering {
+                    ratio: number(3)?,
+                    price: number(4)?,
+                },
+                5,
+            ),
+            other => return Err(format!("unknown action `{}`", other)),
+        };
+        if fields.len() > len {
+            return Err(format!("unexpected fields after `{}`", fields[len - 1]));
+        }
+
+        Ok(Self {
+            symbol: symbol.to_uppercase(),
+            ex_date,
+            kind: kind.validate().map_err(|err| err.to_string())?,
+        })
+    }
+}
+
+/// Parses a file in the format described in the [module docs](self).
+pub fn parse(data: &str) -> Result<Vec<CorporateAction>, CorporateActionError> {
+    data.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, text)| {
+            text.parse()
+                .map_err(|message| CorporateActionError::Parse { line, message })
+        })
+        .collect()
+}
+
+/// The adjustments `actions` call for in `series`, by ex-date. Only actions
+/// on the series' symbol with an ex-date after its first bar apply; several
+/// on one day combine.
+pub fn adjustments(
+    series: &BarSeries,
+    actions: &[CorporateAction],
+) -> Result<Vec<Adjustment>, CorporateActionError> {
+    let mut result: Vec<Adjustment> = Vec::new();
+    let mut actions: Vec<&CorporateAction> = actions
+        .iter()
+        .filter(|action| action.symbol.eq_ignore_ascii_case(series.symbol()))
+        .collect();
+    // Actions sharing an ex-date are priced against the close as adjusted by
+    // those before them, share count changes first.
+    actions.sort_by_key(|action| (action.ex_date, !action.kind.changes_shares()));
+
+    for action in actions {
+        let before = series
+            .dates()
+            .iter()
+            .filter(|date| **date < action.ex_date)
+            .count();
+        if before == 0 || before == series.len() {
+            continue;
+        }
+
+        let prev_close = series.bars()[before - 1].close();
+        match result.last_mut() {
+            Some(last) if last.ex_date == action.ex_date => {
+                let (price_factor, volume_factor) =
+                    action.kind.factors(prev_close * last.price_factor)?;
+                last.price_factor *= price_factor;
+                last.volume_factor *= volume_factor;
+            }
+            _ => {
+                let (price_factor, volume_factor) = action.kind.factors(prev_close)?;
+                result.push(Adjustment {
+                    ex_date: action.ex_date,
+                    price_factor,
+                    volume_factor,
+                })
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Back-adjusts `series` so prices before each action are comparable with
+/// those after it. The latest bars keep their traded prices.
+pub fn adjust(
+    series: &BarSeries,
+    actions: &[CorporateAction],
+) -> Result<BarSeries, CorporateActionError> {
+    let adjustments = adjustments(series, actions)?;
+    let mut adjusted = BarSeries::new(series.symbol());
+
+    for (date, bar) in series.iter() {
+        let (price, volume) = adjustments
+            .iter()
+            .filter(|adjustment| date < adjustment.ex_date)
+            .fold((1.0, 1.0), |(price, volume), adjustment| {
+                (
+                    price * adjustment.price_factor,
+                    volume * adjustment.volume_factor,
+                )
+            });
+
+        let bar = OHLCVData::builder()
+            .open(bar.open() * price)
+            .high(bar.high() * price)
+            .low(bar.low() * price)
+            .close(bar.close() * price)
+            .volume(bar.volume() * volume)
+            .build()?;
+        adjusted.push(date, bar)?;
+    }
+
+    Ok(adjusted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 6, day).unwrap()
+    }
+
+    fn bar(open: f64, high: f64, low: f64, close: f64, volume: f64) -> OHLCVData {
+        OHLCVData::builder()
+            .open(open)
+            .high(high)
+            .low(low)
+            .close(close)
+            .volume(volume)
+            .build()
+            .unwrap()
+    }
+
+    /// A 2-for-1 split takes effect on the 15th.
+    fn split_series() -> BarSeries {
+        let mut series = BarSeries::new("SM");
+        series
+            .push(date(10), bar(98.0, 101.0, 97.0, 100.0, 1000.0))
+            .unwrap();
+        series
+            .push(date(11), bar(100.0, 104.0, 99.0, 102.0, 1200.0))
+            .unwrap();
+        series
+            .push(date(15), bar(51.0, 52.0, 50.0, 51.0, 2600.0))
+            .unwrap();
+        series
+            .push(date(16), bar(51.0, 53.0, 50.5, 52.0, 2400.0))
+            .unwrap();
+        series
+    }
+
+    fn action(symbol: &str, day: u32, kind: ActionKind) -> CorporateAction {
+        CorporateAction {
+            symbol: symbol.to_string(),
+            ex_date: date(day),
+            kind,
+        }
+    }
+
+    fn returns(closes: &[f64]) -> Vec<f64> {
+        closes
+            .windows(2)
+            .map(|pair| pair[1] / pair[0] - 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_prices_continuous_across_a_split() {
+        let series = split_series();
+        let raw = returns(&series.closes());
+        assert!(raw[1] < -0.45, "the raw series halves at the split");
+
+        let split = action("SM", 15, ActionKind::Split { ratio: 2.0 });
+        let adjusted = adjust(&series, &[split]).unwrap();
+        assert_eq!(adjusted.closes(), vec![50.0, 51.0, 51.0, 52.0]);
+        assert_eq!(returns(&adjusted.closes())[1], 0.0);
+
+        let volumes: Vec<f64> = adjusted.bars().iter().map(|bar| bar.volume()).collect();
+        assert_eq!(volumes, vec![2000.0, 2400.0, 2600.0, 2400.0]);
+        assert_eq!(adjusted.bars()[0].high(), 50.5);
+        assert_eq!(adjusted.dates(), series.dates());
+    }
+
+    #[test]
+    fn adjusts_for_dividends_and_rights() {
+        let series = split_series();
+        let after = |kind| {
+            let adjusted = adjust(&series, &[action("SM", 11, kind)]).unwrap();
+            (adjusted.closes()[0], adjusted.bars()[0].volume())
+        };
+
+        // 10% stock dividend: 100 / 1.1.
+        let (close, volume) = after(ActionKind::StockDividend { rate: 0.1 });
+        assert!((close - 90.909_090).abs() < 1e-6);
+        assert!((volume - 1100.0).abs() < 1e-9);
+
+        // A 5.00 dividend against a close of 100 takes 5% off earlier prices.
+        let (close, volume) = after(ActionKind::CashDividend { amount: 5.0 });
+        assert!((close - 95.0).abs() < 1e-9);
+        assert_eq!(volume, 1000.0);
+
+        // One new share at 40 per four held: ex-rights price (400 + 40) / 5.
+        let (close, _) = after(ActionKind::RightsOffering {
+            ratio: 0.25,
+            price: 40.0,
+        });
+        assert!((close - 88.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn applies_only_matching_actions_within_the_series() {
+        let series = split_series();
+        let actions = vec![
+            action("sm", 15, ActionKind::Split { ratio: 2.0 }),
+            action("SM", 15, ActionKind::CashDividend { amount: 1.0 }),
+            action("ALI", 11, ActionKind::Split { ratio: 5.0 }),
+            action("SM", 10, ActionKind::Split { ratio: 3.0 }),
+            action("SM", 20, ActionKind::Split { ratio: 3.0 }),
+        ];
+
+        let found = adjustments(&series, &actions).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].ex_date, date(15));
+        assert!((found[0].price_factor - 0.5 * 50.0 / 51.0).abs() < 1e-12);
+        assert_eq!(found[0].volume_factor, 2.0);
+
+        assert_eq!(adjust(&series, &[]).unwrap(), series);
+    }
+
+    #[test]
+    fn prices_same_day_dividends_after_the_split() {
+        let series = split_series();
+        // Listed before the split, but paid on the split shares: 2.00 against
+        // a split-adjusted close of 51.
+        let actions = vec![
+            action("SM", 15, ActionKind::CashDividend { amount: 2.0 }),
+            action("SM", 15, ActionKind::Split { ratio: 2.0 }),
+        ];
+
+        let found = adjustments(&series, &actions).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!((found[0].price_factor - 0.5 * 49.0 / 51.0).abs() < 1e-12);
+        assert_eq!(found[0].volume_factor, 2.0);
+
+        let adjusted = adjust(&series, &actions).unwrap();
+        assert!((adjusted.closes()[1] - 49.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_impossible_actions() {
+        let series = split_series();
+        let dividend = action("SM", 15, ActionKind::CashDividend { amount: 102.0 });
+        assert!(matches!(
+            adjust(&series, &[dividend]),
+            Err(CorporateActionError::InvalidAction(_))
+        ));
+        let split = action("SM", 15, ActionKind::Split { ratio: 0.0 });
+        assert!(adjust(&series, &[split]).is_err());
+    }
+
+    #[test]
+    fn parses_action_files() {
+        let actions = parse(include_str!("../tests/fixtures/corporate_actions.csv")).unwrap();
+        assert_eq!(actions.len(), 5);
+        assert_eq!(
+            actions[0],
+            action("SM", 15, ActionKind::Split { ratio: 2.0 })
+        );
+        assert_eq!(
+            actions[4].kind,
+            ActionKind::RightsOffering {
+                ratio: 0.25,
+                price: 28.5
+            }
+        );
+        for action in &actions {
+            assert_eq!(parse(&action.to_string()).unwrap(), vec![action.clone()]);
+        }
+
+        for (line, bad) in &[
+            (1, "SM,2020-06-15,merger,2"),
+            (1, "SM,15/06/2020,split,2"),
+            (1, "SM,2020-06-15,split"),
+            (1, "SM,2020-06-15,split,-2"),
+            (1, "SM,2020-06-15,split,2,3"),
+            (2, "# comment\n,2020-06-15,split,2"),
+        ] {
+            match parse(bad) {
+                Err(CorporateActionError::Parse { line: found, .. }) => assert_eq!(found, *line),
+                other => panic!("{} parsed as {:?}", bad, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! Company disclosures and dividend declarations as listed on PSE EDGE.
+//!
+//! EDGE serves both as HTML tables, so the parsers here read the rows of a
+//! saved page: the disclosure search results, and a company's "Dividends and
+//! Rights" page.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::corporate_actions::{ActionKind, CorporateAction};
+use crate::errors::DisclosureError;
+
+const DATE_FORMAT: &str = "%b %d, %Y";
+const DATE_TIME_FORMAT: &str = "%b %d, %Y %I:%M %p";
+
+/// A row of the EDGE disclosure search results.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Disclosure {
+    pub company: String,
+    /// The kind of disclosure, e.g. `Declaration of Cash Dividends`.
+    pub template: String,
+    pub form_number: String,
+    pub announced_at: NaiveDateTime,
+    pub circular_number: Option<String>,
+    /// Id of the document, as passed to EDGE's `openPopup`.
+    pub edge_id: Option<String>,
+}
+
+impl Disclosure {
+    pub fn is_dividend_declaration(&self) -> bool {
+        self.template.to_ascii_lowercase().contains("dividend")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum DividendKind {
+    Cash,
+    Stock,
+    Property,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "unit", rename_all = "snake_case")
+)]
+pub enum DividendRate {
+    PerShare {
+        currency: String,
+        amount: f64,
+    },
+    Percent {
+        percent: f64,
+    },
+    /// Rates kept as EDGE lists them, such as `1:5` for property dividends.
+    Other {
+        text: String,
+    },
+}
+
+/// A row of a company's EDGE "Dividends and Rights" page.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Dividend {
+    pub symbol: String,
+    /// `COMMON` or `PREFERRED`, as EDGE lists it.
+    pub security_type: String,
+    pub kind: DividendKind,
+    pub rate: DividendRate,
+    /// `None` while EDGE lists it as `TBA`.
+    pub ex_date: Option<NaiveDate>,
+    pub record_date: NaiveDate,
+    /// `None` while EDGE lists it as `TBA`.
+    pub payment_date: Option<NaiveDate>,
+    pub circular_number: Option<String>,
+}
+
+impl Dividend {
+    /// The price adjustment this dividend calls for. Only cash dividends in
+    /// pesos and stock dividends on common shares move the traded price, and
+    /// only once their ex-date is set.
+    pub fn corporate_action(&self) -> Option<CorporateAction> {
+        if !self.security_type.eq_ignore_ascii_case("common") {
+            return None;
+        }
+        let ex_date = self.ex_date?;
+
+        let kind = match (self.kind, &self.rate) {
+            (DividendKind::Cash, DividendRate::PerShare { currency, amount })
+                if currency == "PHP" =>
+            {
+                ActionKind::CashDividend { amount: *amount }
+            }
+            (DividendKind::Stock, DividendRate::Percent { percent }) => ActionKind::StockDividend {
+                rate: percent / 100.0,
+            },
+            _ => return None,
+        };
+
+        Some(CorporateAction {
+            symbol: self.symbol.clone(),
+            ex_date,
+            kind,
+        })
+    }
+}
+
+/// Parses the disclosure search results, skipping rows that do not read as
+/// one.
+pub fn parse_disclosures(html: &str) -> Vec<Disclosure> {
+    disclosure_rows(html)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Each row of the disclosure search results, parsed on its own.
+pub fn disclosure_rows(html: &str) -> Vec<Result<Disclosure, DisclosureError>> {
+    table_rows(html)
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let row_error = |message: String| DisclosureError::Parse {
+                row: index + 1,
+                message,
+            };
+            match row.cells.as_slice() {
+                [company, template, form_number, announced_at, circular_number] => Ok(Disclosure {
+                    company: company.clone(),
+                    template: template.clone(),
+                    form_number: form_number.clone(),
+                    announced_at: NaiveDateTime::parse_from_str(announced_at, DATE_TIME_FORMAT)
+                        .map_err(|_| row_error(format!("invalid time `{}`", announced_at)))?,
+                    circular_number: optional(circular_number),
+                    edge_id: popup_id(&row.html),
+                }),
+                cells => Err(row_error(format!("expected 5 cells, got {}", cells.len()))),
+            }
+        })
+        .collect()
+}
+
+/// Parses the dividends of `symbol` from its "Dividends and Rights" page,
+/// skipping rows that do not read as one.
+pub fn parse_dividends(symbol: &str, html: &str) -> Vec<Dividend> {
+    dividend_rows(symbol, html)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Each row of a "Dividends and Rights" page, parsed on its own.
+pub fn dividend_rows(symbol: &str, html: &str) -> Vec<Result<Dividend, DisclosureError>> {
+    table_rows(html)
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let row_error = |message: String| DisclosureError::Parse {
+                row: index + 1,
+                message,
+            };
+            let date = |value: &str| {
+                NaiveDate::parse_from_str(value, DATE_FORMAT)
+                    .map_err(|_| row_error(format!("invalid date `{}`", value)))
+            };
+            let scheduled = |value: &str| match optional(value) {
+                Some(value) if !value.eq_ignore_ascii_case("tba") => date(&value).map(Some),
+                _ => Ok(None),
+            };
+
+            match row.cells.as_slice() {
+                [security_type, kind, rate, ex_date, record_date, payment_date, circular_number] => {
+                    Ok(Dividend {
+                        symbol: symbol.to_uppercase(),
+                        security_type: security_type.to_uppercase(),
+                        kind: dividend_kind(kind).map_err(row_error)?,
+                        rate: dividend_rate(rate).map_err(row_error)?,
+                        ex_date: scheduled(ex_date)?,
+                        record_date: date(record_date)?,
+                        payment_date: scheduled(payment_date)?,
+                        circular_number: optional(circular_number),
+                    })
+                }
+                cells => Err(row_error(format!("expected 7 cells, got {}", cells.len()))),
+            }
+        })
+        .collect()
+}
+
+/// Reads dividends saved as JSON.
+#[cfg(feature = "serde")]
+pub fn dividends_from_json(json: &str) -> Result<Vec<Dividend>, DisclosureError> {
+    Ok(serde_json::from_str(json)?)
+}
+
+fn dividend_kind(value: &str) -> Result<DividendKind, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "cash" => Ok(DividendKind::Cash),
+        "stock" => Ok(DividendKind::Stock),
+        "property" => Ok(DividendKind::Property),
+        _ => Err(format!("unknown dividend type `{}`", value)),
+    }
+}
+
+/// Reads rates such as `Php2.76`, `PHP 1.3750`, `US$0.05` or `10%`. Any
+/// other rate is kept as [`DividendRate::Other`].
+fn dividend_rate(value: &str) -> Result<DividendRate, String> {
+    if value.is_empty() {
+        return Err("missing dividend rate".to_string());
+    }
+    Ok(known_rate(value).unwrap_or_else(|| DividendRate::Other {
+        text: value.to_string(),
+    }))
+}
+
+fn known_rate(value: &str) -> Option<DividendRate> {
+    if let Some(percent) = value.strip_suffix('%') {
+        let percent = percent.trim().parse().ok()?;
+        return Some(DividendRate::Percent { percent });
+    }
+
+    let start = value.find(|c: char| c.is_ascii_digit() || c == '.')?;
+    let (currency, amount) = value.split_at(start);
+    let currency = match currency.trim().to_ascii_uppercase().as_str() {
+        "P" | "PHP" | "₱" => "PHP",
+        "US$" | "USD" | "$" => "USD",
+        _ => return None,
+    };
+    let amount = amount.replace(',', "").parse().ok()?;
+    Some(DividendRate::PerShare {
+        currency: currency.to_string(),
+        amount,
+    })
+}
+
+fn optional(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty())
+}
+
+fn popup_id(html: &str) -> Option<String> {
+    let start = html.find("openPopup('")? + "openPopup('".len();
+    let len = html[start..].find('\'')?;
+    optional(&html[start..start + len])
+}
+
+/// A `<tr>` with `<td>` cells, their text unescaped and trimmed.
+struct TableRow {
+    html: String,
+    cells: Vec<String>,
+}
+
+/// The data rows of every table in `html`. Header rows, made of `<th>`
+/// cells, are skipped.
+fn table_rows(html: &str) -> Vec<TableRow> {
+    elements(html, "tr")
+        .into_iter()
+        .map(|row| TableRow {
+            cells: elements(row, "td").into_iter().map(text).collect(),
+            html: row.to_string(),
+        })
+        .filter(|row| !row.cells.is_empty())
+        .collect()
+}
+
+/// The inner HTML of each `<tag>` element. Tags are matched without regard
+/// to case, and elements of the same tag must not nest.
+fn elements<'a>(html: &'a str, tag: &str) -> Vec<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let (open, close) = (format!("<{}", tag), format!("</{}>", tag));
+    let mut result = Vec::new();
+    let mut position = 0;
+
+    while let Some(found) = lower[position..].find(&open) {
+        let start = position + found;
+        let after = lower[start + open.len()..].chars().next();
+        if !matches!(
+            after,
+            Some('>') | Some(' ') | Some('\t') | Some('\n') | Some('\r')
+        ) {
+            position = start + open.len();
+            continue;
+        }
+        let content = match lower[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        let end = lower[content..]
+            .find(&close)
+            .map_or(html.len(), |end| content + end);
+        result.push(&html[content..end]);
+        position = (end + close.len()).min(html.len());
+    }
+    result
+}
+
+/// The text of an HTML fragment, with tags dropped, common entities decoded
+/// and whitespace collapsed.
+fn text(html: &str) -> String {
+    let mut stripped = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => stripped.push(c),
+            _ => (),
+        }
+    }
+
+    let decoded = stripped
+        .replace("&nbsp;", " ")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISCLOSURES: &str = include_str!("../tests/fixtures/edge_disclosures.html");
+    const DIVIDENDS: &str = include_str!("../tests/fixtures/edge_dividends.html");
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_disclosure_listings() {
+        let disclosures = parse_disclosures(DISCLOSURES);
+        assert_eq!(disclosures.len(), 3);
+
+        let first = &disclosures[0];
+        assert_eq!(first.company, "SM Investments Corporation");
+        assert_eq!(first.template, "Declaration of Cash Dividends");
+        assert_eq!(first.form_number, "6-1");
+        assert_eq!(
+            first.announced_at,
+            date(4, 22).and_hms_opt(17, 42, 0).unwrap()
+        );
+        assert_eq!(first.circular_number.as_deref(), Some("C02791-2020"));
+        assert_eq!(
+            first.edge_id.as_deref(),
+            Some("a1f0c2d3e4b5968778695a4b3c2d1e0f")
+        );
+
+        let meeting = &disclosures[1];
+        assert_eq!(
+            meeting.template,
+            "Results of Annual or Special Stockholders' Meeting"
+        );
+        assert_eq!(meeting.circular_number, None);
+
+        let declarations: Vec<&str> = disclosures
+            .iter()
+            .filter(|disclosure| disclosure.is_dividend_declaration())
+            .map(|disclosure| disclosure.company.as_str())
+            .collect();
+        assert_eq!(
+            declarations,
+            vec!["SM Investments Corporation", "BDO Unibank, Inc."]
+        );
+    }
+
+    #[test]
+    fn parses_dividend_pages() {
+        let dividends = parse_dividends("sm", DIVIDENDS);
+        assert_eq!(dividends.len(), 4);
+
+        let cash = &dividends[0];
+        assert_eq!(cash.symbol, "SM");
+        assert_eq!(cash.kind, DividendKind::Cash);
+        assert_eq!(
+            cash.rate,
+            DividendRate::PerShare {
+                currency: "PHP".to_string(),
+                amount: 2.76
+            }
+        );
+        assert_eq!(cash.ex_date, Some(date(5, 6)));
+        assert_eq!(cash.record_date, date(5, 11));
+        assert_eq!(cash.payment_date, Some(date(5, 20)));
+
+        let stock = &dividends[1];
+        assert_eq!(stock.rate, DividendRate::Percent { percent: 10.0 });
+        assert_eq!(stock.payment_date, None);
+
+        let preferred = &dividends[2];
+        assert_eq!(preferred.security_type, "PREFERRED");
+        assert_eq!(preferred.circular_number, None);
+
+        let property = &dividends[3];
+        assert_eq!(property.kind, DividendKind::Property);
+        assert_eq!(
+            property.rate,
+            DividendRate::Other {
+                text: "1:5".to_string()
+            }
+        );
+        assert_eq!(property.ex_date, None);
+        assert_eq!(property.corporate_action(), None);
+    }
+
+    #[test]
+    fn maps_dividends_to_corporate_actions() {
+        let actions: Vec<CorporateAction> = parse_dividends("SM", DIVIDENDS)
+            .iter()
+            .filter_map(Dividend::corporate_action)
+            .collect();
+
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].kind, ActionKind::CashDividend { amount: 2.76 });
+        assert_eq!(actions[0].ex_date, date(5, 6));
+        assert_eq!(actions[1].kind, ActionKind::StockDividend { rate: 0.1 });
+    }
+
+    #[test]
+    fn reports_malformed_rows() {
+        let row = |cells: &str| format!("<table><tr>{}</tr></table>", cells);
+        for (html, message) in &[
+            (row("<td>COMMON</td><td>Cash</td>"), "expected 7 cells"),
+            (
+                row("<td>COMMON</td><td>Rights</td><td>1%</td><td>May 06, 2020</td><td>May 11, 2020</td><td></td><td></td>"),
+                "unknown dividend type",
+            ),
+            (
+                row("<td>COMMON</td><td>Cash</td><td></td><td>May 06, 2020</td><td>May 11, 2020</td><td></td><td></td>"),
+                "missing dividend rate",
+            ),
+            (
+                row("<td>COMMON</td><td>Cash</td><td>P1</td><td>2020-05-06</td><td>May 11, 2020</td><td></td><td></td>"),
+                "invalid date",
+            ),
+        ] {
+            match dividend_rows("SM", html).as_slice() {
+                [Err(err @ DisclosureError::Parse { row: 1, .. })] => {
+                    assert!(err.to_string().contains(message), "{}", err)
+                }
+                other => panic!("{} parsed as {:?}", html, other),
+            }
+            assert!(parse_dividends("SM", html).is_empty());
+        }
+
+        for (html, message) in &[
+            (row("<td>SM</td><td>Notice</td>"), "expected 5 cells"),
+            (
+                row("<td>SM</td><td>Notice</td><td>4-31</td><td>Apr 20, 2020</td><td></td>"),
+                "invalid time",
+            ),
+        ] {
+            match disclosure_rows(html).as_slice() {
+                [Err(err @ DisclosureError::Parse { row: 1, .. })] => {
+                    assert!(err.to_string().contains(message), "{}", err)
+                }
+                other => panic!("{} parsed as {:?}", html, other),
+            }
+            assert!(parse_disclosures(html).is_empty());
+        }
+
+        assert!(parse_disclosures("<p>Maintenance</p>").is_empty());
+    }
+
+    #[test]
+    fn extracts_table_text() {
+        assert_eq!(
+            text(" <a href='#'>A &amp; B</a>\n  <b>Co.</b> "),
+            "A & B Co."
+        );
+        let html = "<TR><TD>1</TD><td class='x'>2</td></TR><thead><tr><th>h</th></tr></thead>";
+        let rows = table_rows(html);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].cells, vec!["1", "2"]);
+        assert!(elements("<tbody><tr><td>x</td></tr></tbody>", "t").is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reads_saved_json() {
+        let dividends =
+            dividends_from_json(include_str!("../tests/fixtures/dividends.json")).unwrap();
+        assert_eq!(dividends, parse_dividends("SM", DIVIDENDS)[..2]);
+
+        let json = serde_json::to_string(&dividends).unwrap();
+        assert!(json.contains(r#""rate":{"unit":"per_share","currency":"PHP","amount":2.76}"#));
+        assert!(matches!(
+            dividends_from_json("{"),
+            Err(DisclosureError::Json(_))
+        ));
+    }
+}
@@ -3,6 +3,8 @@ use thiserror::Error;
 pub enum CommonError {
     #[error("expected a valid argument")]
     InvalidArgument,
+    #[error("not enough data points")]
+    InsufficientData,
 }
 
 #[derive(Error, Debug)]
@@ -11,6 +13,67 @@ pub enum OHLCVDataError {
     Invalid,
     #[error("expected a complete OHLCV input data")]
     Incomplete,
+    #[error("expected OHLCV data in ascending date order")]
+    OutOfOrder,
     #[error("unknown data error")]
     Unknown,
 }
+
+#[derive(Error, Debug)]
+pub enum CorporateActionError {
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("invalid corporate action `{0}`")]
+    InvalidAction(String),
+    #[error(transparent)]
+    Data(#[from] OHLCVDataError),
+}
+
+#[derive(Error, Debug)]
+pub enum DisclosureError {
+    #[error("row {row}: {message}")]
+    Parse { row: usize, message: String },
+    #[error("malformed saved records: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "client")]
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("PSE responded with status {status} for {url}")]
+    Status { status: u16, url: String },
+    #[error("malformed PSE response: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error("no security with symbol `{0}`")]
+    UnknownSymbol(String),
+    #[error(transparent)]
+    Data(#[from] OHLCVDataError),
+}
+
+#[cfg(feature = "chart")]
+#[derive(Error, Debug)]
+pub enum ChartError {
+    #[error("no bars to chart")]
+    Empty,
+    #[error("a {width}x{height} chart is too small")]
+    InvalidSize { width: u32, height: u32 },
+    #[error("unsupported chart format `{0}`, expected svg or png")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    Indicator(#[from] CommonError),
+    #[error("failed to encode PNG: {0}")]
+    Png(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+    #[error("malformed snapshot: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
@@ -1,2 +1,6 @@
+mod bollinger_bands;
 mod exponential_moving_average;
+mod relative_strength_index;
+pub use self::bollinger_bands::{BollingerBands, BollingerBandsOutput};
 pub use self::exponential_moving_average::ExponentialMovingAverage;
+pub use self::relative_strength_index::RelativeStrengthIndex;
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::data_traits::{Close, Next, Period, Reset};
+use crate::errors::CommonError;
+use crate::numeric::{Decimal, Numeric};
+
+/// Bands `multiplier` population standard deviations above and below the
+/// simple moving average of the last `period` closes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BollingerBands<N = f64> {
+    period: usize,
+    multiplier: N,
+    window: VecDeque<N>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BollingerBandsOutput<N = f64> {
+    pub average: N,
+    pub upper: N,
+    pub lower: N,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, multiplier: f64) -> Result<Self, CommonError> {
+        Self::with_period(period, multiplier)
+    }
+}
+
+impl<N: Numeric> BollingerBands<N> {
+    pub fn with_period(period: usize, multiplier: f64) -> Result<Self, CommonError> {
+        if period == 0 || multiplier.is_nan() || multiplier <= 0.0 {
+            return Err(CommonError::InvalidArgument);
+        }
+
+        Ok(Self {
+            period,
+            multiplier: N::from_f64(multiplier),
+            window: VecDeque::with_capacity(period),
+        })
+    }
+
+    /// Whether a full period of closes has been seen. Until then the bands
+    /// only cover the closes so far.
+    pub fn is_ready(&self) -> bool {
+        self.window.len() == self.period
+    }
+
+    fn update(&mut self, input: N) -> BollingerBandsOutput<N> {
+        if self.window.len() == self.period {
+            self.window.pop_front();
+        }
+        self.window.push_back(input);
+
+        let count = N::from_usize(self.window.len());
+        let sum = self
+            .window
+            .iter()
+            .fold(N::zero(), |sum, value| sum + *value);
+        let average = sum / count;
+        let variance = self
+            .window
+            .iter()
+            .map(|value| (*value - average) * (*value - average))
+            .fold(N::zero(), |sum, value| sum + value)
+            / count;
+        let deviation = self.multiplier * N::from_f64(variance.to_f64().sqrt());
+
+        BollingerBandsOutput {
+            average,
+            upper: average + deviation,
+            lower: average - deviation,
+        }
+    }
+}
+
+macro_rules! impl_next {
+    ($($t:ty),*) => {$(
+        impl Next<$t> for BollingerBands<$t> {
+            type Output = BollingerBandsOutput<$t>;
+
+            fn next(&mut self, input: $t) -> Self::Output {
+                self.update(input)
+            }
+        }
+    )*};
+}
+
+impl_next!(f32, f64, Decimal);
+
+impl<N: Numeric, T: Close<N>> Next<&T> for BollingerBands<N> {
+    type Output = BollingerBandsOutput<N>;
+
+    fn next(&mut self, input: &T) -> Self::Output {
+        self.update(input.close())
+    }
+}
+
+impl<N> Period for BollingerBands<N> {
+    fn period(&self) -> usize {
+        self.period
+    }
+}
+
+impl<N: Numeric> Reset for BollingerBands<N> {
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+impl Default for BollingerBands {
+    fn default() -> Self {
+        Self::new(20, 2.0).unwrap()
+    }
+}
+
+impl<N: Numeric> fmt::Display for BollingerBands<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BB({}, {})", self.period, self.multiplier.to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bollinger_bands_track_the_deviation() {
+        let mut bands = BollingerBands::<f64>::with_period(3, 2.0).unwrap();
+        bands.next(1.0);
+        bands.next(2.0);
+        assert!(!bands.is_ready());
+        let output = bands.next(3.0);
+        assert!(bands.is_ready());
+        assert_eq!(output.average, 2.0);
+        assert!((output.upper - (2.0 + 2.0 * (2.0f64 / 3.0).sqrt())).abs() < 1e-12);
+        bands.next(3.0);
+        let flat = bands.next(3.0);
+        assert_eq!((flat.lower, flat.upper), (3.0, 3.0));
+        assert!(BollingerBands::new(20, 0.0).is_err());
+    }
+
+    #[test]
+    fn bollinger_bands_over_decimals() {
+        let mut bands = BollingerBands::<Decimal>::with_period(2, 2.0).unwrap();
+        bands.next(Decimal::from(10));
+        let output = bands.next(Decimal::from(12));
+        assert_eq!(output.average, Decimal::from(11));
+        assert_eq!(output.upper, Decimal::from(13));
+        assert_eq!(output.lower, Decimal::from(9));
+    }
+}
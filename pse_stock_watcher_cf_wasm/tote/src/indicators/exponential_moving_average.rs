@@ -1,61 +1,79 @@
 use std::fmt;
 
-use crate::errors::CommonError;
 use crate::data_traits::{Close, Next, Period, Reset};
+use crate::errors::CommonError;
+use crate::numeric::{Decimal, Numeric};
 
 #[derive(Debug, Clone)]
-pub struct ExponentialMovingAverage {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExponentialMovingAverage<N = f64> {
     period: usize,
-    k: f64,
-    current: f64,
+    k: N,
+    current: N,
     is_new: bool,
 }
 
 impl ExponentialMovingAverage {
     pub fn new(period: usize) -> Result<Self, CommonError> {
+        Self::with_period(period)
+    }
+}
+
+impl<N: Numeric> ExponentialMovingAverage<N> {
+    pub fn with_period(period: usize) -> Result<Self, CommonError> {
         match period {
             0 => Err(CommonError::InvalidArgument),
             _ => Ok(Self {
                 period,
-                k: 2.0 / (period + 1) as f64,
-                current: 0.0,
+                k: N::from_usize(2) / N::from_usize(period + 1),
+                current: N::zero(),
                 is_new: true,
             }),
         }
     }
+
+    pub(crate) fn update(&mut self, input: N) -> N {
+        if self.is_new {
+            self.is_new = false;
+            self.current = input;
+        } else {
+            self.current = self.k * input + (N::one() - self.k) * self.current;
+        }
+        self.current
+    }
 }
 
-impl Period for ExponentialMovingAverage {
+impl<N> Period for ExponentialMovingAverage<N> {
     fn period(&self) -> usize {
         self.period
     }
 }
 
-impl Next<f64> for ExponentialMovingAverage {
-    type Output = f64;
+macro_rules! impl_next {
+    ($($t:ty),*) => {$(
+        impl Next<$t> for ExponentialMovingAverage<$t> {
+            type Output = $t;
 
-    fn next(&mut self, input: f64) -> Self::Output {
-        if self.is_new {
-            self.is_new = false;
-            self.current = input;
-        } else {
-            self.current = self.k * input + (1.0 - self.k) * self.current;
+            fn next(&mut self, input: $t) -> Self::Output {
+                self.update(input)
+            }
         }
-        self.current
-    }
+    )*};
 }
 
-impl<T: Close> Next<&T> for ExponentialMovingAverage {
-    type Output = f64;
+impl_next!(f32, f64, Decimal);
+
+impl<N: Numeric, T: Close<N>> Next<&T> for ExponentialMovingAverage<N> {
+    type Output = N;
 
     fn next(&mut self, input: &T) -> Self::Output {
-        self.next(input.close())
+        self.update(input.close())
     }
 }
 
-impl Reset for ExponentialMovingAverage {
+impl<N: Numeric> Reset for ExponentialMovingAverage<N> {
     fn reset(&mut self) {
-        self.current = 0.0;
+        self.current = N::zero();
         self.is_new = true;
     }
 }
@@ -66,7 +84,7 @@ impl Default for ExponentialMovingAverage {
     }
 }
 
-impl fmt::Display for ExponentialMovingAverage {
+impl<N> fmt::Display for ExponentialMovingAverage<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "EMA({})", self.period)
     }
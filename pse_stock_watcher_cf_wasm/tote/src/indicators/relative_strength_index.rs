@@ -1,80 +1,100 @@
 use std::fmt;
 
-use crate::errors::CommonError;
 use crate::data_traits::{Close, Next, Period, Reset};
+use crate::errors::CommonError;
+use crate::numeric::{Decimal, Numeric};
 
 use super::ExponentialMovingAverage as Ema;
 
 #[derive(Debug, Clone)]
-pub struct RelativeStrengthIndex {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RelativeStrengthIndex<N = f64> {
     period: usize,
-    up_ema_indicator: Ema,
-    down_ema_indicator: Ema,
-    prev_val: f64,
+    up_ema_indicator: Ema<N>,
+    down_ema_indicator: Ema<N>,
+    prev_val: N,
     is_new: bool,
 }
 
 impl RelativeStrengthIndex {
     pub fn new(period: usize) -> Result<Self, CommonError> {
+        Self::with_period(period)
+    }
+}
+
+impl<N: Numeric> RelativeStrengthIndex<N> {
+    pub fn with_period(period: usize) -> Result<Self, CommonError> {
         match period {
             0 => Err(CommonError::InvalidArgument),
             _ => Ok(Self {
                 period,
-                up_ema_indicator: Ema::new(period)?,
-                down_ema_indicator: Ema::new(period)?,
-                prev_val: 0.0,
+                up_ema_indicator: Ema::with_period(period)?,
+                down_ema_indicator: Ema::with_period(period)?,
+                prev_val: N::zero(),
                 is_new: true,
             }),
         }
     }
-}
 
-impl Next<f64> for RelativeStrengthIndex {
-    type Output = f64;
-
-    fn next(&mut self, input: f64) -> Self::Output {
-        let mut up = 0.0;
-        let mut down = 0.0;
+    fn update(&mut self, input: N) -> N {
+        let mut up = N::zero();
+        let mut down = N::zero();
 
         if self.is_new {
             self.is_new = false;
-            up = 0.1;
-            down = 0.1;
+            up = N::from_f64(0.1);
+            down = N::from_f64(0.1);
+        } else if input > self.prev_val {
+            up = input - self.prev_val;
         } else {
-            if input > self.prev_val {
-                up = input - self.prev_val;
-            } else {
-                down = self.prev_val - input;
-            }
+            down = self.prev_val - input;
         }
-        
+
         self.prev_val = input;
-        let up_ema = self.up_ema_indicator.next(up);
-        let down_ema = self.down_ema_indicator.next(down);
-        let result = 100.0 * up_ema / (up_ema + down_ema);
-        result
+        let up_ema = self.up_ema_indicator.update(up);
+        let down_ema = self.down_ema_indicator.update(down);
+        // Both averages decay to zero over a long flat run, fastest in
+        // fixed point. No movement either way is neutral.
+        if up_ema + down_ema == N::zero() {
+            return N::from_usize(50);
+        }
+        N::from_usize(100) * up_ema / (up_ema + down_ema)
     }
 }
 
-impl<T: Close> Next<&T> for RelativeStrengthIndex {
-    type Output = f64;
+macro_rules! impl_next {
+    ($($t:ty),*) => {$(
+        impl Next<$t> for RelativeStrengthIndex<$t> {
+            type Output = $t;
+
+            fn next(&mut self, input: $t) -> Self::Output {
+                self.update(input)
+            }
+        }
+    )*};
+}
+
+impl_next!(f32, f64, Decimal);
+
+impl<N: Numeric, T: Close<N>> Next<&T> for RelativeStrengthIndex<N> {
+    type Output = N;
 
     fn next(&mut self, input: &T) -> Self::Output {
-        self.next(input.close())
+        self.update(input.close())
     }
 }
 
-impl Period for RelativeStrengthIndex {
+impl<N> Period for RelativeStrengthIndex<N> {
     fn period(&self) -> usize {
         self.period
     }
 }
 
-impl Reset for RelativeStrengthIndex {
+impl<N: Numeric> Reset for RelativeStrengthIndex<N> {
     fn reset(&mut self) {
         self.up_ema_indicator.reset();
         self.down_ema_indicator.reset();
-        self.prev_val = 0.0;
+        self.prev_val = N::zero();
         self.is_new = true;
     }
 }
@@ -85,7 +105,7 @@ impl Default for RelativeStrengthIndex {
     }
 }
 
-impl fmt::Display for RelativeStrengthIndex {
+impl<N> fmt::Display for RelativeStrengthIndex<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "RSI({})", self.period)
     }
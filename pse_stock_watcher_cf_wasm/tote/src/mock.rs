@@ -0,0 +1,510 @@
+//! A local stand-in for PSE's `stockMarket` pages, serving recorded
+//! responses so network code can be tested offline.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tote::mock::{Fault, MockPse};
+//!
+//! let pse = MockPse::start().unwrap();
+//! pse.inject("getRecentSecurityQuoteData", Fault::Status(503));
+//! pse.inject_all(Fault::Latency(Duration::from_millis(50)));
+//! // Point a client at `pse.base_url()`.
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The path PSE serves its pages under.
+const BASE_PATH: &str = "/stockMarket";
+
+/// Every `method=` call `tote` and `tote_worker` make, and the page it is
+/// made on.
+pub const ROUTES: [(&str, &str); 9] = [
+    ("getSecuritiesAndIndicesForPublic", "home.html"),
+    ("findSecurityOrCompany", "home.html"),
+    ("fetchHeaderData", "companyInfo.html"),
+    (
+        "getRecentSecurityQuoteData",
+        "companyInfoHistoricalData.html",
+    ),
+    ("getMarketIndices", "dailySummary.html"),
+    ("getTopActiveStocks", "dailySummary.html"),
+    ("getTopSecurity", "dailySummary.html"),
+    ("getAdvancedSecurity", "dailySummary.html"),
+    ("getDeclinesSecurity", "dailySummary.html"),
+];
+
+/// The recordings in `tests/fixtures/pse`, by method.
+const RECORDINGS: [(&str, &str); 9] = [
+    (
+        "getSecuritiesAndIndicesForPublic",
+        include_str!("../tests/fixtures/pse/getSecuritiesAndIndicesForPublic.json"),
+    ),
+    (
+        "findSecurityOrCompany",
+        include_str!("../tests/fixtures/pse/findSecurityOrCompany.json"),
+    ),
+    (
+        "fetchHeaderData",
+        include_str!("../tests/fixtures/pse/fetchHeaderData.json"),
+    ),
+    (
+        "getRecentSecurityQuoteData",
+        include_str!("../tests/fixtures/pse/getRecentSecurityQuoteData.json"),
+    ),
+    (
+        "getMarketIndices",
+        include_str!("../tests/fixtures/pse/getMarketIndices.json"),
+    ),
+    (
+        "getTopActiveStocks",
+        include_str!("../tests/fixtures/pse/getTopActiveStocks.json"),
+    ),
+    (
+        "getTopSecurity",
+        include_str!("../tests/fixtures/pse/getTopSecurity.json"),
+    ),
+    (
+        "getAdvancedSecurity",
+        include_str!("../tests/fixtures/pse/getAdvancedSecurity.json"),
+    ),
+    (
+        "getDeclinesSecurity",
+        include_str!("../tests/fixtures/pse/getDeclinesSecurity.json"),
+    ),
+];
+
+/// Something to go wrong with a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Wait this long before answering.
+    Latency(Duration),
+    /// Answer with this status and an HTML error page, like PSE does while
+    /// under maintenance.
+    Status(u16),
+    /// Answer `200 OK` with the recording cut in half.
+    MalformedJson,
+}
+
+/// A request the server received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// `GET` or `POST`.
+    pub verb: String,
+    /// The page under `/stockMarket`, e.g. `home.html`.
+    pub page: String,
+    /// The decoded query string in order.
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.param("method")
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    recordings: HashMap<String, String>,
+    faults: HashMap<String, Vec<Fault>>,
+    all: Vec<Fault>,
+    requests: Vec<Request>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json;charset=UTF-8",
+            body,
+        }
+    }
+
+    fn error(status: u16) -> Self {
+        Self {
+            status,
+            content_type: "text/html;charset=UTF-8",
+            body: format!(
+                "<html><body><h1>{} {}</h1></body></html>",
+                status,
+                reason(status)
+            ),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+fn hex(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes `+` and `%XX` escapes, keeping malformed escapes as they are.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                match (hex(bytes[index + 1]), hex(bytes[index + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high << 4 | low);
+                        index += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (decode(key), decode(value)),
+            None => (decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Reads the request line and headers, and skips any body.
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (verb, target) = match (parts.next(), parts.next()) {
+        (Some(verb), Some(target)) => (verb.to_string(), target.to_string()),
+        _ => return Ok(None),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    io::copy(&mut reader.take(content_length as u64), &mut io::sink())?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let page = path
+        .strip_prefix(BASE_PATH)
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .to_string();
+    Ok(Some(Request {
+        verb,
+        page,
+        query: parse_query(query),
+    }))
+}
+
+fn respond(state: &Mutex<State>, request: Request) -> Response {
+    let mut state = lock(state);
+    state.requests.push(request.clone());
+
+    let method = request.method().unwrap_or_default();
+    let routed = ROUTES
+        .iter()
+        .any(|(name, page)| *name == method && *page == request.page);
+    let recording = match state.recordings.get(method) {
+        Some(recording) if routed => recording.clone(),
+        _ => return Response::error(404),
+    };
+
+    let faults: Vec<Fault> = state
+        .all
+        .iter()
+        .chain(state.faults.get(method).into_iter().flatten())
+        .copied()
+        .collect();
+    drop(state);
+
+    let latency: Duration = faults
+        .iter()
+        .filter_map(|fault| match fault {
+            Fault::Latency(latency) => Some(*latency),
+            _ => None,
+        })
+        .sum();
+    thread::sleep(latency);
+
+    for fault in faults {
+        match fault {
+            Fault::Latency(_) => {}
+            Fault::Status(status) => return Response::error(status),
+            Fault::MalformedJson => {
+                let mut cut = recording.len() / 2;
+                while !recording.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                return Response::json(recording[..cut].to_string());
+            }
+        }
+    }
+    Response::json(recording)
+}
+
+fn serve(mut stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    let response = match read_request(&stream)? {
+        Some(request) => respond(state, request),
+        None => Response::error(400),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// A poisoned lock only means a connection thread panicked; the state is
+/// still usable.
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A mock PSE server on a free local port, stopped when dropped.
+#[derive(Debug)]
+pub struct MockPse {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockPse {
+    /// Serves the recordings bundled from `tests/fixtures/pse`.
+    pub fn start() -> io::Result<Self> {
+        let recordings = RECORDINGS
+            .iter()
+            .map(|(method, body)| (method.to_string(), body.to_string()))
+            .collect();
+        Self::serve(recordings)
+    }
+
+    /// Serves `<method>.json` files from `dir`. Methods without a file
+    /// answer `404 Not Found`.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut recordings = HashMap::new();
+        for (method, _) in ROUTES.iter() {
+            let path = dir.as_ref().join(format!("{}.json", method));
+            match fs::read_to_string(&path) {
+                Ok(body) => {
+                    recordings.insert(method.to_string(), body);
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Self::serve(recordings)
+    }
+
+    fn serve(recordings: HashMap<String, String>) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            recordings,
+            ..State::default()
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // Each connection gets a thread so injected latency
+                    // does not hold up the others.
+                    if let Ok(stream) = stream {
+                        let state = Arc::clone(&state);
+                        thread::spawn(move || {
+                            let _ = serve(stream, &state);
+                        });
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    /// The address to use in place of [`crate::client::DEFAULT_BASE_URL`].
+    pub fn base_url(&self) -> String {
+        format!("http://{}{}", self.addr, BASE_PATH)
+    }
+
+    /// Replaces the response to `method`.
+    pub fn record<S: Into<String>>(&self, method: &str, body: S) {
+        lock(&self.state)
+            .recordings
+            .insert(method.to_string(), body.into());
+    }
+
+    /// Applies `fault` to every later call of `method`.
+    pub fn inject(&self, method: &str, fault: Fault) {
+        lock(&self.state)
+            .faults
+            .entry(method.to_string())
+            .or_default()
+            .push(fault);
+    }
+
+    /// Applies `fault` to every later call.
+    pub fn inject_all(&self, fault: Fault) {
+        lock(&self.state).all.push(fault);
+    }
+
+    pub fn clear_faults(&self) {
+        let mut state = lock(&self.state);
+        state.faults.clear();
+        state.all.clear();
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        lock(&self.state).requests.clone()
+    }
+}
+
+impl Drop for MockPse {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(pse: &MockPse, target: &str) -> String {
+        let mut stream = TcpStream::connect(pse.addr).unwrap();
+        write!(
+            stream,
+            "GET {}{} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            BASE_PATH, target
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn decodes_queries() {
+        assert_eq!(decode("SM+Prime%2C%20Inc."), "SM Prime, Inc.");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(
+            parse_query("method=find&query=&ajax"),
+            vec![
+                ("method".to_string(), "find".to_string()),
+                ("query".to_string(), String::new()),
+                ("ajax".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn routes_methods_to_pages() {
+        let pse = MockPse::start().unwrap();
+        let ok = get(&pse, "/home.html?method=findSecurityOrCompany&query=SM");
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        assert!(ok.contains("SM Investments Corporation"));
+        assert!(get(&pse, "/dailySummary.html?method=findSecurityOrCompany")
+            .starts_with("HTTP/1.1 404"));
+        assert!(get(&pse, "/home.html?method=nope").starts_with("HTTP/1.1 404"));
+
+        let requests = pse.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].page, "home.html");
+        assert_eq!(requests[0].param("query"), Some("SM"));
+    }
+
+    #[test]
+    fn injects_faults() {
+        let pse = MockPse::start().unwrap();
+        pse.inject("getMarketIndices", Fault::Status(503));
+        pse.inject("getTopSecurity", Fault::MalformedJson);
+
+        let failed = get(&pse, "/dailySummary.html?method=getMarketIndices");
+        assert!(failed.starts_with("HTTP/1.1 503 Service Unavailable"));
+        let cut = get(&pse, "/dailySummary.html?method=getTopSecurity");
+        let body = cut.split("\r\n\r\n").nth(1).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(body).is_err());
+
+        pse.clear_faults();
+        assert!(
+            get(&pse, "/dailySummary.html?method=getMarketIndices").starts_with("HTTP/1.1 200 OK")
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use crate::errors::CommonError;
+
+/// Arithmetic needed by the indicators and OHLCV data. Implemented for `f32`,
+/// `f64` and the fixed-point [`Decimal`].
+pub trait Numeric:
+    Copy
+    + fmt::Debug
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn from_usize(value: usize) -> Self;
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_numeric_for_float {
+    ($($t:ty),*) => {$(
+        impl Numeric for $t {
+            fn zero() -> Self {
+                0.0
+            }
+
+            fn one() -> Self {
+                1.0
+            }
+
+            fn from_usize(value: usize) -> Self {
+                value as $t
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+impl_numeric_for_float!(f32, f64);
+
+/// Fixed-point number with four decimal places, enough for PSE prices and
+/// fee computations without binary floating point rounding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decimal(i64);
+
+impl Decimal {
+    pub const SCALE: i64 = 10_000;
+    pub const DECIMAL_PLACES: usize = 4;
+
+    /// Creates a decimal from its value in ten-thousandths.
+    pub const fn from_raw(raw: i64) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+}
+
+impl Numeric for Decimal {
+    fn zero() -> Self {
+        Self(0)
+    }
+
+    fn one() -> Self {
+        Self(Self::SCALE)
+    }
+
+    fn from_usize(value: usize) -> Self {
+        Self(value as i64 * Self::SCALE)
+    }
+
+    fn from_f64(value: f64) -> Self {
+        Self((value * Self::SCALE as f64).round() as i64)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Self(value as i64 * Self::SCALE)
+    }
+}
+
+impl Add for Decimal {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Self;
+
+    /// Panics on overflow, like the integer operators, rather than
+    /// truncating.
+    fn mul(self, rhs: Self) -> Self::Output {
+        (self.0 as i128)
+            .checked_mul(rhs.0 as i128)
+            .map(|product| product / Self::SCALE as i128)
+            .and_then(|raw| i64::try_from(raw).ok())
+            .map(Self)
+            .expect("Decimal multiplication overflowed")
+    }
+}
+
+impl Div for Decimal {
+    type Output = Self;
+
+    /// Panics on division by zero or overflow, like the integer operators,
+    /// rather than truncating.
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.0 == 0 {
+            panic!("attempt to divide a Decimal by zero");
+        }
+        (self.0 as i128 * Self::SCALE as i128)
+            .checked_div(rhs.0 as i128)
+            .and_then(|raw| i64::try_from(raw).ok())
+            .map(Self)
+            .expect("Decimal division overflowed")
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:04}",
+            sign,
+            abs / Self::SCALE as u64,
+            abs % Self::SCALE as u64
+        )
+    }
+}
+
+/// Parses plain decimal strings such as `"-12.5"`, rejecting anything with
+/// more than four decimal places.
+impl FromStr for Decimal {
+    type Err = CommonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        let (whole, fraction) = match digits.find('.') {
+            Some(i) => (&digits[..i], &digits[i + 1..]),
+            None => (digits, ""),
+        };
+
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty()
+            || !is_digits(whole)
+            || !is_digits(fraction)
+            || fraction.len() > Self::DECIMAL_PLACES
+        {
+            return Err(CommonError::InvalidArgument);
+        }
+
+        let whole: i64 = whole.parse().map_err(|_| CommonError::InvalidArgument)?;
+        let fraction: i64 = format!("{:0<4}", fraction)
+            .parse()
+            .map_err(|_| CommonError::InvalidArgument)?;
+        let raw = whole
+            .checked_mul(Self::SCALE)
+            .and_then(|v| v.checked_add(fraction))
+            .ok_or(CommonError::InvalidArgument)?;
+
+        Ok(Self(if negative { -raw } else { raw }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_traits::Next;
+    use crate::indicators::{ExponentialMovingAverage, RelativeStrengthIndex};
+    use crate::OHLCVDataBuilder;
+
+    #[test]
+    fn decimal_arithmetic_is_exact() {
+        let price: Decimal = "12.34".parse().unwrap();
+        let shares = Decimal::from(1000);
+        let fee_rate: Decimal = "0.0025".parse().unwrap();
+
+        assert_eq!((price * shares).to_string(), "12340.0000");
+        assert_eq!((price * shares * fee_rate).to_string(), "30.8500");
+        assert_eq!((price / Decimal::from(2)).to_string(), "6.1700");
+        assert_eq!((-price).to_string(), "-12.3400");
+    }
+
+    #[test]
+    #[should_panic(expected = "divide a Decimal by zero")]
+    fn decimal_division_by_zero_panics() {
+        let _ = Decimal::from(1) / Decimal::zero();
+    }
+
+    #[test]
+    #[should_panic(expected = "multiplication overflowed")]
+    fn decimal_overflow_panics() {
+        let _ = Decimal::from_raw(i64::MAX) * Decimal::from(2);
+    }
+
+    #[test]
+    fn decimal_rsi_survives_flat_series() {
+        let mut rsi = RelativeStrengthIndex::<Decimal>::with_period(14).unwrap();
+        let mut last = rsi.next(Decimal::from(10));
+        for _ in 0..200 {
+            last = rsi.next(Decimal::from(10));
+        }
+        assert_eq!(last, Decimal::from(50));
+        assert_eq!(rsi.next(Decimal::from(11)), Decimal::from(100));
+    }
+
+    #[test]
+    fn decimal_parsing() {
+        assert_eq!("-0.5".parse::<Decimal>().unwrap(), Decimal::from_raw(-5000));
+        assert_eq!("7".parse::<Decimal>().unwrap(), Decimal::from(7));
+        assert!("1.23456".parse::<Decimal>().is_err());
+        assert!("1,234".parse::<Decimal>().is_err());
+        assert!(".5".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn indicators_are_generic() {
+        let mut ema = ExponentialMovingAverage::<f32>::with_period(3).unwrap();
+        assert_eq!(ema.next(2.0f32), 2.0);
+        assert_eq!(ema.next(4.0f32), 3.0);
+
+        let mut ema = ExponentialMovingAverage::<Decimal>::with_period(3).unwrap();
+        let bar = OHLCVDataBuilder::<Decimal>::new()
+            .open("10.5".parse::<Decimal>().unwrap())
+            .high(11)
+            .low(10)
+            .close(11)
+            .volume(1000)
+            .build()
+            .unwrap();
+        assert_eq!(ema.next(Decimal::from(10)), Decimal::from(10));
+        assert_eq!(ema.next(&bar).to_string(), "10.5000");
+
+        let mut rsi = RelativeStrengthIndex::<Decimal>::with_period(14).unwrap();
+        assert_eq!(rsi.next(Decimal::from(10)), Decimal::from(50));
+    }
+}
@@ -1,13 +1,15 @@
-use crate::errors::OHLCVDataError;
 use crate::data_traits::*;
+use crate::errors::OHLCVDataError;
+use crate::numeric::Numeric;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct OHLCVData {
-    open: f64,
-    high: f64,
-    low: f64,
-    close: f64,
-    volume: f64,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OHLCVData<N = f64> {
+    open: N,
+    high: N,
+    low: N,
+    close: N,
+    volume: N,
 }
 
 impl OHLCVData {
@@ -16,46 +18,46 @@ impl OHLCVData {
     }
 }
 
-impl Open for OHLCVData {
-    fn open(&self) -> f64 {
+impl<N: Numeric> Open<N> for OHLCVData<N> {
+    fn open(&self) -> N {
         self.open
     }
 }
 
-impl High for OHLCVData {
-    fn high(&self) -> f64 {
+impl<N: Numeric> High<N> for OHLCVData<N> {
+    fn high(&self) -> N {
         self.high
     }
 }
 
-impl Low for OHLCVData {
-    fn low(&self) -> f64 {
+impl<N: Numeric> Low<N> for OHLCVData<N> {
+    fn low(&self) -> N {
         self.low
     }
 }
 
-impl Close for OHLCVData {
-    fn close(&self) -> f64 {
+impl<N: Numeric> Close<N> for OHLCVData<N> {
+    fn close(&self) -> N {
         self.close
     }
 }
 
-impl Volume for OHLCVData {
-    fn volume(&self) -> f64 {
+impl<N: Numeric> Volume<N> for OHLCVData<N> {
+    fn volume(&self) -> N {
         self.volume
     }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct OHLCVDataBuilder {
-    pub(self) open: Option<f64>,
-    pub(self) high: Option<f64>,
-    pub(self) low: Option<f64>,
-    pub(self) close: Option<f64>,
-    pub(self) volume: Option<f64>,
+pub struct OHLCVDataBuilder<N = f64> {
+    pub(self) open: Option<N>,
+    pub(self) high: Option<N>,
+    pub(self) low: Option<N>,
+    pub(self) close: Option<N>,
+    pub(self) volume: Option<N>,
 }
 
-impl OHLCVDataBuilder {
+impl<N: Numeric> OHLCVDataBuilder<N> {
     pub fn new() -> Self {
         Self {
             open: None,
@@ -66,32 +68,32 @@ impl OHLCVDataBuilder {
         }
     }
 
-    pub fn open<T: Into<f64>>(mut self, val: T) -> Self {
+    pub fn open<T: Into<N>>(mut self, val: T) -> Self {
         self.open = Some(val.into());
         self
     }
 
-    pub fn high<T: Into<f64>>(mut self, val: T) -> Self {
+    pub fn high<T: Into<N>>(mut self, val: T) -> Self {
         self.high = Some(val.into());
         self
     }
 
-    pub fn low<T: Into<f64>>(mut self, val: T) -> Self {
+    pub fn low<T: Into<N>>(mut self, val: T) -> Self {
         self.low = Some(val.into());
         self
     }
 
-    pub fn volume<T: Into<f64>>(mut self, val: T) -> Self {
+    pub fn volume<T: Into<N>>(mut self, val: T) -> Self {
         self.volume = Some(val.into());
         self
     }
 
-    pub fn close<T: Into<f64>>(mut self, val: T) -> Self {
+    pub fn close<T: Into<N>>(mut self, val: T) -> Self {
         self.close = Some(val.into());
         self
     }
 
-    pub fn build(self) -> Result<OHLCVData, OHLCVDataError> {
+    pub fn build(self) -> Result<OHLCVData<N>, OHLCVDataError> {
         if let Self {
             open: Some(open),
             high: Some(high),
@@ -103,10 +105,10 @@ impl OHLCVDataBuilder {
             if low <= open
                 && low <= close
                 && low <= high
-                && high <= open
-                && high <= close
-                && volume >= 0.0f64
-                && low >= 0.0f64
+                && high >= open
+                && high >= close
+                && volume >= N::zero()
+                && low >= N::zero()
             {
                 let item = OHLCVData {
                     open,
@@ -125,3 +127,42 @@ impl OHLCVDataBuilder {
         }
     }
 }
+
+impl<N: Numeric> Default for OHLCVDataBuilder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(open: f64, high: f64, low: f64, close: f64) -> Result<OHLCVData, OHLCVDataError> {
+        OHLCVData::builder()
+            .open(open)
+            .high(high)
+            .low(low)
+            .close(close)
+            .volume(1000.0)
+            .build()
+    }
+
+    #[test]
+    fn high_bounds_open_and_close() {
+        assert!(bar(100.0, 105.0, 95.0, 102.0).is_ok());
+        assert!(bar(100.0, 100.0, 100.0, 100.0).is_ok());
+        assert!(matches!(
+            bar(100.0, 99.0, 95.0, 98.0),
+            Err(OHLCVDataError::Invalid)
+        ));
+        assert!(matches!(
+            bar(98.0, 99.0, 95.0, 100.0),
+            Err(OHLCVDataError::Invalid)
+        ));
+        assert!(matches!(
+            OHLCVData::builder().open(1.0).build(),
+            Err(OHLCVDataError::Incomplete)
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::SnapshotError;
+
+/// Bumped whenever the serialized state of any indicator changes shape.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Indicator state tagged with the format version it was written with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    pub version: u32,
+    pub indicator: String,
+    pub state: T,
+}
+
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+impl<T: fmt::Display> Snapshot<T> {
+    pub fn new(state: T) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            indicator: state.to_string(),
+            state,
+        }
+    }
+}
+
+impl<T> Snapshot<T> {
+    pub fn into_state(self) -> Result<T, SnapshotError> {
+        match self.version {
+            SNAPSHOT_VERSION => Ok(self.state),
+            version => Err(SnapshotError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Serializes an indicator after its last processed bar.
+pub fn save<T: Serialize + fmt::Display>(indicator: &T) -> Result<String, SnapshotError> {
+    Ok(serde_json::to_string(&Snapshot::new(indicator))?)
+}
+
+/// Restores an indicator saved with [`save`] so it can keep consuming bars
+/// where it left off.
+pub fn restore<T: DeserializeOwned>(data: &str) -> Result<T, SnapshotError> {
+    let header: SnapshotHeader = serde_json::from_str(data)?;
+    if header.version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(header.version));
+    }
+
+    let snapshot: Snapshot<T> = serde_json::from_str(data)?;
+    snapshot.into_state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breadth::McClellanOscillator;
+    use crate::data_traits::Next;
+    use crate::indicators::{ExponentialMovingAverage, RelativeStrengthIndex};
+
+    const CLOSES: [f64; 10] = [
+        10.12, 10.3, 10.18, 9.94, 10.0, 10.46, 10.52, 10.24, 10.88, 11.06,
+    ];
+
+    fn assert_resumes<T>(mut indicator: T)
+    where
+        T: Next<f64, Output = f64> + Serialize + DeserializeOwned + fmt::Display,
+    {
+        let (today, tomorrow) = CLOSES.split_at(6);
+        for close in today {
+            indicator.next(*close);
+        }
+
+        let data = save(&indicator).unwrap();
+        let mut restored: T = restore(&data).unwrap();
+
+        for close in tomorrow {
+            assert_eq!(indicator.next(*close), restored.next(*close));
+        }
+    }
+
+    #[test]
+    fn indicators_resume_with_identical_results() {
+        assert_resumes(ExponentialMovingAverage::new(3).unwrap());
+        assert_resumes(RelativeStrengthIndex::new(4).unwrap());
+        assert_resumes(McClellanOscillator::new(2, 5).unwrap());
+    }
+
+    #[test]
+    fn restore_rejects_other_versions() {
+        let data = save(&ExponentialMovingAverage::default())
+            .unwrap()
+            .replace("\"version\":1", "\"version\":99");
+
+        match restore::<ExponentialMovingAverage>(&data) {
+            Err(SnapshotError::UnsupportedVersion(99)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
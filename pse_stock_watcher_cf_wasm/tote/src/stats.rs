@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::errors::CommonError;
+use crate::BarSeries;
+
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMatrix {
+    pub symbols: Vec<String>,
+    /// `None` where a pair has too few common dates or one of them never
+    /// moved, as happens with illiquid names.
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl CorrelationMatrix {
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|s| s == a)?;
+        let j = self.symbols.iter().position(|s| s == b)?;
+        self.values[i][j]
+    }
+}
+
+/// Largest peak to trough decline, expressed as a positive fraction of the peak.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    pub peak: NaiveDate,
+    pub trough: NaiveDate,
+    pub value: f64,
+}
+
+/// Simple one period returns of a price sequence.
+pub fn returns(prices: &[f64]) -> Vec<f64> {
+    prices.windows(2).map(|w| w[1] / w[0] - 1.0).collect()
+}
+
+/// Returns over `window` bars ending at each bar after the first `window` bars.
+pub fn rolling_returns(series: &BarSeries, window: usize) -> Result<Vec<f64>, CommonError> {
+    if window == 0 {
+        return Err(CommonError::InvalidArgument);
+    }
+
+    let closes = series.closes();
+    Ok(closes
+        .iter()
+        .zip(closes.iter().skip(window))
+        .map(|(start, end)| end / start - 1.0)
+        .collect())
+}
+
+pub fn mean(values: &[f64]) -> Result<f64, CommonError> {
+    match values.len() {
+        0 => Err(CommonError::InsufficientData),
+        n => Ok(values.iter().sum::<f64>() / n as f64),
+    }
+}
+
+/// Sample standard deviation.
+pub fn standard_deviation(values: &[f64]) -> Result<f64, CommonError> {
+    if values.len() < 2 {
+        return Err(CommonError::InsufficientData);
+    }
+
+    let mean = mean(values)?;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Ok(variance.sqrt())
+}
+
+/// Annualized volatility of daily close to close returns.
+pub fn volatility(series: &BarSeries) -> Result<f64, CommonError> {
+    Ok(standard_deviation(&returns(&series.closes()))? * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// Annualized volatility of the daily returns inside each `window` bar window.
+pub fn rolling_volatility(series: &BarSeries, window: usize) -> Result<Vec<f64>, CommonError> {
+    if window < 2 {
+        return Err(CommonError::InvalidArgument);
+    }
+
+    returns(&series.closes())
+        .windows(window)
+        .map(|w| Ok(standard_deviation(w)? * TRADING_DAYS_PER_YEAR.sqrt()))
+        .collect()
+}
+
+fn covariance(a: &[f64], b: &[f64]) -> Result<f64, CommonError> {
+    if a.len() != b.len() {
+        return Err(CommonError::InvalidArgument);
+    }
+    if a.len() < 2 {
+        return Err(CommonError::InsufficientData);
+    }
+
+    let mean_a = mean(a)?;
+    let mean_b = mean(b)?;
+    let sum: f64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - mean_a) * (y - mean_b))
+        .sum();
+    Ok(sum / (a.len() - 1) as f64)
+}
+
+/// Pearson correlation coefficient of two equally sized samples.
+pub fn pearson_correlation(a: &[f64], b: &[f64]) -> Result<f64, CommonError> {
+    let denominator = standard_deviation(a)? * standard_deviation(b)?;
+    if denominator == 0.0 {
+        return Err(CommonError::InsufficientData);
+    }
+    Ok(covariance(a, b)? / denominator)
+}
+
+/// Closes of both series on the dates they have in common.
+fn aligned_closes(a: &BarSeries, b: &BarSeries) -> (Vec<f64>, Vec<f64>) {
+    let b_closes: HashMap<NaiveDate, f64> = b.dates().iter().cloned().zip(b.closes()).collect();
+
+    a.dates()
+        .iter()
+        .zip(a.closes())
+        .filter_map(|(date, close)| b_closes.get(date).map(|other| (close, *other)))
+        .unzip()
+}
+
+/// Pairwise correlation of daily returns, computed over the dates each pair
+/// of series have in common.
+pub fn correlation_matrix(series: &[BarSeries]) -> CorrelationMatrix {
+    let n = series.len();
+    let mut values = vec![vec![Some(1.0); n]; n];
+
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (a, b) = aligned_closes(&series[i], &series[j]);
+            let value = pearson_correlation(&returns(&a), &returns(&b)).ok();
+            values[i][j] = value;
+            values[j][i] = value;
+        }
+    }
+
+    CorrelationMatrix {
+        symbols: series.iter().map(|s| s.symbol().to_string()).collect(),
+        values,
+    }
+}
+
+/// Beta of `series` against a benchmark such as the PSEi, using daily
+/// returns on common dates.
+pub fn beta(series: &BarSeries, benchmark: &BarSeries) -> Result<f64, CommonError> {
+    let (a, b) = aligned_closes(series, benchmark);
+    let (a, b) = (returns(&a), returns(&b));
+    let variance = covariance(&b, &b)?;
+    if variance == 0.0 {
+        return Err(CommonError::InsufficientData);
+    }
+    Ok(covariance(&a, &b)? / variance)
+}
+
+pub fn max_drawdown(series: &BarSeries) -> Result<Drawdown, CommonError> {
+    let mut iter = series.dates().iter().cloned().zip(series.closes());
+    let (first_date, first_close) = iter.next().ok_or(CommonError::InsufficientData)?;
+
+    let mut peak = (first_date, first_close);
+    let mut result = Drawdown {
+        peak: first_date,
+        trough: first_date,
+        value: 0.0,
+    };
+
+    for (date, close) in iter {
+        if close > peak.1 {
+            peak = (date, close);
+            continue;
+        }
+
+        let value = 1.0 - close / peak.1;
+        if value > result.value {
+            result = Drawdown {
+                peak: peak.0,
+                trough: date,
+                value,
+            };
+        }
+    }
+
+    Ok(result)
+}
+
+fn check_confidence(confidence: f64) -> Result<(), CommonError> {
+    if confidence > 0.0 && confidence < 1.0 {
+        Ok(())
+    } else {
+        Err(CommonError::InvalidArgument)
+    }
+}
+
+fn check_finite(returns: &[f64]) -> Result<(), CommonError> {
+    if returns.iter().all(|r| r.is_finite()) {
+        Ok(())
+    } else {
+        Err(CommonError::InvalidArgument)
+    }
+}
+
+/// One period historical Value-at-Risk, returned as a positive loss fraction.
+/// Non-finite returns, such as those off a zero close, are rejected.
+pub fn historical_var(returns: &[f64], confidence: f64) -> Result<f64, CommonError> {
+    check_confidence(confidence)?;
+    if returns.is_empty() {
+        return Err(CommonError::InsufficientData);
+    }
+    check_finite(returns)?;
+
+    let mut sorted = returns.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let index = ((1.0 - confidence) * sorted.len() as f64).floor() as usize;
+    Ok(-sorted[index.min(sorted.len() - 1)])
+}
+
+/// One period Value-at-Risk assuming normally distributed returns, returned
+/// as a positive loss fraction. Non-finite returns are rejected.
+pub fn parametric_var(returns: &[f64], confidence: f64) -> Result<f64, CommonError> {
+    check_confidence(confidence)?;
+    check_finite(returns)?;
+    let mean = mean(returns)?;
+    let sd = standard_deviation(returns)?;
+    Ok(-(mean + inverse_normal_cdf(1.0 - confidence) * sd))
+}
+
+/// Acklam's rational approximation of the standard normal quantile function.
+fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -inverse_normal_cdf(1.0 - p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OHLCVData;
+
+    fn series(symbol: &str, closes: &[f64]) -> BarSeries {
+        let mut series = BarSeries::new(symbol);
+        let start = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        for (i, close) in closes.iter().enumerate() {
+            let bar = OHLCVData::builder()
+                .open(*close)
+                .high(*close)
+                .low(*close)
+                .close(*close)
+                .volume(1000.0)
+                .build()
+                .unwrap();
+            series
+                .push(start + chrono::Duration::days(i as i64), bar)
+                .unwrap();
+        }
+        series
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn rolling_returns_span_the_window() {
+        let s = series("SM", &[100.0, 110.0, 121.0]);
+        let result = rolling_returns(&s, 2).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_close(result[0], 0.21);
+        assert!(rolling_returns(&s, 0).is_err());
+    }
+
+    #[test]
+    fn correlation_and_beta() {
+        let market = series("PSEI", &[100.0, 102.0, 101.0, 104.0, 103.0]);
+        let leveraged = series("LEV", &[50.0, 52.0, 51.0, 54.0, 53.0]);
+        let inverse = series("INV", &[100.0, 98.0, 99.0, 96.0, 97.0]);
+
+        let flat = series("FLAT", &[10.0, 10.0, 10.0, 10.0, 10.0]);
+        let matrix = correlation_matrix(&[market.clone(), leveraged.clone(), inverse, flat]);
+        assert_close(matrix.get("PSEI", "PSEI").unwrap(), 1.0);
+        assert!(matrix.get("PSEI", "LEV").unwrap() > 0.99);
+        assert!(matrix.get("PSEI", "INV").unwrap() < -0.99);
+        assert_eq!(matrix.get("PSEI", "FLAT"), None);
+        assert_eq!(matrix.get("FLAT", "INV"), None);
+        assert_close(matrix.get("FLAT", "FLAT").unwrap(), 1.0);
+
+        assert_close(beta(&market, &market).unwrap(), 1.0);
+        assert!(beta(&leveraged, &market).unwrap() > 1.0);
+    }
+
+    #[test]
+    fn max_drawdown_finds_peak_and_trough() {
+        let s = series("SM", &[100.0, 120.0, 90.0, 110.0, 60.0, 130.0]);
+        let drawdown = max_drawdown(&s).unwrap();
+        assert_close(drawdown.value, 0.5);
+        assert_eq!(drawdown.peak, s.dates()[1]);
+        assert_eq!(drawdown.trough, s.dates()[4]);
+    }
+
+    #[test]
+    fn value_at_risk() {
+        let sample: Vec<f64> = (0..100).map(|i| (i as f64 - 50.0) / 1000.0).collect();
+        assert_close(historical_var(&sample, 0.95).unwrap(), 0.045);
+        assert!(historical_var(&sample, 1.0).is_err());
+        let off_zero_close = returns(&[0.0, 10.0, 0.0, 5.0]);
+        assert!(matches!(
+            historical_var(&off_zero_close, 0.95),
+            Err(CommonError::InvalidArgument)
+        ));
+
+        assert_close(inverse_normal_cdf(0.5), 0.0);
+        assert!((inverse_normal_cdf(0.05) + 1.644_853_6).abs() < 1e-6);
+        let var = parametric_var(&sample, 0.95).unwrap();
+        assert_close(
+            var,
+            -(mean(&sample).unwrap()
+                - 1.644_853_626_951_472_2 * standard_deviation(&sample).unwrap()),
+        );
+        for bad in &[f64::NAN, f64::INFINITY] {
+            let mut with_bad = sample.clone();
+            with_bad[3] = *bad;
+            assert!(matches!(
+                parametric_var(&with_bad, 0.95),
+                Err(CommonError::InvalidArgument)
+            ));
+        }
+        assert!(matches!(
+            parametric_var(&off_zero_close, 0.95),
+            Err(CommonError::InvalidArgument)
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::breadth::SecurityQuote;
+use crate::errors::OHLCVDataError;
+use crate::{Numeric, OHLCVData, OHLCVDataBuilder};
+
+/// Format of the "Stock Update As of" row PSE puts in front of the list.
+const AS_OF_FORMAT: &str = "%m/%d/%Y %I:%M %p";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicator {
+    Up,
+    Down,
+    Unchanged,
+}
+
+/// A security or index as listed by `getSecuritiesAndIndicesForPublic`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Stock {
+    #[serde(deserialize_with = "de_whole")]
+    pub total_volume: u64,
+    pub indicator: Indicator,
+    #[serde(deserialize_with = "de_number")]
+    pub perc_change_close: f64,
+    #[serde(deserialize_with = "de_number")]
+    pub last_traded_price: f64,
+    pub security_alias: String,
+    pub security_symbol: String,
+}
+
+/// The response of `getSecuritiesAndIndicesForPublic`: every listed
+/// security and index, and the time PSE last updated them.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Stocks {
+    pub as_of: Option<NaiveDateTime>,
+    pub stocks: Vec<Stock>,
+}
+
+impl Stocks {
+    /// The stock with `symbol`, ignoring case.
+    pub fn get(&self, symbol: &str) -> Option<&Stock> {
+        self.stocks
+            .iter()
+            .find(|stock| stock.security_symbol.eq_ignore_ascii_case(symbol))
+    }
+}
+
+impl Stock {
+    /// Traded value for the day, the last price times the volume.
+    pub fn value(&self) -> f64 {
+        self.last_traded_price * self.total_volume as f64
+    }
+}
+
+impl From<&Stock> for SecurityQuote {
+    fn from(stock: &Stock) -> Self {
+        Self {
+            symbol: stock.security_symbol.clone(),
+            sector: None,
+            close: stock.last_traded_price,
+            percent_change: stock.perc_change_close,
+        }
+    }
+}
+
+/// A snapshot only carries the last traded price, so the bar is flat at that
+/// price.
+impl<N: Numeric> TryFrom<&Stock> for OHLCVData<N> {
+    type Error = OHLCVDataError;
+
+    fn try_from(stock: &Stock) -> Result<Self, Self::Error> {
+        let price = N::from_f64(stock.last_traded_price);
+        OHLCVDataBuilder::new()
+            .open(price)
+            .high(price)
+            .low(price)
+            .close(price)
+            .volume(N::from_f64(stock.total_volume as f64))
+            .build()
+    }
+}
+
+/// Rows matching neither shape, such as a suspended security listed with
+/// blank prices, are skipped rather than failing the whole list.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Row {
+    Stock(Stock),
+    AsOf(AsOfRow),
+    Other(de::IgnoredAny),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsOfRow {
+    #[serde(deserialize_with = "de_as_of")]
+    last_traded_price: NaiveDateTime,
+}
+
+impl<'de> Deserialize<'de> for Stocks {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Public(Vec<Row>),
+            Typed {
+                #[serde(rename = "asOf")]
+                as_of: Option<NaiveDateTime>,
+                stocks: Vec<Stock>,
+            },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Public(rows) => {
+                let mut result = Stocks {
+                    as_of: None,
+                    stocks: Vec::with_capacity(rows.len()),
+                };
+                for row in rows {
+                    match row {
+                        Row::Stock(stock) => result.stocks.push(stock),
+                        Row::AsOf(row) => result.as_of = Some(row.last_traded_price),
+                        Row::Other(_) => {}
+                    }
+                }
+                Ok(result)
+            }
+            Repr::Typed { as_of, stocks } => Ok(Stocks { as_of, stocks }),
+        }
+    }
+}
+
+impl Serialize for Indicator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            Indicator::Up => "up",
+            Indicator::Down => "down",
+            Indicator::Unchanged => "unchanged",
+        })
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Indicator {
+    fn schema_name() -> String {
+        "Indicator".to_string()
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, SchemaObject};
+
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(vec!["up".into(), "down".into(), "unchanged".into()]),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Accepts PSE's `U`/`D` flags as well as the names this crate serializes.
+impl<'de> Deserialize<'de> for Indicator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(match value.trim().to_lowercase().as_str() {
+            "u" | "up" => Indicator::Up,
+            "d" | "down" => Indicator::Down,
+            _ => Indicator::Unchanged,
+        })
+    }
+}
+
+/// Parses numbers PSE sends as comma formatted strings such as `"1,234.50"`.
+/// Plain JSON numbers are accepted too so archived data reads back.
+pub fn parse_number(value: &str) -> Option<f64> {
+    let value: String = value.trim().chars().filter(|c| *c != ',').collect();
+    value.parse().ok().filter(|v: &f64| v.is_finite())
+}
+
+struct NumberVisitor;
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = f64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number or a comma formatted number string")
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v as f64)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v as f64)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        parse_number(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+/// Numbers PSE sends either as JSON numbers or strings such as `"1,234.50"`.
+pub fn de_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    deserializer.deserialize_any(NumberVisitor)
+}
+
+/// Volumes and ids, which PSE also sends in either form.
+pub fn de_whole<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let value = deserializer.deserialize_any(NumberVisitor)?;
+    if value >= 0.0 && value.fract() == 0.0 {
+        Ok(value as u64)
+    } else {
+        Err(de::Error::custom(format!(
+            "expected a whole number, got {}",
+            value
+        )))
+    }
+}
+
+fn de_as_of<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    NaiveDateTime::parse_from_str(value.trim(), AS_OF_FORMAT).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_traits::{Close, Volume};
+
+    const PUBLIC: &str = r#"[
+        {"totalVolume":"","indicator":"","percChangeClose":"","lastTradedPrice":"10/16/2020 03:00 PM","securityAlias":"Stock Update As of","indicatorImg":"","securitySymbol":"Stock Update As of"},
+        {"totalVolume":"1,234,500","indicator":"U","percChangeClose":"1.25","lastTradedPrice":"1,010.00","securityAlias":"SM Investments Corporation","indicatorImg":"up.jpg","securitySymbol":"SM"},
+        {"totalVolume":"3,200","indicator":"D","percChangeClose":"-0.50","lastTradedPrice":"35.50","securityAlias":"Ayala Land, Inc.","indicatorImg":"down.jpg","securitySymbol":"ALI"},
+        {"totalVolume":"","indicator":"","percChangeClose":"","lastTradedPrice":"","securityAlias":"Suspended Corporation","indicatorImg":"","securitySymbol":"SUSP"}
+    ]"#;
+
+    #[test]
+    fn parses_public_list() {
+        let stocks: Stocks = serde_json::from_str(PUBLIC).unwrap();
+        assert_eq!(
+            stocks.as_of.unwrap().to_string(),
+            "2020-10-16 15:00:00".to_string()
+        );
+        assert_eq!(stocks.stocks.len(), 2);
+
+        let sm = &stocks.stocks[0];
+        assert_eq!(sm.total_volume, 1_234_500);
+        assert_eq!(sm.indicator, Indicator::Up);
+        assert_eq!(sm.perc_change_close, 1.25);
+        assert_eq!(sm.last_traded_price, 1010.0);
+        assert_eq!(stocks.stocks[1].indicator, Indicator::Down);
+        assert_eq!(stocks.get("ali"), Some(&stocks.stocks[1]));
+        assert_eq!(stocks.get("BDO"), None);
+        assert_eq!(stocks.get("SUSP"), None);
+    }
+
+    #[test]
+    fn round_trips_typed_json() {
+        let stocks: Stocks = serde_json::from_str(PUBLIC).unwrap();
+        let json = serde_json::to_string(&stocks).unwrap();
+        assert!(json.contains(r#""totalVolume":1234500"#));
+        assert!(json.contains(r#""indicator":"up""#));
+
+        let parsed: Stocks = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, stocks);
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        let row = r#"{"totalVolume":"1.5","indicator":"U","percChangeClose":"1","lastTradedPrice":"1","securityAlias":"A","securitySymbol":"A"}"#;
+        assert!(serde_json::from_str::<Stock>(row).is_err());
+        assert_eq!(parse_number(" 1,234.5 "), Some(1234.5));
+        assert_eq!(parse_number("n/a"), None);
+    }
+
+    #[test]
+    fn converts_into_ohlcv() {
+        let stocks: Stocks = serde_json::from_str(PUBLIC).unwrap();
+        let bar = OHLCVData::<f64>::try_from(&stocks.stocks[1]).unwrap();
+        assert_eq!(bar.close(), 35.5);
+        assert_eq!(bar.volume(), 3200.0);
+    }
+}
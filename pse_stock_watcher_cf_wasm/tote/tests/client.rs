@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use tote::client::{Client, Indicator};
+use tote::errors::ClientError;
+use tote::mock::{Fault, MockPse, ROUTES};
+
+fn setup() -> (MockPse, Client) {
+    let pse = MockPse::start().unwrap();
+    let client = Client::new().unwrap().with_base_url(pse.base_url());
+    (pse, client)
+}
+
+#[tokio::test]
+async fn lists_securities() {
+    let (pse, client) = setup();
+
+    let securities = client.securities().await.unwrap();
+    assert_eq!(securities.as_of.unwrap().to_string(), "2020-10-16 15:00:00");
+    assert_eq!(securities.stocks.len(), 9);
+
+    let sm = securities.get("SM").unwrap();
+    assert_eq!(sm.security_alias, "SM Investments Corporation");
+    assert_eq!(sm.last_traded_price, 1010.0);
+    assert_eq!(sm.total_volume, 486_210);
+    assert_eq!(sm.indicator, Indicator::Up);
+    assert_eq!(securities.get("ALI").unwrap().indicator, Indicator::Down);
+
+    let requests = pse.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].verb, "GET");
+    assert_eq!(requests[0].page, "home.html");
+    assert_eq!(
+        requests[0].method(),
+        Some("getSecuritiesAndIndicesForPublic")
+    );
+    assert_eq!(requests[0].param("ajax"), Some("true"));
+}
+
+#[tokio::test]
+async fn finds_securities() {
+    let (pse, client) = setup();
+
+    let matches = client.find_security("SM Prime", 0, 5).await.unwrap();
+    let symbols: Vec<&str> = matches.iter().map(|m| m.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["SM", "SMPH"]);
+    assert_eq!((matches[1].security_id, matches[1].company_id), (146, 112));
+
+    let request = &pse.requests()[0];
+    assert_eq!(request.method(), Some("findSecurityOrCompany"));
+    assert_eq!(request.param("query"), Some("SM Prime"));
+    assert_eq!(request.param("start"), Some("0"));
+    assert_eq!(request.param("limit"), Some("5"));
+}
+
+#[tokio::test]
+async fn builds_history() {
+    let (pse, client) = setup();
+
+    let series = client.history("sm").await.unwrap();
+    assert_eq!(series.symbol(), "SM");
+    assert_eq!(series.len(), 5);
+    let closes = series.closes();
+    assert_eq!(closes.last(), Some(&1010.0));
+    assert_eq!(
+        series.dates().last(),
+        NaiveDate::from_ymd_opt(2020, 10, 16).as_ref()
+    );
+
+    let requests = pse.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].page, "companyInfoHistoricalData.html");
+    assert_eq!(requests[1].param("security"), Some("520"));
+
+    match client.history("NOPE").await {
+        Err(ClientError::UnknownSymbol(symbol)) => assert_eq!(symbol, "NOPE"),
+        other => panic!("expected an unknown symbol, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn reports_server_errors() {
+    let (pse, client) = setup();
+    pse.inject("getRecentSecurityQuoteData", Fault::Status(503));
+
+    match client.recent_quotes(520).await {
+        Err(ClientError::Status { status, url }) => {
+            assert_eq!(status, 503);
+            assert!(url.contains("method=getRecentSecurityQuoteData"));
+        }
+        other => panic!("expected a status error, got {:?}", other),
+    }
+    // Other methods are unaffected.
+    assert!(client.securities().await.is_ok());
+
+    pse.clear_faults();
+    assert_eq!(client.recent_quotes(520).await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn reports_malformed_json() {
+    let (pse, client) = setup();
+    pse.inject_all(Fault::MalformedJson);
+
+    match client.securities().await {
+        Err(ClientError::Malformed(_)) => {}
+        other => panic!("expected malformed JSON, got {:?}", other),
+    }
+
+    pse.record("getSecuritiesAndIndicesForPublic", "{\"records\":[]}");
+    pse.clear_faults();
+    assert!(matches!(
+        client.securities().await,
+        Err(ClientError::Malformed(_))
+    ));
+}
+
+#[tokio::test]
+async fn waits_out_latency_within_the_timeout() {
+    let (pse, client) = setup();
+    let latency = Duration::from_millis(200);
+    pse.inject_all(Fault::Latency(latency));
+
+    let started = Instant::now();
+    client.securities().await.unwrap();
+    assert!(started.elapsed() >= latency);
+
+    let impatient = client.with_timeout(Duration::from_millis(50));
+    match impatient.securities().await {
+        Err(ClientError::Http(error)) => assert!(error.is_timeout()),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn serves_every_recorded_method() {
+    let (pse, _) = setup();
+    let http = reqwest::Client::new();
+
+    for (method, page) in ROUTES.iter() {
+        let response = http
+            .get(&format!("{}/{}", pse.base_url(), page))
+            .query(&[("method", *method), ("ajax", "true")])
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success(), "{} failed", method);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(!body.is_null(), "{} has no recording", method);
+    }
+    assert_eq!(pse.requests().len(), ROUTES.len());
+}
+
+#[tokio::test]
+async fn serves_recordings_from_a_directory() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pse");
+    let pse = MockPse::from_dir(dir).unwrap();
+    let client = Client::new().unwrap().with_base_url(pse.base_url());
+    assert_eq!(client.securities().await.unwrap().stocks.len(), 9);
+
+    let empty = MockPse::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    let client = client.with_base_url(empty.base_url());
+    assert!(matches!(
+        client.securities().await,
+        Err(ClientError::Status { status: 404, .. })
+    ));
+}
@@ -0,0 +1,322 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar;
+use crate::errors::ArchiveError;
+use crate::storage::Storage;
+use crate::{Stock, Stocks};
+
+/// All stocks as they stood at the close of a trading day.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Snapshot {
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub as_of: Option<NaiveDateTime>,
+    pub stocks: Vec<Stock>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveStatus {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// What an [`archive_snapshot`] call wrote.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSummary {
+    pub date: NaiveDate,
+    pub key: String,
+    pub stocks: usize,
+    pub status: ArchiveStatus,
+}
+
+pub fn snapshot_key(date: NaiveDate) -> String {
+    format!("snapshot:{}", date)
+}
+
+/// Parses either a plain `YYYY-MM-DD` date or an RFC 3339 timestamp, the
+/// latter resolved to its Manila calendar date.
+pub fn parse_date(timestamp: &str) -> Result<NaiveDate, ArchiveError> {
+    let timestamp = timestamp.trim();
+    NaiveDate::parse_from_str(timestamp, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(timestamp)
+                .ok()
+                .map(|instant| calendar::trading_date(&instant))
+        })
+        .ok_or_else(|| ArchiveError::InvalidTimestamp(timestamp.to_string()))
+}
+
+/// Like [`parse_date`], but rejects days the market is closed.
+pub fn parse_trading_date(timestamp: &str) -> Result<NaiveDate, ArchiveError> {
+    let date = parse_date(timestamp)?;
+    if calendar::is_trading_day(date) {
+        Ok(date)
+    } else {
+        Err(ArchiveError::NonTradingDay(date))
+    }
+}
+
+pub async fn load_snapshot<S>(storage: &S, date: NaiveDate) -> Result<Snapshot, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    let data = storage
+        .get(&snapshot_key(date))
+        .await?
+        .ok_or(ArchiveError::MissingSnapshot(date))?;
+
+    Ok(serde_json::from_str(&data)?)
+}
+
+pub async fn find_stock_by_date<S>(
+    storage: &S,
+    symbol: &str,
+    timestamp: &str,
+) -> Result<Stock, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    let date = parse_trading_date(timestamp)?;
+    let snapshot = load_snapshot(storage, date).await?;
+
+    snapshot
+        .stocks
+        .into_iter()
+        .find(|stock| stock.security_symbol.eq_ignore_ascii_case(symbol.trim()))
+        .ok_or_else(|| ArchiveError::SymbolNotFound {
+            symbol: symbol.to_string(),
+            date,
+        })
+}
+
+/// Trims names, upper-cases symbols, drops rows without a symbol and keeps
+/// one entry per symbol, sorted by symbol.
+pub fn normalize(stocks: Vec<Stock>) -> Vec<Stock> {
+    let mut result: Vec<Stock> = Vec::with_capacity(stocks.len());
+    for stock in stocks {
+        let symbol = stock.security_symbol.trim().to_uppercase();
+        if symbol.is_empty() {
+            continue;
+        }
+
+        let stock = Stock {
+            security_alias: stock.security_alias.trim().to_string(),
+            security_symbol: symbol,
+            ..stock
+        };
+
+        match result
+            .iter_mut()
+            .find(|item| item.security_symbol == stock.security_symbol)
+        {
+            Some(item) => *item = stock,
+            None => result.push(stock),
+        }
+    }
+
+    result.sort_by(|a, b| a.security_symbol.cmp(&b.security_symbol));
+    result
+}
+
+/// Persists `stocks` as the snapshot of the trading day PSE last updated
+/// them, or of the day `now` falls on when PSE sent no timestamp. Running it
+/// again on the same day only rewrites the snapshot when the normalized data
+/// changed.
+pub async fn archive_snapshot<S, Tz>(
+    storage: &S,
+    stocks: Stocks,
+    now: &DateTime<Tz>,
+) -> Result<ArchiveSummary, ArchiveError>
+where
+    S: Storage + ?Sized,
+    Tz: TimeZone,
+{
+    let date = stocks
+        .as_of
+        .map(|as_of| as_of.date())
+        .unwrap_or_else(|| calendar::trading_date(now));
+    if !calendar::is_trading_day(date) {
+        return Err(ArchiveError::NonTradingDay(date));
+    }
+
+    let snapshot = Snapshot {
+        date,
+        as_of: stocks.as_of,
+        stocks: normalize(stocks.stocks),
+    };
+
+    let status = match load_snapshot(storage, date).await {
+        Ok(existing) if existing == snapshot => ArchiveStatus::Unchanged,
+        Ok(_) => ArchiveStatus::Updated,
+        Err(ArchiveError::MissingSnapshot(_)) | Err(ArchiveError::Malformed(_)) => {
+            ArchiveStatus::Created
+        }
+        Err(err) => return Err(err),
+    };
+
+    let key = snapshot_key(date);
+    if status != ArchiveStatus::Unchanged {
+        storage
+            .put(&key, &serde_json::to_string(&snapshot)?)
+            .await?;
+    }
+
+    Ok(ArchiveSummary {
+        date,
+        key,
+        stocks: snapshot.stocks.len(),
+        status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::Indicator;
+    use futures::executor::block_on;
+
+    fn stock(symbol: &str, price: f64) -> Stock {
+        Stock {
+            total_volume: 1000,
+            indicator: Indicator::Up,
+            perc_change_close: 1.0,
+            last_traded_price: price,
+            security_alias: symbol.to_string(),
+            security_symbol: symbol.to_string(),
+        }
+    }
+
+    fn stocks(items: Vec<Stock>) -> Stocks {
+        Stocks {
+            as_of: None,
+            stocks: items,
+        }
+    }
+
+    fn storage_with_snapshot(date: NaiveDate) -> MemoryStorage {
+        let storage = MemoryStorage::new();
+        let snapshot = Snapshot {
+            date,
+            as_of: None,
+            stocks: vec![stock("SM", 1000.0), stock("ALI", 35.5)],
+        };
+        block_on(storage.put(
+            &snapshot_key(date),
+            &serde_json::to_string(&snapshot).unwrap(),
+        ))
+        .unwrap();
+        storage
+    }
+
+    #[test]
+    fn parses_dates_and_timestamps() {
+        let date = NaiveDate::from_ymd_opt(2020, 10, 16).unwrap();
+        assert_eq!(parse_trading_date("2020-10-16").unwrap(), date);
+        assert_eq!(parse_trading_date("2020-10-16T07:00:00Z").unwrap(), date);
+        // 17:00 UTC on Thursday is already Friday in Manila.
+        assert_eq!(parse_trading_date("2020-10-15T17:00:00Z").unwrap(), date);
+
+        assert!(matches!(
+            parse_trading_date("2020-10-17"),
+            Err(ArchiveError::NonTradingDay(_))
+        ));
+        assert!(matches!(
+            parse_trading_date("yesterday"),
+            Err(ArchiveError::InvalidTimestamp(_))
+        ));
+    }
+
+    #[test]
+    fn finds_stock_in_archived_snapshot() {
+        let storage = storage_with_snapshot(NaiveDate::from_ymd_opt(2020, 10, 16).unwrap());
+
+        let stock = block_on(find_stock_by_date(&storage, "ali", "2020-10-16")).unwrap();
+        assert_eq!(stock.security_symbol, "ALI");
+        assert_eq!(stock.last_traded_price, 35.5);
+
+        assert!(matches!(
+            block_on(find_stock_by_date(&storage, "JFC", "2020-10-16")),
+            Err(ArchiveError::SymbolNotFound { .. })
+        ));
+        assert!(matches!(
+            block_on(find_stock_by_date(&storage, "SM", "2020-10-15")),
+            Err(ArchiveError::MissingSnapshot(_))
+        ));
+    }
+
+    #[test]
+    fn normalizes_stocks() {
+        let mut padded = stock(" sm ", 1010.0);
+        padded.security_alias = " SM Investments ".to_string();
+
+        let stocks = normalize(vec![
+            stock("", 1.0),
+            stock("SM", 1000.0),
+            stock("ALI", 35.5),
+            padded,
+        ]);
+
+        let symbols: Vec<&str> = stocks.iter().map(|s| s.security_symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["ALI", "SM"]);
+        assert_eq!(stocks[1].last_traded_price, 1010.0);
+        assert_eq!(stocks[1].security_alias, "SM Investments");
+    }
+
+    #[test]
+    fn archive_is_idempotent() {
+        let storage = MemoryStorage::new();
+        let now = calendar::manila()
+            .with_ymd_and_hms(2020, 10, 16, 15, 30, 0)
+            .unwrap();
+        let items = vec![stock("SM", 1000.0), stock("ALI", 35.5)];
+
+        let summary = block_on(archive_snapshot(&storage, stocks(items.clone()), &now)).unwrap();
+        assert_eq!(summary.status, ArchiveStatus::Created);
+        assert_eq!(summary.key, "snapshot:2020-10-16");
+        assert_eq!(summary.stocks, 2);
+
+        let summary = block_on(archive_snapshot(&storage, stocks(items.clone()), &now)).unwrap();
+        assert_eq!(summary.status, ArchiveStatus::Unchanged);
+        assert_eq!(storage.len(), 1);
+
+        let mut changed = items;
+        changed[0].last_traded_price = 1020.0;
+        let summary = block_on(archive_snapshot(&storage, stocks(changed), &now)).unwrap();
+        assert_eq!(summary.status, ArchiveStatus::Updated);
+
+        let stock = block_on(find_stock_by_date(&storage, "SM", "2020-10-16")).unwrap();
+        assert_eq!(stock.last_traded_price, 1020.0);
+    }
+
+    #[test]
+    fn archive_uses_pse_timestamp() {
+        let storage = MemoryStorage::new();
+        let saturday = calendar::manila()
+            .with_ymd_and_hms(2020, 10, 17, 9, 0, 0)
+            .unwrap();
+
+        assert!(matches!(
+            block_on(archive_snapshot(
+                &storage,
+                stocks(vec![stock("SM", 1.0)]),
+                &saturday
+            )),
+            Err(ArchiveError::NonTradingDay(_))
+        ));
+        assert!(storage.is_empty());
+
+        let mut items = stocks(vec![stock("SM", 1.0)]);
+        items.as_of = NaiveDate::from_ymd_opt(2020, 10, 16)
+            .unwrap()
+            .and_hms_opt(15, 0, 0);
+        let summary = block_on(archive_snapshot(&storage, items, &saturday)).unwrap();
+        assert_eq!(summary.date, NaiveDate::from_ymd_opt(2020, 10, 16).unwrap());
+    }
+}
@@ -0,0 +1,228 @@
+//! Serves the worker's routes on a local port, answering upstream requests
+//! from recorded fixtures and archiving into memory.
+//!
+//! ```text
+//! cargo run -p tote_worker --features dev-server --bin tote_dev_server -- \
+//!     --addr 127.0.0.1:8787 --fixtures tote_worker/tests/fixtures \
+//!     --now 2020-10-16T14:30:00+08:00
+//! ```
+
+use std::io::Write;
+use std::process;
+use std::thread;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::executor::block_on;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+
+use tote_worker::cache::{MemoryCache, ResponseCache};
+use tote_worker::calendar::Clock;
+use tote_worker::errors::StreamError;
+use tote_worker::fixtures::FixtureFetcher;
+use tote_worker::http::{HttpRequest, HttpResponse, Method};
+use tote_worker::job::{run_snapshot_job, Schedule};
+use tote_worker::router::Router;
+use tote_worker::storage::MemoryStorage;
+use tote_worker::stream::{self, EventSink, StreamConfig, Timer};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8787";
+const DEFAULT_FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+const USAGE: &str = "usage: tote_dev_server [--addr HOST:PORT] [--fixtures DIR] [--now RFC3339]";
+
+struct Options {
+    addr: String,
+    fixtures: String,
+    now: Option<DateTime<Utc>>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        addr: DEFAULT_ADDR.to_string(),
+        fixtures: DEFAULT_FIXTURES.to_string(),
+        now: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--addr" => options.addr = value()?,
+            "--fixtures" => options.fixtures = value()?,
+            "--now" => {
+                let now = value()?;
+                let now = DateTime::parse_from_rfc3339(&now)
+                    .map_err(|err| format!("invalid --now `{}`: {}", now, err))?;
+                options.now = Some(now.with_timezone(&Utc));
+            }
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Runs from `--now` at wall clock speed, so cache expiry and market hours
+/// can be tried against the dates of the fixtures.
+#[derive(Debug, Clone, Copy)]
+struct DevClock {
+    offset: Duration,
+}
+
+impl DevClock {
+    fn starting_at(now: Option<DateTime<Utc>>) -> Self {
+        Self {
+            offset: now.map_or_else(Duration::zero, |now| now.signed_duration_since(Utc::now())),
+        }
+    }
+}
+
+impl Clock for DevClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset
+    }
+}
+
+struct ThreadTimer;
+
+#[async_trait(?Send)]
+impl Timer for ThreadTimer {
+    async fn sleep(&self, duration: Duration) {
+        thread::sleep(duration.to_std().unwrap_or_default());
+    }
+}
+
+/// Writes each event straight to the connection. tiny_http buffers
+/// chunked bodies, so stream responses are written by hand.
+struct WriterSink(Box<dyn Write + Send>);
+
+#[async_trait(?Send)]
+impl EventSink for WriterSink {
+    async fn send(&mut self, event: &str) -> Result<(), StreamError> {
+        self.0
+            .write_all(event.as_bytes())
+            .and_then(|()| self.0.flush())
+            .map_err(|err| StreamError::Disconnected(err.to_string()))
+    }
+}
+
+fn headers(response: &HttpResponse) -> Vec<Header> {
+    response
+        .headers
+        .iter()
+        .filter_map(|(name, value)| Header::from_bytes(name.as_bytes(), value.as_bytes()).ok())
+        .collect()
+}
+
+fn to_request(request: &mut Request) -> HttpRequest {
+    let mut http = request.headers().iter().fold(
+        HttpRequest::new(Method::parse(request.method().as_str()), request.url()),
+        |http, header| http.with_header(header.field.as_str().as_str(), header.value.as_str()),
+    );
+
+    let mut body = String::new();
+    match request.as_reader().read_to_string(&mut body) {
+        Ok(read) if read > 0 => http.body = Some(body),
+        Ok(_) => {}
+        Err(err) => eprintln!("could not read request body: {}", err),
+    }
+    http
+}
+
+fn respond(request: Request, response: HttpResponse) {
+    let data = response.body.clone().unwrap_or_default().into_bytes();
+    let mut reply = Response::from_data(data).with_status_code(StatusCode(response.status));
+    for header in headers(&response) {
+        reply.add_header(header);
+    }
+    if let Err(err) = request.respond(reply) {
+        eprintln!("could not answer request: {}", err);
+    }
+}
+
+/// Streams quotes from a thread of its own, as the worker does from a task.
+/// Open streams are not capped as they are in the worker.
+fn respond_stream(request: Request, http: &HttpRequest, fetcher: FixtureFetcher, clock: DevClock) {
+    let symbols = match stream::parse_symbols(http.query_param("symbols")) {
+        Ok(symbols) => symbols,
+        Err(err) => return respond(request, HttpResponse::error(&err)),
+    };
+
+    let head = stream::response_head();
+    let mut writer = request.into_writer();
+    let status = StatusCode(head.status);
+    let mut preamble = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.0,
+        status.default_reason_phrase()
+    );
+    for (name, value) in &head.headers {
+        preamble.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    preamble.push_str("Connection: close\r\n\r\n");
+    if let Err(err) = writer.write_all(preamble.as_bytes()) {
+        return eprintln!("stream closed: {}", err);
+    }
+
+    thread::spawn(move || {
+        let result = block_on(stream::run(
+            &fetcher,
+            None,
+            &clock,
+            &ThreadTimer,
+            &StreamConfig::default(),
+            &symbols,
+            &mut WriterSink(writer),
+        ));
+        if let Err(err) = result {
+            eprintln!("stream closed: {}", err);
+        }
+    });
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(2);
+    });
+
+    let fetcher = FixtureFetcher::from_dir(&options.fixtures);
+    let clock = DevClock::starting_at(options.now);
+    let storage = MemoryStorage::new();
+    match block_on(run_snapshot_job(
+        &fetcher,
+        &storage,
+        &clock,
+        &Schedule::default(),
+        true,
+    )) {
+        Ok(status) => eprintln!("archived fixtures for {:?}", status.date),
+        Err(err) => eprintln!("could not archive fixtures: {}", err),
+    }
+
+    let router = Router::new(fetcher.clone())
+        .with_cache(ResponseCache::new(MemoryCache::new(), clock))
+        .with_storage(storage)
+        .with_clock(clock);
+
+    let server = Server::http(&options.addr).unwrap_or_else(|err| {
+        eprintln!("could not listen on {}: {}", options.addr, err);
+        process::exit(1);
+    });
+    eprintln!(
+        "serving {} on http://{}",
+        fetcher.dir().display(),
+        options.addr
+    );
+
+    for mut request in server.incoming_requests() {
+        let http = to_request(&mut request);
+        eprintln!("{:?} {}", http.method, request.url());
+        if http.path == stream::STREAM_PATH {
+            respond_stream(request, &http, fetcher.clone(), clock);
+        } else {
+            let response = block_on(router.handle(&http));
+            respond(request, response);
+        }
+    }
+}
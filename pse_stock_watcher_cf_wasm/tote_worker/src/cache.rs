@@ -0,0 +1,467 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::{self, Clock};
+use crate::errors::{FetchError, StorageError};
+use crate::http::HttpResponse;
+use crate::storage::Storage;
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+
+/// How long upstream responses are reused while the market is open.
+pub const MARKET_HOURS_TTL_SECONDS: i64 = 60;
+
+/// How long stored entries outlive their expiry, so they can still be
+/// served stale while PSE is down.
+pub const STALE_GRACE_SECONDS: i64 = 24 * 60 * 60;
+
+/// The shortest `expirationTtl` Workers KV accepts.
+const MIN_STORAGE_TTL_SECONDS: i64 = 60;
+
+/// An upstream response body and the window it is served from cache in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub body: String,
+    pub stored_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+#[async_trait(?Send)]
+pub trait Cache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, StorageError>;
+    async fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), StorageError>;
+}
+
+/// Cache kept in memory for the lifetime of the value, used in tests.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: RefCell<HashMap<String, CacheEntry>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait(?Send)]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, StorageError> {
+        Ok(self.entries.borrow().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), StorageError> {
+        self.entries
+            .borrow_mut()
+            .insert(key.to_string(), entry.clone());
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: Cache + ?Sized> Cache for Rc<C> {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, StorageError> {
+        (**self).get(key).await
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), StorageError> {
+        (**self).put(key, entry).await
+    }
+}
+
+/// Cache entries serialized into a [`Storage`], such as the `TOTE_CACHE` KV
+/// namespace.
+#[derive(Debug)]
+pub struct StorageCache<S> {
+    storage: S,
+}
+
+impl<S: Storage> StorageCache<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: Storage> Cache for StorageCache<S> {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, StorageError> {
+        // An unreadable entry is as good as a miss.
+        Ok(self
+            .storage
+            .get(key)
+            .await?
+            .and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Keys include query strings, so entries are stored expiring rather
+    /// than kept forever.
+    async fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), StorageError> {
+        let value =
+            serde_json::to_string(entry).map_err(|e| StorageError::Request(e.to_string()))?;
+        let ttl = entry
+            .expires_at
+            .signed_duration_since(entry.stored_at)
+            .num_seconds()
+            .saturating_add(STALE_GRACE_SECONDS)
+            .max(MIN_STORAGE_TTL_SECONDS);
+        self.storage.put_expiring(key, &value, ttl as u64).await
+    }
+}
+
+pub fn cache_key(endpoint: &Endpoint) -> String {
+    format!("cache:{}", endpoint.url(""))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    /// Served past its expiry because the upstream request failed.
+    Stale,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Stale => "STALE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached {
+    pub body: String,
+    pub status: CacheStatus,
+    pub entry: CacheEntry,
+    pub now: DateTime<Utc>,
+}
+
+impl Cached {
+    fn from_entry(entry: CacheEntry, status: CacheStatus, now: DateTime<Utc>) -> Self {
+        Self {
+            body: entry.body.clone(),
+            status,
+            entry,
+            now,
+        }
+    }
+
+    /// Adds `X-Cache`, `Age` and `Cache-Control` describing this result.
+    pub fn headers(&self, response: HttpResponse) -> HttpResponse {
+        let age = self
+            .now
+            .signed_duration_since(self.entry.stored_at)
+            .num_seconds()
+            .max(0);
+        let max_age = self
+            .entry
+            .expires_at
+            .signed_duration_since(self.now)
+            .num_seconds()
+            .max(0);
+
+        response
+            .with_header("X-Cache", self.status.as_str())
+            .with_header("Age", &age.to_string())
+            .with_header("Cache-Control", &format!("public, max-age={}", max_age))
+    }
+}
+
+/// Reuses upstream responses until the market moves: briefly during trading
+/// sessions and until the next open once the market closes.
+pub struct ResponseCache {
+    cache: Box<dyn Cache>,
+    clock: Box<dyn Clock>,
+}
+
+impl ResponseCache {
+    pub fn new<C, K>(cache: C, clock: K) -> Self
+    where
+        C: Cache + 'static,
+        K: Clock + 'static,
+    {
+        Self {
+            cache: Box::new(cache),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// The raw response to `endpoint`. Only bodies that are JSON are cached.
+    pub async fn fetch<F>(&self, fetcher: &F, endpoint: &Endpoint) -> Result<Cached, FetchError>
+    where
+        F: Fetcher + ?Sized,
+    {
+        let (_, cached) = self.fetch_json::<IgnoredAny, F>(fetcher, endpoint).await?;
+        Ok(cached)
+    }
+
+    /// The response to `endpoint` decoded as `T`. A body that does not
+    /// decode, such as PSE's HTML maintenance page, is never cached: the
+    /// last good entry is served stale instead, or the error returned.
+    pub async fn fetch_json<T, F>(
+        &self,
+        fetcher: &F,
+        endpoint: &Endpoint,
+    ) -> Result<(T, Cached), FetchError>
+    where
+        T: DeserializeOwned,
+        F: Fetcher + ?Sized,
+    {
+        let key = cache_key(endpoint);
+        let now = self.clock.now();
+        let existing = self
+            .cache
+            .get(&key)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("cache read for {} failed: {}", key, err);
+                None
+            })
+            // Entries stored before bodies were checked may not decode.
+            .and_then(|entry| {
+                let value = serde_json::from_str::<T>(&entry.body).ok()?;
+                Some((value, entry))
+            });
+
+        let existing = match existing {
+            Some((value, entry)) if entry.is_fresh(now) => {
+                return Ok((value, Cached::from_entry(entry, CacheStatus::Hit, now)));
+            }
+            existing => existing,
+        };
+
+        let fetched = fetcher.fetch(endpoint).await.and_then(|body| {
+            let value = serde_json::from_str::<T>(&body)?;
+            Ok((value, body))
+        });
+        let (decoded, body) = match fetched {
+            Ok(fetched) => fetched,
+            Err(err) => {
+                return match existing {
+                    Some((value, entry)) => {
+                        warn!("serving stale {}: {}", key, err);
+                        Ok((value, Cached::from_entry(entry, CacheStatus::Stale, now)))
+                    }
+                    None => Err(err),
+                }
+            }
+        };
+
+        let entry = CacheEntry {
+            body,
+            stored_at: now,
+            expires_at: now
+                + calendar::cache_ttl(&now, Duration::seconds(MARKET_HOURS_TTL_SECONDS)),
+        };
+        if let Err(err) = self.cache.put(&key, &entry).await {
+            warn!("cache write for {} failed: {}", key, err);
+        }
+
+        Ok((decoded, Cached::from_entry(entry, CacheStatus::Miss, now)))
+    }
+}
+
+/// Decodes `endpoint` through `cache` when there is one.
+pub async fn cached_json<T, F>(
+    cache: Option<&ResponseCache>,
+    fetcher: &F,
+    endpoint: &Endpoint,
+) -> Result<T, FetchError>
+where
+    T: DeserializeOwned,
+    F: Fetcher + ?Sized,
+{
+    match cache {
+        Some(cache) => Ok(cache.fetch_json(fetcher, endpoint).await?.0),
+        None => fetch_json(fetcher, endpoint).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::FixedClock;
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+    use std::cell::Cell;
+
+    struct CountingFetcher {
+        calls: Cell<usize>,
+        fail: bool,
+    }
+
+    #[async_trait(?Send)]
+    impl Fetcher for CountingFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            self.calls.set(self.calls.get() + 1);
+            if self.fail {
+                Err(FetchError::Network("offline".to_string()))
+            } else {
+                Ok(format!("[{}]", self.calls.get()))
+            }
+        }
+    }
+
+    struct PageFetcher(&'static str);
+
+    #[async_trait(?Send)]
+    impl Fetcher for PageFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    const MAINTENANCE: &str = "<html><body>Site under maintenance</body></html>";
+
+    fn fetcher(fail: bool) -> CountingFetcher {
+        CountingFetcher {
+            calls: Cell::new(0),
+            fail,
+        }
+    }
+
+    /// Friday 16 October 2020 in Manila.
+    fn friday(hour: u32, minute: u32) -> DateTime<Utc> {
+        calendar::manila()
+            .with_ymd_and_hms(2020, 10, 16, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn response_cache(cache: &Rc<MemoryCache>, now: DateTime<Utc>) -> ResponseCache {
+        ResponseCache::new(cache.clone(), FixedClock(now))
+    }
+
+    #[test]
+    fn reuses_responses_during_market_hours_briefly() {
+        let cache = Rc::new(MemoryCache::new());
+        let upstream = fetcher(false);
+        let endpoint = Endpoint::SecuritiesAndIndices;
+
+        let first = block_on(response_cache(&cache, friday(10, 0)).fetch(&upstream, &endpoint));
+        let first = first.unwrap();
+        assert_eq!(first.status, CacheStatus::Miss);
+        assert_eq!(
+            first.entry.expires_at,
+            friday(10, 0) + Duration::seconds(MARKET_HOURS_TTL_SECONDS)
+        );
+
+        let later = response_cache(&cache, friday(10, 0) + Duration::seconds(30));
+        let second = block_on(later.fetch(&upstream, &endpoint)).unwrap();
+        assert_eq!(second.status, CacheStatus::Hit);
+        assert_eq!(second.body, "[1]");
+
+        let response = second.headers(HttpResponse::new(200));
+        assert_eq!(response.header("X-Cache"), Some("HIT"));
+        assert_eq!(response.header("Age"), Some("30"));
+        assert_eq!(response.header("Cache-Control"), Some("public, max-age=30"));
+
+        let expired = response_cache(&cache, friday(10, 1));
+        let third = block_on(expired.fetch(&upstream, &endpoint)).unwrap();
+        assert_eq!(third.status, CacheStatus::Miss);
+        assert_eq!(third.body, "[2]");
+    }
+
+    #[test]
+    fn keeps_closing_data_until_next_open() {
+        let cache = Rc::new(MemoryCache::new());
+        let upstream = fetcher(false);
+        let endpoint = Endpoint::MarketIndices;
+
+        let closed = block_on(response_cache(&cache, friday(15, 30)).fetch(&upstream, &endpoint));
+        let next_open = calendar::manila()
+            .with_ymd_and_hms(2020, 10, 19, 9, 30, 0)
+            .unwrap();
+        assert_eq!(closed.unwrap().entry.expires_at, next_open);
+
+        let weekend = response_cache(&cache, friday(23, 0) + Duration::days(1));
+        let cached = block_on(weekend.fetch(&upstream, &endpoint)).unwrap();
+        assert_eq!(cached.status, CacheStatus::Hit);
+        assert_eq!(upstream.calls.get(), 1);
+    }
+
+    #[test]
+    fn serves_stale_entries_when_upstream_fails() {
+        let cache = Rc::new(MemoryCache::new());
+        let endpoint = Endpoint::TopActiveStocks;
+        block_on(response_cache(&cache, friday(10, 0)).fetch(&fetcher(false), &endpoint)).unwrap();
+
+        let later = response_cache(&cache, friday(11, 0));
+        let stale = block_on(later.fetch(&fetcher(true), &endpoint)).unwrap();
+        assert_eq!(stale.status, CacheStatus::Stale);
+        assert_eq!(stale.body, "[1]");
+
+        let other = Endpoint::DeclinesSecurity;
+        assert!(block_on(later.fetch(&fetcher(true), &other)).is_err());
+    }
+
+    #[test]
+    fn never_caches_bodies_that_do_not_decode() {
+        let cache = Rc::new(MemoryCache::new());
+        let endpoint = Endpoint::MarketIndices;
+        let closed = response_cache(&cache, friday(15, 30));
+
+        let err = block_on(closed.fetch(&PageFetcher(MAINTENANCE), &endpoint)).unwrap_err();
+        assert!(matches!(err, FetchError::Decode(_)));
+        assert_eq!(block_on(cache.get(&cache_key(&endpoint))).unwrap(), None);
+
+        let upstream = fetcher(false);
+        let fetched = block_on(closed.fetch(&upstream, &endpoint)).unwrap();
+        assert_eq!(fetched.status, CacheStatus::Miss);
+        assert_eq!(upstream.calls.get(), 1);
+
+        let weekend = response_cache(&cache, friday(10, 0) + Duration::days(3));
+        let (value, stale) =
+            block_on(weekend.fetch_json::<Vec<u32>, _>(&PageFetcher(MAINTENANCE), &endpoint))
+                .unwrap();
+        assert_eq!(value, vec![1]);
+        assert_eq!(stale.status, CacheStatus::Stale);
+        assert_eq!(stale.body, "[1]");
+    }
+
+    #[test]
+    fn storage_cache_stores_entries_expiring() {
+        let cache = StorageCache::new(MemoryStorage::new());
+        let entry = CacheEntry {
+            body: "[]".to_string(),
+            stored_at: friday(10, 0),
+            expires_at: friday(10, 1),
+        };
+        block_on(cache.put("cache:key", &entry)).unwrap();
+        assert_eq!(
+            cache.storage.ttl("cache:key"),
+            Some(60 + STALE_GRACE_SECONDS as u64)
+        );
+    }
+
+    #[test]
+    fn storage_cache_round_trips_entries() {
+        let cache = StorageCache::new(MemoryStorage::new());
+        let entry = CacheEntry {
+            body: "[]".to_string(),
+            stored_at: friday(10, 0),
+            expires_at: friday(10, 1),
+        };
+        block_on(cache.put("cache:key", &entry)).unwrap();
+        assert_eq!(block_on(cache.get("cache:key")).unwrap(), Some(entry));
+
+        block_on(cache.storage.put("cache:bad", "not json")).unwrap();
+        assert_eq!(block_on(cache.get("cache:bad")).unwrap(), None);
+    }
+}
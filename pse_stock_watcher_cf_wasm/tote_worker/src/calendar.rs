@@ -0,0 +1,138 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+
+/// Morning and afternoon trading sessions as `(hour, minute)` Manila times.
+const SESSIONS: [((u32, u32), (u32, u32)); 2] = [((9, 30), (12, 0)), ((13, 0), (15, 0))];
+
+/// Philippine Standard Time, which the PSE trades in.
+pub fn manila() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+/// Whether the PSE is open on `date`. Only weekends are excluded; exchange
+/// holidays surface as missing archived data instead.
+pub fn is_trading_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// The Manila calendar date of an instant.
+pub fn trading_date<Tz: TimeZone>(instant: &DateTime<Tz>) -> NaiveDate {
+    instant.with_timezone(&manila()).date_naive()
+}
+
+fn session_time((hour, minute): (u32, u32)) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// Whether `instant` falls within a trading session.
+pub fn is_market_open<Tz: TimeZone>(instant: &DateTime<Tz>) -> bool {
+    let local = instant.with_timezone(&manila());
+    if !is_trading_day(local.date_naive()) {
+        return false;
+    }
+
+    let time = local.time();
+    SESSIONS
+        .iter()
+        .any(|(open, close)| time >= session_time(*open) && time < session_time(*close))
+}
+
+/// The start of the first trading session after `instant`.
+pub fn next_open<Tz: TimeZone>(instant: &DateTime<Tz>) -> DateTime<FixedOffset> {
+    let local = instant.with_timezone(&manila());
+    let mut date = local.date_naive();
+    loop {
+        if is_trading_day(date) {
+            for (open, _) in SESSIONS.iter() {
+                let start = date
+                    .and_time(session_time(*open))
+                    .and_local_timezone(manila())
+                    .unwrap();
+                if start > local {
+                    return start;
+                }
+            }
+        }
+        date = date.succ_opt().unwrap();
+    }
+}
+
+/// Source of the current time, injected so time dependent behavior can be
+/// tested.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at a given instant.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// How long data fetched at `instant` stays current: `market_ttl` while a
+/// session is running, otherwise until the next session opens.
+pub fn cache_ttl<Tz: TimeZone>(instant: &DateTime<Tz>, market_ttl: Duration) -> Duration {
+    if is_market_open(instant) {
+        market_ttl
+    } else {
+        next_open(instant).signed_duration_since(instant.with_timezone(&manila()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        // October 2020: the 16th is a Friday.
+        manila()
+            .with_ymd_and_hms(2020, 10, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn market_hours() {
+        assert!(!is_market_open(&at(16, 9, 29)));
+        assert!(is_market_open(&at(16, 9, 30)));
+        assert!(!is_market_open(&at(16, 12, 30)));
+        assert!(is_market_open(&at(16, 14, 59)));
+        assert!(!is_market_open(&at(16, 15, 0)));
+        assert!(!is_market_open(&at(17, 10, 0)));
+        assert!(is_market_open(
+            &Utc.with_ymd_and_hms(2020, 10, 16, 2, 0, 0).unwrap()
+        ));
+    }
+
+    #[test]
+    fn next_open_skips_breaks_and_weekends() {
+        assert_eq!(next_open(&at(16, 8, 0)), at(16, 9, 30));
+        assert_eq!(next_open(&at(16, 12, 15)), at(16, 13, 0));
+        assert_eq!(next_open(&at(16, 15, 0)), at(19, 9, 30));
+        assert_eq!(next_open(&at(18, 23, 0)), at(19, 9, 30));
+    }
+
+    #[test]
+    fn ttl_extends_outside_market_hours() {
+        let minute = Duration::minutes(1);
+        assert_eq!(cache_ttl(&at(16, 10, 0), minute), minute);
+        assert_eq!(cache_ttl(&at(16, 12, 0), minute), Duration::hours(1));
+        assert_eq!(
+            cache_ttl(&at(16, 15, 0).with_timezone(&Utc), minute),
+            Duration::hours(66) + Duration::minutes(30)
+        );
+    }
+}
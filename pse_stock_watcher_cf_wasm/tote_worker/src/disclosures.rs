@@ -0,0 +1,184 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tote::disclosures::{Disclosure, Dividend};
+
+use crate::errors::ArchiveError;
+use crate::storage::Storage;
+
+/// Dividends parsed from EDGE are archived per symbol, next to the daily
+/// snapshots price history is filled from.
+pub fn dividends_key(symbol: &str) -> String {
+    format!("dividends:{}", symbol.trim().to_uppercase())
+}
+
+pub fn disclosures_key(symbol: &str) -> String {
+    format!("disclosures:{}", symbol.trim().to_uppercase())
+}
+
+async fn load<S, T>(storage: &S, key: &str) -> Result<Vec<T>, ArchiveError>
+where
+    S: Storage + ?Sized,
+    T: DeserializeOwned,
+{
+    match storage.get(key).await? {
+        Some(data) => Ok(serde_json::from_str(&data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Adds the records of `items` not already stored under `key`, as told by
+/// `same`, keeping them sorted by `order`. Returns how many were added.
+async fn merge<S, T, O>(
+    storage: &S,
+    key: &str,
+    items: Vec<T>,
+    same: impl Fn(&T, &T) -> bool,
+    order: impl Fn(&T) -> O,
+) -> Result<usize, ArchiveError>
+where
+    S: Storage + ?Sized,
+    T: Serialize + DeserializeOwned,
+    O: Ord,
+{
+    let mut stored: Vec<T> = load(storage, key).await?;
+    let before = stored.len();
+    for item in items {
+        if !stored.iter().any(|existing| same(existing, &item)) {
+            stored.push(item);
+        }
+    }
+
+    let added = stored.len() - before;
+    if added > 0 {
+        stored.sort_by_key(|item| order(item));
+        storage.put(key, &serde_json::to_string(&stored)?).await?;
+    }
+    Ok(added)
+}
+
+pub async fn load_dividends<S>(storage: &S, symbol: &str) -> Result<Vec<Dividend>, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    load(storage, &dividends_key(symbol)).await
+}
+
+/// Archives dividends of `symbol`, oldest ex-date first and those still
+/// `TBA` last. A dividend already stored for the same security type, kind,
+/// ex-date and record date is kept as is.
+pub async fn store_dividends<S>(
+    storage: &S,
+    symbol: &str,
+    dividends: Vec<Dividend>,
+) -> Result<usize, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    merge(
+        storage,
+        &dividends_key(symbol),
+        dividends,
+        |a, b| {
+            a.security_type == b.security_type
+                && a.kind == b.kind
+                && a.ex_date == b.ex_date
+                && a.record_date == b.record_date
+        },
+        |dividend| (dividend.ex_date.is_none(), dividend.ex_date),
+    )
+    .await
+}
+
+pub async fn load_disclosures<S>(storage: &S, symbol: &str) -> Result<Vec<Disclosure>, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    load(storage, &disclosures_key(symbol)).await
+}
+
+/// Archives disclosures of `symbol`, oldest first, skipping any with the
+/// same template and announcement time as one already stored.
+pub async fn store_disclosures<S>(
+    storage: &S,
+    symbol: &str,
+    disclosures: Vec<Disclosure>,
+) -> Result<usize, ArchiveError>
+where
+    S: Storage + ?Sized,
+{
+    merge(
+        storage,
+        &disclosures_key(symbol),
+        disclosures,
+        |a, b| a.template == b.template && a.announced_at == b.announced_at,
+        |disclosure| disclosure.announced_at,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use futures::executor::block_on;
+    use tote::corporate_actions::ActionKind;
+    use tote::disclosures::{parse_disclosures, parse_dividends};
+
+    const DIVIDENDS: &str = include_str!("../../tote/tests/fixtures/edge_dividends.html");
+    const DISCLOSURES: &str = include_str!("../../tote/tests/fixtures/edge_disclosures.html");
+
+    #[test]
+    fn archives_dividends_once() {
+        let storage = MemoryStorage::new();
+        let parsed = parse_dividends("SM", DIVIDENDS);
+        let mut dividends = parsed.clone();
+        dividends.reverse();
+
+        assert_eq!(
+            block_on(store_dividends(&storage, "sm", dividends.clone())).unwrap(),
+            4
+        );
+        assert_eq!(
+            block_on(store_dividends(&storage, "SM", dividends)).unwrap(),
+            0
+        );
+
+        let stored = block_on(load_dividends(&storage, "SM")).unwrap();
+        assert_eq!(stored, parsed);
+        assert_eq!(stored[3].ex_date, None);
+        assert_eq!(
+            stored[0].corporate_action().unwrap().kind,
+            ActionKind::CashDividend { amount: 2.76 }
+        );
+        assert!(block_on(load_dividends(&storage, "ALI"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn archives_disclosures_per_symbol() {
+        let storage = MemoryStorage::new();
+        let disclosures = parse_disclosures(DISCLOSURES);
+        let sm: Vec<Disclosure> = disclosures
+            .into_iter()
+            .filter(|disclosure| disclosure.company.starts_with("SM "))
+            .collect();
+
+        assert_eq!(
+            block_on(store_disclosures(&storage, "SM", sm.clone())).unwrap(),
+            1
+        );
+        let stored = block_on(load_disclosures(&storage, "SM")).unwrap();
+        assert_eq!(stored, sm);
+        assert!(stored[0].is_dividend_declaration());
+        assert!(block_on(load_disclosures(&storage, "BDO"))
+            .unwrap()
+            .is_empty());
+
+        block_on(storage.put(&disclosures_key("SM"), "{")).unwrap();
+        assert!(matches!(
+            block_on(load_disclosures(&storage, "SM")),
+            Err(ArchiveError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tote::errors::{CommonError, OHLCVDataError};
+use wasm_bindgen::JsValue;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("storage binding `{0}` is not available")]
+    Unavailable(String),
+    #[error("storage request failed: {0}")]
+    Request(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("expected an ISO 8601 date or timestamp, got `{0}`")]
+    InvalidTimestamp(String),
+    #[error("{0} is not a trading day")]
+    NonTradingDay(NaiveDate),
+    #[error("no archived snapshot for {0}")]
+    MissingSnapshot(NaiveDate),
+    #[error("no archived quote for `{symbol}` on {date}")]
+    SymbolNotFound { symbol: String, date: NaiveDate },
+    #[error("malformed archived snapshot: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+#[derive(Error, Debug)]
+pub enum WatchlistError {
+    #[error("watchlist names must be 1 to {0} characters")]
+    InvalidName(usize),
+    #[error("no more than {0} watchlists per token")]
+    TooManyWatchlists(usize),
+    #[error("watchlists hold no more than {0} symbols")]
+    TooManySymbols(usize),
+    #[error("`{0}` is not in the security list")]
+    UnknownSymbol(String),
+    #[error("no watchlist {0}")]
+    NotFound(u32),
+    #[error("malformed watchlists: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+#[derive(Error, Debug)]
+pub enum StreamError {
+    #[error("stream client disconnected: {0}")]
+    Disconnected(String),
+}
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("could not build upstream request: {0}")]
+    Request(String),
+    #[error("upstream returned HTTP {status} for {url}")]
+    Status { status: u16, url: String },
+    #[error("upstream request failed: {0}")]
+    Network(String),
+    #[error("could not decode upstream response: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("no worker global scope to fetch from")]
+    NoGlobalScope,
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("`from` ({from}) is after `to` ({to})")]
+    InvalidRange { from: NaiveDate, to: NaiveDate },
+    #[error("history ranges are limited to {max} days, got {days}")]
+    RangeTooLong { days: i64, max: i64 },
+    #[error("no listed security with symbol `{0}`")]
+    UnknownSymbol(String),
+    #[error("invalid indicator spec `{0}`, expected e.g. `ema:12,rsi:14`")]
+    InvalidSpec(String),
+    #[error("invalid bar data: {0}")]
+    Data(#[from] OHLCVDataError),
+    #[error("could not compute indicator: {0}")]
+    Indicator(#[from] CommonError),
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+}
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("search query must not be empty")]
+    EmptyQuery,
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+}
+
+#[derive(Error, Debug)]
+pub enum JobError {
+    #[error("invalid snapshot schedule `{0}`, expected e.g. `15:05,15:30`")]
+    InvalidSchedule(String),
+    #[error("malformed job data: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+/// Error reported to API clients, carrying an HTTP status and a stable
+/// machine readable code.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WorkerError {
+    #[error("{0}")]
+    InvalidSymbol(String),
+    #[error("{0}")]
+    InvalidDate(String),
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("method not allowed")]
+    MethodNotAllowed,
+    #[error("{0}")]
+    Upstream(String),
+    #[error("{0}")]
+    UpstreamMalformed(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// The JSON body of an error response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub code: String,
+    pub message: String,
+}
+
+impl WorkerError {
+    pub fn status(&self) -> u16 {
+        match self {
+            WorkerError::InvalidSymbol(_)
+            | WorkerError::InvalidDate(_)
+            | WorkerError::InvalidArgument(_) => 400,
+            WorkerError::Unauthorized(_) => 401,
+            WorkerError::NotFound(_) => 404,
+            WorkerError::MethodNotAllowed => 405,
+            WorkerError::TooManyRequests(_) => 429,
+            WorkerError::Internal(_) => 500,
+            WorkerError::Upstream(_) | WorkerError::UpstreamMalformed(_) => 502,
+            WorkerError::Unavailable(_) => 503,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            WorkerError::InvalidSymbol(_) => "invalid_symbol",
+            WorkerError::InvalidDate(_) => "invalid_date",
+            WorkerError::InvalidArgument(_) => "invalid_argument",
+            WorkerError::Unauthorized(_) => "unauthorized",
+            WorkerError::NotFound(_) => "not_found",
+            WorkerError::MethodNotAllowed => "method_not_allowed",
+            WorkerError::TooManyRequests(_) => "too_many_requests",
+            WorkerError::Upstream(_) => "upstream_unavailable",
+            WorkerError::UpstreamMalformed(_) => "upstream_malformed",
+            WorkerError::Unavailable(_) => "storage_unavailable",
+            WorkerError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody {
+            status: self.status(),
+            code: self.code().to_string(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl From<FetchError> for WorkerError {
+    fn from(err: FetchError) -> Self {
+        match err {
+            FetchError::Decode(_) => WorkerError::UpstreamMalformed(err.to_string()),
+            _ => WorkerError::Upstream(err.to_string()),
+        }
+    }
+}
+
+impl From<StorageError> for WorkerError {
+    fn from(err: StorageError) -> Self {
+        WorkerError::Unavailable(err.to_string())
+    }
+}
+
+impl From<ArchiveError> for WorkerError {
+    fn from(err: ArchiveError) -> Self {
+        match err {
+            ArchiveError::InvalidTimestamp(_) | ArchiveError::NonTradingDay(_) => {
+                WorkerError::InvalidDate(err.to_string())
+            }
+            ArchiveError::MissingSnapshot(_) | ArchiveError::SymbolNotFound { .. } => {
+                WorkerError::NotFound(err.to_string())
+            }
+            ArchiveError::Malformed(_) => WorkerError::Internal(err.to_string()),
+            ArchiveError::Storage(err) => err.into(),
+        }
+    }
+}
+
+impl From<HistoryError> for WorkerError {
+    fn from(err: HistoryError) -> Self {
+        match err {
+            HistoryError::InvalidRange { .. }
+            | HistoryError::RangeTooLong { .. }
+            | HistoryError::InvalidSpec(_)
+            | HistoryError::Indicator(_) => WorkerError::InvalidArgument(err.to_string()),
+            HistoryError::UnknownSymbol(_) => WorkerError::NotFound(err.to_string()),
+            HistoryError::Data(_) => WorkerError::UpstreamMalformed(err.to_string()),
+            HistoryError::Archive(err) => err.into(),
+            HistoryError::Fetch(err) => err.into(),
+        }
+    }
+}
+
+impl From<SearchError> for WorkerError {
+    fn from(err: SearchError) -> Self {
+        match err {
+            SearchError::EmptyQuery => WorkerError::InvalidArgument(err.to_string()),
+            SearchError::Fetch(err) => err.into(),
+        }
+    }
+}
+
+impl From<JobError> for WorkerError {
+    fn from(err: JobError) -> Self {
+        match err {
+            JobError::InvalidSchedule(_) | JobError::Malformed(_) => {
+                WorkerError::Internal(err.to_string())
+            }
+            JobError::Archive(err) => err.into(),
+            JobError::Fetch(err) => err.into(),
+            JobError::Storage(err) => err.into(),
+        }
+    }
+}
+
+impl From<WatchlistError> for WorkerError {
+    fn from(err: WatchlistError) -> Self {
+        match err {
+            WatchlistError::InvalidName(_)
+            | WatchlistError::TooManyWatchlists(_)
+            | WatchlistError::TooManySymbols(_) => WorkerError::InvalidArgument(err.to_string()),
+            WatchlistError::UnknownSymbol(_) => WorkerError::InvalidSymbol(err.to_string()),
+            WatchlistError::NotFound(_) => WorkerError::NotFound(err.to_string()),
+            WatchlistError::Malformed(_) => WorkerError::Internal(err.to_string()),
+            WatchlistError::Storage(err) => err.into(),
+        }
+    }
+}
+
+/// Errors reach JavaScript as an [`ErrorBody`] object so `worker.js` can
+/// answer with the right status.
+impl From<WorkerError> for JsValue {
+    fn from(err: WorkerError) -> Self {
+        let body = serde_json::to_string(&err.body()).unwrap_or_default();
+        js_sys::JSON::parse(&body).unwrap_or_else(|_| JsValue::from_str(&err.to_string()))
+    }
+}
+
+macro_rules! impl_into_js {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsValue {
+            fn from(err: $t) -> Self {
+                WorkerError::from(err).into()
+            }
+        }
+    )*};
+}
+
+impl_into_js!(
+    StorageError,
+    ArchiveError,
+    FetchError,
+    HistoryError,
+    SearchError,
+    JobError,
+    WatchlistError
+);
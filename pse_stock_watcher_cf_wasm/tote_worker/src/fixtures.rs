@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use crate::errors::FetchError;
+use crate::upstream::{Endpoint, Fetcher};
+
+/// Serves recorded PSE responses from a directory holding one
+/// `<method>.json` file per endpoint, e.g. `getRecentSecurityQuoteData.json`.
+/// Lets tests and local development run without reaching PSE.
+#[derive(Debug, Clone)]
+pub struct FixtureFetcher {
+    dir: PathBuf,
+}
+
+impl FixtureFetcher {
+    pub fn from_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file answering `endpoint`. Arguments are ignored, so every
+    /// security gets the same recorded history.
+    pub fn path(&self, endpoint: &Endpoint) -> PathBuf {
+        self.dir.join(format!("{}.json", endpoint.method()))
+    }
+}
+
+#[async_trait(?Send)]
+impl Fetcher for FixtureFetcher {
+    async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError> {
+        let path = self.path(endpoint);
+        std::fs::read_to_string(&path).map_err(|_| FetchError::Status {
+            status: 404,
+            url: path.display().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{ErrorBody, WorkerError};
+    use crate::history::get_history;
+    use crate::http::{HttpRequest, Method};
+    use crate::router::Router;
+    use crate::storage::MemoryStorage;
+    use crate::upstream::fetch_json;
+    use crate::Stocks;
+    use chrono::NaiveDate;
+    use futures::executor::block_on;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    struct PageFetcher(&'static str);
+
+    #[async_trait(?Send)]
+    impl Fetcher for PageFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 10, day).unwrap()
+    }
+
+    #[test]
+    fn decodes_recorded_securities() {
+        let fetcher = FixtureFetcher::from_dir(FIXTURES);
+        let stocks: Stocks =
+            block_on(fetch_json(&fetcher, &Endpoint::SecuritiesAndIndices)).unwrap();
+
+        assert!(stocks.as_of.is_some());
+        let sm = stocks
+            .stocks
+            .iter()
+            .find(|stock| stock.security_symbol == "SM")
+            .unwrap();
+        assert_eq!(sm.last_traded_price, 1010.0);
+        assert_eq!(sm.total_volume, 486_210);
+    }
+
+    #[test]
+    fn builds_history_from_recorded_quotes() {
+        let fetcher = FixtureFetcher::from_dir(FIXTURES);
+        let history = block_on(get_history(
+            &fetcher,
+            &MemoryStorage::new(),
+            "SM",
+            date(12),
+            date(16),
+        ))
+        .unwrap();
+
+        assert_eq!(history.bars.len(), 5);
+        assert_eq!(history.bars[0].date, date(12));
+        assert_eq!(history.bars[3].close, 998.0);
+        assert_eq!(history.bars[4].volume, 486_210.0);
+    }
+
+    #[test]
+    fn maps_missing_and_malformed_responses() {
+        let missing = FixtureFetcher::from_dir(FIXTURES);
+        let err = block_on(missing.fetch(&Endpoint::TopActiveStocks)).unwrap_err();
+        assert!(matches!(err, FetchError::Status { status: 404, .. }));
+
+        let page = PageFetcher(include_str!("../tests/fixtures/maintenance.html"));
+        let err: WorkerError = block_on(fetch_json::<Stocks, _>(
+            &page,
+            &Endpoint::SecuritiesAndIndices,
+        ))
+        .unwrap_err()
+        .into();
+        assert_eq!(err.code(), "upstream_malformed");
+        assert_eq!(err.status(), 502);
+    }
+
+    #[test]
+    fn router_answers_with_json_errors() {
+        let router = Router::new(FixtureFetcher::from_dir(FIXTURES));
+
+        let response = block_on(router.handle(&HttpRequest::new(
+            Method::Get,
+            "https://worker.test/stocks/get_top_security?limit=nope",
+        )));
+        assert_eq!(response.status, 400);
+        let body: ErrorBody = serde_json::from_str(response.body.as_ref().unwrap()).unwrap();
+        assert_eq!(body.code, "invalid_argument");
+
+        let response = block_on(router.handle(&HttpRequest::new(
+            Method::Get,
+            "https://worker.test/stocks/get_top_security",
+        )));
+        assert_eq!(response.status, 502);
+        let body: ErrorBody = serde_json::from_str(response.body.as_ref().unwrap()).unwrap();
+        assert_eq!(body.code, "upstream_unavailable");
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use tote::errors::OHLCVDataError;
+use tote::stock::de_number;
+use tote::{BarSeries, OHLCVData};
+
+use crate::archive::{self, load_snapshot};
+use crate::calendar;
+use crate::errors::{ArchiveError, HistoryError};
+use crate::security::resolve_security;
+use crate::storage::Storage;
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+
+/// Longest range, in days, a single history request may span.
+pub const MAX_HISTORY_DAYS: i64 = 366;
+
+/// Most archived snapshots read to fill one history. Each is a KV read of
+/// the whole market, so only the most recent gaps are filled.
+pub const MAX_ARCHIVE_READS: usize = 30;
+
+/// Formats PSE has used for `tradingDate` in quote history records.
+const TRADING_DATE_FORMATS: [&str; 4] = [
+    "%b %d, %Y %H:%M:%S",
+    "%b %d, %Y %I:%M:%S %p",
+    "%Y-%m-%d %H:%M:%S",
+    "%m/%d/%Y %H:%M:%S",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BarSource {
+    /// A daily bar from `getRecentSecurityQuoteData`.
+    Upstream,
+    /// A flat bar at the last traded price of an archived snapshot.
+    Archive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryBar {
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub source: BarSource,
+}
+
+impl TryFrom<&HistoryBar> for OHLCVData {
+    type Error = OHLCVDataError;
+
+    fn try_from(bar: &HistoryBar) -> Result<Self, Self::Error> {
+        OHLCVData::builder()
+            .open(bar.open)
+            .high(bar.high)
+            .low(bar.low)
+            .close(bar.close)
+            .volume(bar.volume)
+            .build()
+    }
+}
+
+/// Daily bars of `symbol` between `from` and `to` inclusive, oldest first.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    pub symbol: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub bars: Vec<HistoryBar>,
+}
+
+impl History {
+    pub fn series(&self) -> Result<BarSeries, OHLCVDataError> {
+        let mut series = BarSeries::new(self.symbol.as_str());
+        for bar in &self.bars {
+            series.push(bar.date, OHLCVData::try_from(bar)?)?;
+        }
+        Ok(series)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuoteRecord {
+    #[serde(deserialize_with = "de_trading_date")]
+    trading_date: NaiveDate,
+    #[serde(deserialize_with = "de_number")]
+    sq_open: f64,
+    #[serde(deserialize_with = "de_number")]
+    sq_high: f64,
+    #[serde(deserialize_with = "de_number")]
+    sq_low: f64,
+    #[serde(deserialize_with = "de_number")]
+    sq_close: f64,
+    #[serde(deserialize_with = "de_number")]
+    total_volume: f64,
+}
+
+/// The response of `getRecentSecurityQuoteData`.
+#[derive(Debug, Deserialize)]
+struct RecentQuotes {
+    #[serde(default)]
+    records: Vec<QuoteRecord>,
+}
+
+fn de_trading_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let value = value.trim();
+    TRADING_DATE_FORMATS
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|instant| instant.date())
+        .or_else(|| archive::parse_date(value).ok())
+        .ok_or_else(|| de::Error::custom(format!("unrecognized trading date `{}`", value)))
+}
+
+pub fn validate_range(from: NaiveDate, to: NaiveDate) -> Result<(), HistoryError> {
+    if from > to {
+        return Err(HistoryError::InvalidRange { from, to });
+    }
+
+    let days = to.signed_duration_since(from).num_days() + 1;
+    if days > MAX_HISTORY_DAYS {
+        return Err(HistoryError::RangeTooLong {
+            days,
+            max: MAX_HISTORY_DAYS,
+        });
+    }
+    Ok(())
+}
+
+/// Builds the daily history of `symbol` from PSE's recent quotes, filling
+/// up to [`MAX_ARCHIVE_READS`] of the latest trading days PSE no longer
+/// reports from archived snapshots.
+pub async fn get_history<F, S>(
+    fetcher: &F,
+    storage: &S,
+    symbol: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<History, HistoryError>
+where
+    F: Fetcher + ?Sized,
+    S: Storage + ?Sized,
+{
+    validate_range(from, to)?;
+
+    let security = resolve_security(fetcher, symbol).await?;
+    let endpoint = Endpoint::RecentSecurityQuoteData {
+        security: security.security_id,
+    };
+    let quotes: RecentQuotes = fetch_json(fetcher, &endpoint).await?;
+
+    let mut bars: BTreeMap<NaiveDate, HistoryBar> = quotes
+        .records
+        .into_iter()
+        .filter(|record| record.trading_date >= from && record.trading_date <= to)
+        .map(|record| {
+            let bar = HistoryBar {
+                date: record.trading_date,
+                open: record.sq_open,
+                high: record.sq_high,
+                low: record.sq_low,
+                close: record.sq_close,
+                volume: record.total_volume,
+                source: BarSource::Upstream,
+            };
+            (bar.date, bar)
+        })
+        .collect();
+
+    let missing: Vec<NaiveDate> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .filter(|date| calendar::is_trading_day(*date) && !bars.contains_key(date))
+        .collect();
+    for date in missing.into_iter().rev().take(MAX_ARCHIVE_READS) {
+        match load_snapshot(storage, date).await {
+            Ok(snapshot) => {
+                let stock = snapshot.stocks.into_iter().find(|stock| {
+                    stock
+                        .security_symbol
+                        .eq_ignore_ascii_case(&security.security_symbol)
+                });
+                if let Some(stock) = stock {
+                    let price = stock.last_traded_price;
+                    bars.insert(
+                        date,
+                        HistoryBar {
+                            date,
+                            open: price,
+                            high: price,
+                            low: price,
+                            close: price,
+                            volume: stock.total_volume as f64,
+                            source: BarSource::Archive,
+                        },
+                    );
+                }
+            }
+            Err(ArchiveError::MissingSnapshot(_)) => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(History {
+        symbol: security.security_symbol,
+        from,
+        to,
+        bars: bars.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::archive::{snapshot_key, Snapshot};
+    use crate::errors::{FetchError, StorageError};
+    use crate::storage::MemoryStorage;
+    use crate::{Indicator, Stock};
+    use async_trait::async_trait;
+    use chrono::Datelike;
+    use futures::executor::block_on;
+
+    /// Answers symbol searches and quote history for SM only.
+    pub(crate) struct PseFetcher;
+
+    #[async_trait(?Send)]
+    impl Fetcher for PseFetcher {
+        async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError> {
+            match endpoint {
+                Endpoint::FindSecurityOrCompany { .. } => Ok(r#"{"count":2,"records":[
+                    {"securityId":"521","companyId":"600","securitySymbol":"SMPH","securityName":"SM Prime Holdings, Inc."},
+                    {"securityId":520,"companyId":599,"securitySymbol":"SM","securityName":"SM Investments Corporation"}
+                ]}"#
+                .to_string()),
+                Endpoint::RecentSecurityQuoteData { security: 520 } => Ok(r#"{"count":3,"records":[
+                    {"tradingDate":"Oct 16, 2020 00:00:00","sqOpen":"1,000.00","sqHigh":"1,020.00","sqLow":"995.00","sqClose":"1,010.00","totalVolume":"120,500"},
+                    {"tradingDate":"Oct 15, 2020 00:00:00","sqOpen":990,"sqHigh":1005,"sqLow":985,"sqClose":1000,"totalVolume":98000},
+                    {"tradingDate":"Oct 14, 2020 00:00:00","sqOpen":980,"sqHigh":995,"sqLow":975,"sqClose":990,"totalVolume":87000}
+                ]}"#
+                .to_string()),
+                _ => Err(FetchError::Status {
+                    status: 404,
+                    url: endpoint.url("https://pse.test"),
+                }),
+            }
+        }
+    }
+
+    pub(crate) fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 10, day).unwrap()
+    }
+
+    /// Archives SM at `price` on each of `days`.
+    pub(crate) fn archive_with(days: &[(u32, f64)]) -> MemoryStorage {
+        let storage = MemoryStorage::new();
+        for (day, price) in days {
+            let snapshot = Snapshot {
+                date: date(*day),
+                as_of: None,
+                stocks: vec![Stock {
+                    total_volume: 1000,
+                    indicator: Indicator::Unchanged,
+                    perc_change_close: 0.0,
+                    last_traded_price: *price,
+                    security_alias: "SM Investments Corporation".to_string(),
+                    security_symbol: "SM".to_string(),
+                }],
+            };
+            block_on(storage.put(
+                &snapshot_key(snapshot.date),
+                &serde_json::to_string(&snapshot).unwrap(),
+            ))
+            .unwrap();
+        }
+        storage
+    }
+
+    #[test]
+    fn merges_upstream_and_archived_bars() {
+        let storage = archive_with(&[(9, 970.0), (12, 975.0), (16, 1.0)]);
+        let history =
+            block_on(get_history(&PseFetcher, &storage, "sm", date(9), date(16))).unwrap();
+
+        assert_eq!(history.symbol, "SM");
+        let dates: Vec<u32> = history.bars.iter().map(|bar| bar.date.day()).collect();
+        assert_eq!(dates, vec![9, 12, 14, 15, 16]);
+
+        assert_eq!(history.bars[0].source, BarSource::Archive);
+        assert_eq!(history.bars[0].close, 970.0);
+        let last = history.bars.last().unwrap();
+        assert_eq!(last.source, BarSource::Upstream);
+        assert_eq!(last.close, 1010.0);
+        assert_eq!(last.volume, 120_500.0);
+
+        let series = history.series().unwrap();
+        assert_eq!(series.len(), 5);
+        assert_eq!(series.closes()[2], 990.0);
+    }
+
+    /// Counts reads on top of a [`MemoryStorage`].
+    struct CountingStorage {
+        inner: MemoryStorage,
+        reads: std::cell::Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl Storage for CountingStorage {
+        async fn get(&self, key: &str) -> Result<Option<String>, StorageError> {
+            self.reads.set(self.reads.get() + 1);
+            self.inner.get(key).await
+        }
+
+        async fn put(&self, key: &str, value: &str) -> Result<(), StorageError> {
+            self.inner.put(key, value).await
+        }
+
+        async fn put_expiring(
+            &self,
+            key: &str,
+            value: &str,
+            ttl_seconds: u64,
+        ) -> Result<(), StorageError> {
+            self.inner.put_expiring(key, value, ttl_seconds).await
+        }
+    }
+
+    #[test]
+    fn bounds_archive_reads_to_recent_days() {
+        let storage = CountingStorage {
+            inner: archive_with(&[(12, 975.0), (13, 980.0)]),
+            reads: std::cell::Cell::new(0),
+        };
+        let from = date(16) - chrono::Duration::days(MAX_HISTORY_DAYS - 1);
+        let history = block_on(get_history(&PseFetcher, &storage, "SM", from, date(16))).unwrap();
+
+        assert_eq!(storage.reads.get(), MAX_ARCHIVE_READS);
+        let dates: Vec<u32> = history.bars.iter().map(|bar| bar.date.day()).collect();
+        assert_eq!(dates, vec![12, 13, 14, 15, 16]);
+    }
+
+    #[test]
+    fn rejects_bad_ranges_and_symbols() {
+        let storage = MemoryStorage::new();
+        assert!(matches!(
+            block_on(get_history(&PseFetcher, &storage, "SM", date(16), date(9))),
+            Err(HistoryError::InvalidRange { .. })
+        ));
+        assert!(matches!(
+            block_on(get_history(
+                &PseFetcher,
+                &storage,
+                "SM",
+                date(1) - chrono::Duration::days(400),
+                date(1)
+            )),
+            Err(HistoryError::RangeTooLong { .. })
+        ));
+        assert!(matches!(
+            block_on(get_history(&PseFetcher, &storage, "SMP", date(9), date(16))),
+            Err(HistoryError::UnknownSymbol(_))
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::errors::WorkerError;
+use crate::upstream::decode_component;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+    Other,
+}
+
+impl Method {
+    pub fn parse(method: &str) -> Self {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "PATCH" => Method::Patch,
+            "DELETE" => Method::Delete,
+            "OPTIONS" => Method::Options,
+            _ => Method::Other,
+        }
+    }
+}
+
+/// An incoming request, decoupled from `web_sys` so routing can be tested
+/// natively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl HttpRequest {
+    /// Builds a request from an absolute URL or an origin-relative path with
+    /// an optional query string.
+    pub fn new(method: Method, url: &str) -> Self {
+        let without_scheme = match url.find("://") {
+            Some(i) => &url[i + 3..],
+            None => url,
+        };
+        let target = if url.contains("://") {
+            match without_scheme.find('/') {
+                Some(i) => &without_scheme[i..],
+                None => "/",
+            }
+        } else {
+            without_scheme
+        };
+        let target = target.split('#').next().unwrap_or("");
+
+        let (path, query) = match target.find('?') {
+            Some(i) => (&target[..i], &target[i + 1..]),
+            None => (target, ""),
+        };
+
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let key = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("");
+                (decode_component(key), decode_component(value))
+            })
+            .collect();
+
+        Self {
+            method,
+            path: if path.is_empty() { "/" } else { path }.to_string(),
+            query,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body<S: Into<String>>(mut self, body: S) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Case-insensitive header lookup.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads the body too, for methods that carry one.
+    pub async fn from_web(request: &web_sys::Request) -> Result<Self, JsValue> {
+        let mut result = Self::new(Method::parse(&request.method()), &request.url());
+        if let Some(entries) = js_sys::try_iter(&request.headers())? {
+            for entry in entries {
+                let entry: js_sys::Array = entry?.dyn_into()?;
+                if let (Some(name), Some(value)) =
+                    (entry.get(0).as_string(), entry.get(1).as_string())
+                {
+                    result.headers.push((name, value));
+                }
+            }
+        }
+        if !matches!(result.method, Method::Get | Method::Head | Method::Options) {
+            result.body = JsFuture::from(request.text()?).await?.as_string();
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn text<S: Into<String>>(status: u16, body: S) -> Self {
+        Self::new(status)
+            .with_header("Content-Type", "text/plain;charset=UTF-8")
+            .with_body(body)
+    }
+
+    pub fn json<S: Into<String>>(status: u16, body: S) -> Self {
+        Self::new(status)
+            .with_header("Content-Type", "application/json;charset=UTF-8")
+            .with_body(body)
+    }
+
+    /// A JSON [`ErrorBody`](crate::errors::ErrorBody) with the status of `err`.
+    pub fn error(err: &WorkerError) -> Self {
+        let body = serde_json::to_string(&err.body()).unwrap_or_default();
+        Self::json(err.status(), body)
+    }
+
+    pub fn with_body<S: Into<String>>(mut self, body: S) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets `name`, replacing any value it already had.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds another value for `name`, keeping the existing ones.
+    pub fn append_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn web_init(&self) -> Result<web_sys::ResponseInit, JsValue> {
+        let headers = web_sys::Headers::new()?;
+        for (name, value) in &self.headers {
+            headers.append(name, value)?;
+        }
+
+        let init = web_sys::ResponseInit::new();
+        init.set_status(self.status);
+        init.set_headers(&headers);
+        Ok(init)
+    }
+
+    pub fn into_web(self) -> Result<web_sys::Response, JsValue> {
+        web_sys::Response::new_with_opt_str_and_init(self.body.as_deref(), &self.web_init()?)
+    }
+
+    /// Like [`HttpResponse::into_web`], with `body` streamed in place of the
+    /// response's own body.
+    pub fn into_web_stream(
+        self,
+        body: &web_sys::ReadableStream,
+    ) -> Result<web_sys::Response, JsValue> {
+        web_sys::Response::new_with_opt_readable_stream_and_init(Some(body), &self.web_init()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_request_urls() {
+        let request = HttpRequest::new(
+            Method::Get,
+            "https://tote.example.workers.dev/stocks/get_stock?symbol=SM%20PH&q=a+b#top",
+        );
+        assert_eq!(request.path, "/stocks/get_stock");
+        assert_eq!(request.query_param("symbol"), Some("SM PH"));
+        assert_eq!(request.query_param("q"), Some("a b"));
+        assert_eq!(request.query_param("missing"), None);
+
+        assert_eq!(
+            HttpRequest::new(Method::Get, "https://example.com").path,
+            "/"
+        );
+        assert_eq!(HttpRequest::new(Method::Get, "/test?").path, "/test");
+    }
+
+    #[test]
+    fn headers_are_case_insensitive() {
+        let request = HttpRequest::new(Method::Options, "/").with_header("Origin", "a");
+        assert_eq!(request.header("origin"), Some("a"));
+
+        let response = HttpResponse::new(200)
+            .with_header("Vary", "Accept")
+            .append_header("vary", "Origin")
+            .with_header("content-type", "text/html");
+        assert_eq!(response.headers.len(), 3);
+        assert_eq!(response.header("Content-Type"), Some("text/html"));
+    }
+}
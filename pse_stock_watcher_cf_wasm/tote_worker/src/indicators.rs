@@ -0,0 +1,180 @@
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tote::batch;
+use tote::indicators::{ExponentialMovingAverage, RelativeStrengthIndex};
+use tote::BarSeries;
+
+use crate::errors::HistoryError;
+use crate::history::{get_history, MAX_HISTORY_DAYS};
+use crate::storage::Storage;
+use crate::upstream::Fetcher;
+
+/// An indicator to run over a symbol's history, written `ema:12` or `rsi:14`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndicatorSpec {
+    Ema(usize),
+    Rsi(usize),
+}
+
+impl FromStr for IndicatorSpec {
+    type Err = HistoryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || HistoryError::InvalidSpec(s.to_string());
+        let mut parts = s.trim().splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim().to_lowercase();
+        let period: usize = parts
+            .next()
+            .and_then(|period| period.trim().parse().ok())
+            .filter(|period| *period > 0)
+            .ok_or_else(invalid)?;
+
+        match name.as_str() {
+            "ema" => Ok(IndicatorSpec::Ema(period)),
+            "rsi" => Ok(IndicatorSpec::Rsi(period)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Parses a comma separated list such as `ema:12,ema:26,rsi:14`.
+pub fn parse_specs(spec: &str) -> Result<Vec<IndicatorSpec>, HistoryError> {
+    if spec.trim().is_empty() {
+        return Err(HistoryError::InvalidSpec(spec.to_string()));
+    }
+    spec.split(',').map(str::parse).collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndicatorPoint {
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndicatorSeries {
+    pub name: String,
+    pub values: Vec<IndicatorPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Indicators {
+    pub symbol: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub indicators: Vec<IndicatorSeries>,
+}
+
+pub fn compute(
+    series: &BarSeries,
+    specs: &[IndicatorSpec],
+) -> Result<Vec<IndicatorSeries>, HistoryError> {
+    specs
+        .iter()
+        .map(|spec| {
+            let (name, values) = match *spec {
+                IndicatorSpec::Ema(period) => (
+                    ExponentialMovingAverage::new(period)?.to_string(),
+                    batch::ema_series(series, period)?,
+                ),
+                IndicatorSpec::Rsi(period) => (
+                    RelativeStrengthIndex::new(period)?.to_string(),
+                    batch::rsi_series(series, period)?,
+                ),
+            };
+
+            Ok(IndicatorSeries {
+                name,
+                values: series
+                    .dates()
+                    .iter()
+                    .zip(values)
+                    .map(|(date, value)| IndicatorPoint { date: *date, value })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Runs `spec` over the longest history of `symbol` ending on `today`.
+pub async fn get_indicators<F, S>(
+    fetcher: &F,
+    storage: &S,
+    symbol: &str,
+    spec: &str,
+    today: NaiveDate,
+) -> Result<Indicators, HistoryError>
+where
+    F: Fetcher + ?Sized,
+    S: Storage + ?Sized,
+{
+    let specs = parse_specs(spec)?;
+    let from = today - Duration::days(MAX_HISTORY_DAYS - 1);
+    let history = get_history(fetcher, storage, symbol, from, today).await?;
+    let series = history.series()?;
+
+    Ok(Indicators {
+        indicators: compute(&series, &specs)?,
+        symbol: history.symbol,
+        from: history.from,
+        to: history.to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::tests::{archive_with, date, PseFetcher};
+    use futures::executor::block_on;
+    use tote::data_traits::Next;
+
+    #[test]
+    fn parses_specs() {
+        assert_eq!(
+            parse_specs("ema:12, EMA:26,rsi:14").unwrap(),
+            vec![
+                IndicatorSpec::Ema(12),
+                IndicatorSpec::Ema(26),
+                IndicatorSpec::Rsi(14)
+            ]
+        );
+        for spec in &["", "ema", "ema:0", "sma:5", "ema:5,"] {
+            assert!(parse_specs(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn computes_indicators_over_history() {
+        let storage = archive_with(&[(12, 975.0), (13, 985.0)]);
+        let result = block_on(get_indicators(
+            &PseFetcher,
+            &storage,
+            "SM",
+            "ema:3,rsi:2",
+            date(16),
+        ))
+        .unwrap();
+
+        assert_eq!(result.to, date(16));
+        assert_eq!(result.indicators.len(), 2);
+        assert_eq!(result.indicators[0].name, "EMA(3)");
+        assert_eq!(result.indicators[1].name, "RSI(2)");
+
+        let closes = [975.0, 985.0, 990.0, 1000.0, 1010.0];
+        let mut ema = ExponentialMovingAverage::new(3).unwrap();
+        let expected: Vec<f64> = closes.iter().map(|close| ema.next(*close)).collect();
+        let values: Vec<f64> = result.indicators[0]
+            .values
+            .iter()
+            .map(|point| point.value)
+            .collect();
+        assert_eq!(values, expected);
+        assert_eq!(result.indicators[0].values[0].date, date(12));
+    }
+}
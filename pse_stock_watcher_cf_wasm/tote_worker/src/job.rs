@@ -0,0 +1,284 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{self, ArchiveSummary};
+use crate::calendar::{self, Clock};
+use crate::errors::JobError;
+use crate::storage::Storage;
+use crate::summary::{summary_key, DailySummary};
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+use crate::Stocks;
+
+/// Key of the status of the most recent snapshot job run.
+pub const JOB_STATUS_KEY: &str = "job:snapshot";
+
+/// Manila times the snapshot job runs at, as a comma separated list such as
+/// `15:05,15:30`. Matches the cron triggers in `wrangler.toml`.
+pub const SCHEDULE_BINDING: &str = "SNAPSHOT_SCHEDULE";
+
+/// When the snapshot job should run: shortly after the close, and again once
+/// PSE has published the final figures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub times: Vec<NaiveTime>,
+    /// How far a trigger may fire from a configured time and still count.
+    pub tolerance: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            times: vec![
+                NaiveTime::from_hms_opt(15, 5, 0).unwrap(),
+                NaiveTime::from_hms_opt(15, 30, 0).unwrap(),
+            ],
+            tolerance: Duration::minutes(10),
+        }
+    }
+}
+
+impl Schedule {
+    pub fn parse(times: &str) -> Result<Self, JobError> {
+        let times = times
+            .split(',')
+            .map(|time| {
+                NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                    .map_err(|_| JobError::InvalidSchedule(times.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            times,
+            ..Self::default()
+        })
+    }
+
+    /// Whether a trigger firing at `now` should run the job.
+    pub fn is_due(&self, now: &DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&calendar::manila());
+        if !calendar::is_trading_day(local.date_naive()) {
+            return false;
+        }
+
+        self.times.iter().any(|time| {
+            let scheduled = local.date_naive().and_time(*time);
+            let offset = local.naive_local().signed_duration_since(scheduled);
+            offset.num_seconds().abs() <= self.tolerance.num_seconds()
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobOutcome {
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub ran_at: DateTime<Utc>,
+    pub outcome: JobOutcome,
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    #[serde(default)]
+    pub archive: Option<ArchiveSummary>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+pub async fn load_status<S>(storage: &S) -> Result<Option<JobStatus>, JobError>
+where
+    S: Storage + ?Sized,
+{
+    match storage.get(JOB_STATUS_KEY).await? {
+        Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+        None => Ok(None),
+    }
+}
+
+async fn snapshot<F, S>(
+    fetcher: &F,
+    storage: &S,
+    now: &DateTime<Utc>,
+) -> Result<JobStatus, JobError>
+where
+    F: Fetcher + ?Sized,
+    S: Storage + ?Sized,
+{
+    let stocks: Stocks = fetch_json(fetcher, &Endpoint::SecuritiesAndIndices).await?;
+    let archived = archive::archive_snapshot(storage, stocks, now).await?;
+    let snapshot = archive::load_snapshot(storage, archived.date).await?;
+
+    let summary = DailySummary::new(archived.date, &snapshot.stocks);
+    storage
+        .put(
+            &summary_key(archived.date),
+            &serde_json::to_string(&summary)?,
+        )
+        .await?;
+
+    Ok(JobStatus {
+        ran_at: *now,
+        outcome: JobOutcome::Succeeded,
+        date: Some(archived.date),
+        archive: Some(archived),
+        message: None,
+    })
+}
+
+/// Archives the current securities list and its daily summary when
+/// `schedule` says a trigger at the clock's time is due, or always when
+/// `force` is set. Runs that do work record their status under
+/// [`JOB_STATUS_KEY`], failures included.
+pub async fn run_snapshot_job<F, S, C>(
+    fetcher: &F,
+    storage: &S,
+    clock: &C,
+    schedule: &Schedule,
+    force: bool,
+) -> Result<JobStatus, JobError>
+where
+    F: Fetcher + ?Sized,
+    S: Storage + ?Sized,
+    C: Clock + ?Sized,
+{
+    let now = clock.now();
+    if !force && !schedule.is_due(&now) {
+        return Ok(JobStatus {
+            ran_at: now,
+            outcome: JobOutcome::Skipped,
+            date: None,
+            archive: None,
+            message: Some("no snapshot scheduled at this time".to_string()),
+        });
+    }
+
+    let result = snapshot(fetcher, storage, &now).await;
+    let status = match &result {
+        Ok(status) => status.clone(),
+        Err(err) => JobStatus {
+            ran_at: now,
+            outcome: JobOutcome::Failed,
+            date: Some(calendar::trading_date(&now)),
+            archive: None,
+            message: Some(err.to_string()),
+        },
+    };
+    storage
+        .put(JOB_STATUS_KEY, &serde_json::to_string(&status)?)
+        .await?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::ArchiveStatus;
+    use crate::calendar::FixedClock;
+    use crate::errors::FetchError;
+    use crate::storage::MemoryStorage;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+
+    struct ListFetcher(Option<&'static str>);
+
+    #[async_trait(?Send)]
+    impl Fetcher for ListFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            self.0
+                .map(str::to_string)
+                .ok_or_else(|| FetchError::Network("offline".to_string()))
+        }
+    }
+
+    const LIST: &str = r#"[
+        {"totalVolume":"","indicator":"","percChangeClose":"","lastTradedPrice":"10/16/2020 03:00 PM","securityAlias":"Stock Update As of","securitySymbol":"Stock Update As of"},
+        {"totalVolume":"1,000","indicator":"U","percChangeClose":"1.25","lastTradedPrice":"1,010.00","securityAlias":"SM Investments Corporation","securitySymbol":"SM"},
+        {"totalVolume":"3,200","indicator":"D","percChangeClose":"-0.50","lastTradedPrice":"35.50","securityAlias":"Ayala Land, Inc.","securitySymbol":"ALI"}
+    ]"#;
+
+    fn manila(day: u32, hour: u32, minute: u32) -> FixedClock {
+        FixedClock(
+            calendar::manila()
+                .with_ymd_and_hms(2020, 10, day, hour, minute, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn schedule_runs_around_the_close_on_trading_days() {
+        let schedule = Schedule::default();
+        assert!(schedule.is_due(&manila(16, 15, 5).now()));
+        assert!(schedule.is_due(&manila(16, 15, 38).now()));
+        assert!(!schedule.is_due(&manila(16, 15, 18).now()));
+        assert!(!schedule.is_due(&manila(16, 10, 0).now()));
+        assert!(!schedule.is_due(&manila(17, 15, 5).now()));
+
+        let custom = Schedule::parse("14:50, 16:00").unwrap();
+        assert!(custom.is_due(&manila(16, 16, 0).now()));
+        assert!(Schedule::parse("3pm").is_err());
+    }
+
+    #[test]
+    fn archives_and_summarizes_when_due() {
+        let storage = MemoryStorage::new();
+        let fetcher = ListFetcher(Some(LIST));
+        let schedule = Schedule::default();
+
+        let skipped = block_on(run_snapshot_job(
+            &fetcher,
+            &storage,
+            &manila(16, 11, 0),
+            &schedule,
+            false,
+        ))
+        .unwrap();
+        assert_eq!(skipped.outcome, JobOutcome::Skipped);
+        assert!(storage.is_empty());
+
+        let status = block_on(run_snapshot_job(
+            &fetcher,
+            &storage,
+            &manila(16, 15, 5),
+            &schedule,
+            false,
+        ))
+        .unwrap();
+        assert_eq!(status.outcome, JobOutcome::Succeeded);
+        assert_eq!(status.archive.unwrap().status, ArchiveStatus::Created);
+
+        let date = NaiveDate::from_ymd_opt(2020, 10, 16).unwrap();
+        let summary: DailySummary =
+            serde_json::from_str(&block_on(storage.get(&summary_key(date))).unwrap().unwrap())
+                .unwrap();
+        assert_eq!(summary.top_gainers[0].security_symbol, "SM");
+        assert_eq!(summary.top_losers[0].security_symbol, "ALI");
+
+        let recorded = block_on(load_status(&storage)).unwrap().unwrap();
+        assert_eq!(recorded.date, Some(date));
+    }
+
+    #[test]
+    fn records_failures() {
+        let storage = MemoryStorage::new();
+        let result = block_on(run_snapshot_job(
+            &ListFetcher(None),
+            &storage,
+            &manila(17, 9, 0),
+            &Schedule::default(),
+            true,
+        ));
+        assert!(matches!(result, Err(JobError::Fetch(_))));
+
+        let recorded = block_on(load_status(&storage)).unwrap().unwrap();
+        assert_eq!(recorded.outcome, JobOutcome::Failed);
+        assert!(recorded.message.unwrap().contains("offline"));
+    }
+}
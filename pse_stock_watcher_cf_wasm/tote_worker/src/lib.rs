@@ -1,16 +1,54 @@
 extern crate js_sys;
 
+use std::rc::Rc;
+
 use cfg_if::cfg_if;
-use serde::{Deserialize, Serialize};
+use chrono::TimeZone;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, Response};
 
 #[allow(unused_imports)]
 use log::{info, Level};
 
+pub mod archive;
+pub mod cache;
+pub mod calendar;
+pub mod disclosures;
+pub mod errors;
+pub mod fixtures;
+pub mod history;
+pub mod http;
+pub mod indicators;
+pub mod job;
+pub mod openapi;
+pub mod router;
+pub mod search;
+pub mod security;
+pub mod storage;
+pub mod stream;
+pub mod summary;
+pub mod upstream;
 mod utils;
+pub mod validate;
+pub mod watchlist;
+
+use crate::cache::{cached_json, ResponseCache, StorageCache};
+use crate::calendar::{FixedClock, SystemClock};
+use crate::errors::{FetchError, WorkerError};
+use crate::http::{HttpRequest, HttpResponse};
+use crate::router::Router;
+use crate::storage::KvStorage;
+use crate::stream::{StreamConfig, Subscriptions, WebSink, WebTimer};
+use crate::upstream::{fetch_json, Endpoint, WorkerFetcher};
+use crate::utils::to_js;
+pub use tote::stock::{Indicator, Stock, Stocks};
+
+/// Name of the Workers KV namespace binding holding archived snapshots.
+pub const ARCHIVE_BINDING: &str = "TOTE_ARCHIVE";
+
+/// Name of the Workers KV namespace binding caching upstream responses.
+/// Responses are not cached when it is not bound.
+pub const CACHE_BINDING: &str = "TOTE_CACHE";
 
 cfg_if! {
     // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -24,192 +62,322 @@ cfg_if! {
 
 cfg_if! {
     if #[cfg(feature = "console_log")] {
+        /// Every entry point calls this, and a warm isolate keeps the logger
+        /// from the first call, so later calls only find it already set.
         fn init_log() {
-            console_log::init_with_level(Level::Trace).expect("error initializing log");
+            let _ = console_log::init_with_level(Level::Trace);
         }
     } else {
         fn init_log() {}
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct Stock {
-    pub total_volume: String,
-    pub indicator: String,
-    pub perc_change_close: String,
-    pub last_traded_price: String,
-    pub security_alias: String,
-    #[serde(skip)]
-    pub indicator_img: String,
-    pub security_symbol: String,
-}
-
 pub fn worker_global_scope() -> Option<web_sys::ServiceWorkerGlobalScope> {
     #[allow(unused_unsafe)]
     unsafe {
-        js_sys::global().dyn_into::<web_sys::ServiceWorkerGlobalScope>().ok()
+        js_sys::global()
+            .dyn_into::<web_sys::ServiceWorkerGlobalScope>()
+            .ok()
     }
 }
 
-pub async fn get_securities_and_indices_for_public() -> Result<JsValue, JsValue> {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-    init_log();
-
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-
-    let request = Request::new_with_str_and_init(
-        "https://cors-anywhere.herokuapp.com/https://www.pse.com.ph/stockMarket/home.html?method=getSecuritiesAndIndicesForPublic&ajax=true",
-        &opts
-    )?;
-
-    request.headers().set("Origin", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("Referer", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("X-Requested-With", "XMLHttpRequest")?;
-
-    let global = worker_global_scope().unwrap();
-    let resp_value = JsFuture::from(global.fetch_with_request(&request)).await?;
+fn response_cache() -> Option<ResponseCache> {
+    KvStorage::from_binding(CACHE_BINDING)
+        .ok()
+        .map(|storage| ResponseCache::new(StorageCache::new(storage), SystemClock))
+}
 
-    assert!(resp_value.is_instance_of::<Response>());
-    let resp: Response = resp_value.dyn_into().unwrap();
-    let json = JsFuture::from(resp.json()?).await?;
+pub async fn get_securities_and_indices_for_public() -> Result<Stocks, FetchError> {
+    utils::set_panic_hook();
+    init_log();
 
-    Ok(json)
+    cached_json(
+        response_cache().as_ref(),
+        &WorkerFetcher::from_env(),
+        &Endpoint::SecuritiesAndIndices,
+    )
+    .await
 }
 
-pub async fn find_security_or_company() -> Result<JsValue, JsValue> {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+#[wasm_bindgen]
+pub async fn find_security_or_company(
+    query: String,
+    start: usize,
+    limit: usize,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
     init_log();
 
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-
-    let request = Request::new_with_str_and_init(
-        "https://cors-anywhere.herokuapp.com/https://www.pse.com.ph/stockMarket/home.html?method=findSecurityOrCompany&ajax=true&start=0&limit=1&query=sm",
-        &opts
-    )?;
+    let endpoint = Endpoint::FindSecurityOrCompany {
+        query,
+        start,
+        limit,
+    };
+    let json: serde_json::Value = fetch_json(&WorkerFetcher::from_env(), &endpoint).await?;
+    to_js(&json)
+}
 
-    request.headers().set("Origin", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("Referer", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("X-Requested-With", "XMLHttpRequest")?;
+#[wasm_bindgen]
+pub async fn company_info(company: u32, security: u32) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
 
-    let global = worker_global_scope().unwrap();
-    let resp_value = JsFuture::from(global.fetch_with_request(&request)).await?;
+    let endpoint = Endpoint::CompanyInfo { company, security };
+    let json: serde_json::Value = fetch_json(&WorkerFetcher::from_env(), &endpoint).await?;
+    to_js(&json)
+}
 
-    assert!(resp_value.is_instance_of::<Response>());
-    let resp: Response = resp_value.dyn_into().unwrap();
-    let json = JsFuture::from(resp.json()?).await?;
+#[wasm_bindgen]
+pub async fn company_info_historical_data(security: u32) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
 
-    Ok(json)
+    let endpoint = Endpoint::RecentSecurityQuoteData { security };
+    let json: serde_json::Value = fetch_json(&WorkerFetcher::from_env(), &endpoint).await?;
+    to_js(&json)
 }
 
-pub async fn company_info() -> Result<JsValue, JsValue> {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+/// Entry point for the versioned `/v1` API, `/openapi.json` and the
+/// `/stocks` routes ported from `tote_proxy`.
+#[wasm_bindgen]
+pub async fn handle_request(request: web_sys::Request) -> Result<web_sys::Response, JsValue> {
+    utils::set_panic_hook();
     init_log();
 
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-
-    let request = Request::new_with_str_and_init(
-        "https://cors-anywhere.herokuapp.com/https://www.pse.com.ph/stockMarket/companyInfo.html?method=fetchHeaderData&ajax=true&company=599&security=520",
-        &opts
-    )?;
+    let request = HttpRequest::from_web(&request).await?;
+    if request.path == stream::STREAM_PATH {
+        return stream_quotes(&request);
+    }
 
-    request.headers().set("Origin", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("Referer", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("X-Requested-With", "XMLHttpRequest")?;
+    let mut router = Router::new(WorkerFetcher::from_env()).with_boards(boards());
+    if let Some(cache) = response_cache() {
+        router = router.with_cache(cache);
+    }
+    if let Ok(storage) = KvStorage::from_binding(ARCHIVE_BINDING) {
+        router = router.with_storage(storage);
+    }
+    router.handle(&request).await.into_web()
+}
 
-    let global = worker_global_scope().unwrap();
-    let resp_value = JsFuture::from(global.fetch_with_request(&request)).await?;
+thread_local! {
+    static SUBSCRIPTIONS: Rc<Subscriptions> = Subscriptions::new(stream::MAX_SUBSCRIPTIONS);
+}
 
-    assert!(resp_value.is_instance_of::<Response>());
-    let resp: Response = resp_value.dyn_into().unwrap();
-    let json = JsFuture::from(resp.json()?).await?;
+/// Answers with a server-sent events stream fed by a task polling upstream.
+fn stream_quotes(request: &HttpRequest) -> Result<web_sys::Response, JsValue> {
+    let subscription = stream::parse_symbols(request.query_param("symbols"))
+        .and_then(|symbols| Ok((symbols, SUBSCRIPTIONS.with(|s| s.acquire())?)));
+    let (symbols, subscription) = match subscription {
+        Ok(accepted) => accepted,
+        Err(err) => {
+            return HttpResponse::error(&err)
+                .with_header("Access-Control-Allow-Origin", "*")
+                .into_web()
+        }
+    };
+
+    let pipe = web_sys::TransformStream::new()?;
+    let writer = pipe.writable().get_writer()?;
+    let cache = response_cache();
+    wasm_bindgen_futures::spawn_local(async move {
+        let _subscription = subscription;
+        let mut sink = WebSink::new(writer);
+        if let Err(err) = stream::run(
+            &WorkerFetcher::from_env(),
+            cache.as_ref(),
+            &SystemClock,
+            &WebTimer,
+            &StreamConfig::default(),
+            &symbols,
+            &mut sink,
+        )
+        .await
+        {
+            info!("{}", err);
+        }
+        sink.close().await;
+    });
 
-    Ok(json)
+    stream::response_head().into_web_stream(&pipe.readable())
 }
 
-pub async fn company_info_historical_data() -> Result<JsValue, JsValue> {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+#[wasm_bindgen]
+pub async fn search(query: String, limit: Option<usize>) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
     init_log();
 
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-
-    let request = Request::new_with_str_and_init(
-        "https://cors-anywhere.herokuapp.com/https://www.pse.com.ph/stockMarket/companyInfoHistoricalData.html?method=getRecentSecurityQuoteData&ajax=true&security=520",
-        &opts
-    )?;
-
-    request.headers().set("Origin", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("Referer", "https://www.pse.com.ph/stockMarket/home.html")?;
-    request.headers().set("X-Requested-With", "XMLHttpRequest")?;
+    let cache = response_cache();
+    let hits = search::search(&WorkerFetcher::from_env(), cache.as_ref(), &query, limit).await?;
+    to_js(&hits)
+}
 
-    let global = worker_global_scope().unwrap();
-    let resp_value = JsFuture::from(global.fetch_with_request(&request)).await?;
+fn boards() -> summary::Boards {
+    js_sys::Reflect::get(
+        &js_sys::global(),
+        &JsValue::from_str(summary::SME_BOARD_BINDING),
+    )
+    .ok()
+    .and_then(|value| value.as_string())
+    .map(|symbols| summary::Boards::parse(&symbols))
+    .unwrap_or_default()
+}
 
-    assert!(resp_value.is_instance_of::<Response>());
-    let resp: Response = resp_value.dyn_into().unwrap();
-    let json = JsFuture::from(resp.json()?).await?;
+/// Movers and advance/decline counts for `board` (`main`, `sme` or `etf`),
+/// or the whole market.
+#[wasm_bindgen]
+pub async fn get_market_summary(
+    board: Option<String>,
+    limit: Option<String>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
 
-    Ok(json)
+    let board = validate::board(board.as_deref())?;
+    let limit = validate::limit(
+        limit.as_deref(),
+        router::DEFAULT_TOP_LIMIT,
+        router::MAX_TOP_LIMIT,
+    )?;
+    let stocks = get_securities_and_indices_for_public().await?;
+    to_js(&summary::MarketSummary::new(
+        &stocks,
+        &boards(),
+        board,
+        limit,
+    ))
 }
 
+/// Every security as a bare array, as this export has always returned.
+/// `/v1/stocks` carries the list's `asOf` time too.
 #[wasm_bindgen]
 pub async fn get_all_stocks() -> Result<JsValue, JsValue> {
-    let json = get_securities_and_indices_for_public().await?;
-    let stocks: Vec<Stock> = json.into_serde().unwrap();
-    Ok(JsValue::from_serde(&stocks).unwrap())
+    utils::set_panic_hook();
+    init_log();
 
+    let stocks = get_securities_and_indices_for_public().await?;
+    to_js(&stocks.stocks)
 }
 
 #[wasm_bindgen]
 pub async fn get_stock(symbol: String) -> Result<JsValue, JsValue> {
-    let json = get_securities_and_indices_for_public().await?;
+    utils::set_panic_hook();
+    init_log();
 
-    let raw_stocks: Vec<Stock> = json.into_serde().unwrap();
+    let symbol = validate::symbol(&symbol)?;
+    let raw_stocks = get_securities_and_indices_for_public().await?;
     let mut res_stocks: Vec<Stock> = Vec::new();
-    for stock in raw_stocks.iter() {
-        if stock.security_symbol.to_lowercase() == symbol.to_lowercase() {
+    for stock in raw_stocks.stocks.iter() {
+        if stock.security_symbol.eq_ignore_ascii_case(&symbol) {
             res_stocks.push(stock.clone());
         }
     }
 
-    Ok(JsValue::from_serde(&res_stocks).unwrap())
+    to_js(&res_stocks)
+}
+
+#[wasm_bindgen]
+pub async fn get_stock_by_date(symbol: String, timestamp: String) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
+
+    let symbol = validate::symbol(&symbol)?;
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let stock = archive::find_stock_by_date(&storage, &symbol, &timestamp).await?;
+    to_js(&stock)
+}
+
+#[wasm_bindgen]
+pub async fn get_history(symbol: String, from: String, to: String) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
+
+    let symbol = validate::symbol(&symbol)?;
+    let from = validate::date(&from)?;
+    let to = validate::date(&to)?;
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let history =
+        history::get_history(&WorkerFetcher::from_env(), &storage, &symbol, from, to).await?;
+    to_js(&history)
 }
 
 #[wasm_bindgen]
-pub async fn get_stock_by_date(_symbol: String,_timestamp: String) -> Result<JsValue, JsValue> {
-    // find_symbol_and_trading_date
-    get_securities_and_indices_for_public().await
+pub async fn get_indicators(symbol: String, spec: String) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
+
+    let symbol = validate::symbol(&symbol)?;
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let today = calendar::trading_date(&chrono::Utc::now());
+    let indicators =
+        indicators::get_indicators(&WorkerFetcher::from_env(), &storage, &symbol, &spec, today)
+            .await?;
+    to_js(&indicators)
 }
 
 #[wasm_bindgen]
 pub async fn archive() -> Result<JsValue, JsValue> {
-    // save all stocks
-    get_securities_and_indices_for_public().await
+    utils::set_panic_hook();
+    init_log();
+
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let stocks = get_securities_and_indices_for_public().await?;
+    let summary = archive::archive_snapshot(&storage, stocks, &chrono::Utc::now()).await?;
+    to_js(&summary)
 }
 
+fn snapshot_schedule() -> Result<job::Schedule, errors::JobError> {
+    match js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(job::SCHEDULE_BINDING))
+        .ok()
+        .and_then(|value| value.as_string())
+    {
+        Some(times) => job::Schedule::parse(&times),
+        None => Ok(job::Schedule::default()),
+    }
+}
+
+/// Entry point for cron triggers. `scheduled_time` is the event's
+/// `scheduledTime`, in milliseconds since the epoch.
 #[wasm_bindgen]
-pub async fn test() -> Result<JsValue, JsValue> {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+pub async fn scheduled(scheduled_time: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
     init_log();
 
-    let mut opts = RequestInit::new();
-    opts.method("GET");
+    let now = chrono::Utc
+        .timestamp_millis_opt(scheduled_time as i64)
+        .single()
+        .ok_or_else(|| {
+            WorkerError::InvalidArgument(format!("invalid scheduled time {}", scheduled_time))
+        })?;
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let status = job::run_snapshot_job(
+        &WorkerFetcher::from_env(),
+        &storage,
+        &FixedClock(now),
+        &snapshot_schedule()?,
+        false,
+    )
+    .await?;
+    to_js(&status)
+}
 
-    let request = Request::new_with_str_and_init(
-        "https://httpbin.org/get",
-        &opts
-    )?;
+#[wasm_bindgen]
+pub async fn get_job_status() -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
 
-    let global = worker_global_scope().unwrap();
-    let resp_value = JsFuture::from(global.fetch_with_request(&request)).await?;
+    let storage = KvStorage::from_binding(ARCHIVE_BINDING)?;
+    let status = job::load_status(&storage).await?;
+    to_js(&status)
+}
 
-    assert!(resp_value.is_instance_of::<Response>());
-    let resp: Response = resp_value.dyn_into().unwrap();
-    let json = JsFuture::from(resp.json()?).await?;
+#[wasm_bindgen]
+pub async fn test() -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+    init_log();
 
-    Ok(json)
-}
\ No newline at end of file
+    let body = WorkerFetcher::from_env()
+        .fetch_url("https://httpbin.org/get")
+        .await?;
+    let json: serde_json::Value = serde_json::from_str(&body).map_err(FetchError::from)?;
+    to_js(&json)
+}
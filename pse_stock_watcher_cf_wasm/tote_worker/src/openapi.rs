@@ -0,0 +1,692 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use serde_json::{json, Map, Value};
+
+use crate::errors::ErrorBody;
+use crate::history::History;
+use crate::indicators::Indicators;
+use crate::job::JobStatus;
+use crate::search::SearchHit;
+use crate::stream::STREAM_PATH;
+use crate::summary::{DailySummary, MarketSummary};
+use crate::watchlist::{NewWatchlist, Watchlist, WatchlistQuotes, WatchlistRename};
+use crate::{Stock, Stocks};
+
+/// Prefix of the versioned API routes.
+pub const API_PREFIX: &str = "/v1";
+
+/// Where the document built by [`document`] is served.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// A query or path parameter of an [`Operation`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    /// `path` or `query`.
+    pub location: &'static str,
+    pub required: bool,
+    /// JSON type of the value, `string` or `integer`.
+    pub kind: &'static str,
+    pub description: &'static str,
+}
+
+const SYMBOL: Parameter = Parameter {
+    name: "symbol",
+    location: "path",
+    required: true,
+    kind: "string",
+    description: "PSE symbol such as `SM`, matched case-insensitively",
+};
+
+const LIMIT: Parameter = Parameter {
+    name: "limit",
+    location: "query",
+    required: false,
+    kind: "integer",
+    description: "Rows to return",
+};
+
+const BOARD: Parameter = Parameter {
+    name: "board",
+    location: "query",
+    required: false,
+    kind: "string",
+    description: "Only securities on this board: `main`, `sme` or `etf`",
+};
+
+const WATCHLIST_ID: Parameter = Parameter {
+    name: "id",
+    location: "path",
+    required: true,
+    kind: "integer",
+    description: "Id of one of the token's watchlists",
+};
+
+/// Name of the bearer token scheme guarding the watchlist routes.
+pub const TOKEN_SCHEME: &str = "token";
+
+/// A route of the versioned API. Most are public `GET` routes answering
+/// `200`.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub id: &'static str,
+    /// Lower case, as in the document.
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub parameters: Vec<Parameter>,
+    /// Schema of the JSON request body, if any.
+    pub request: Option<Schema>,
+    pub status: u16,
+    pub response: Schema,
+    /// Whether a bearer token is required.
+    pub authenticated: bool,
+}
+
+impl Default for Operation {
+    fn default() -> Self {
+        Self {
+            id: "",
+            method: "get",
+            path: "",
+            summary: "",
+            parameters: vec![],
+            request: None,
+            status: 200,
+            response: Schema::Bool(true),
+            authenticated: false,
+        }
+    }
+}
+
+/// Schemas live under `#/components/schemas`. OpenAPI 3.1 schemas are JSON
+/// Schema 2019-09 and later, so optional values are typed `[T, "null"]`.
+pub fn generator() -> SchemaGenerator {
+    SchemaSettings::draft2019_09()
+        .with(|settings| {
+            settings.definitions_path = "#/components/schemas/".to_string();
+            settings.meta_schema = None;
+        })
+        .into_generator()
+}
+
+fn date(name: &'static str, location: &'static str, description: &'static str) -> Parameter {
+    Parameter {
+        name,
+        location,
+        required: true,
+        kind: "string",
+        description,
+    }
+}
+
+pub fn operations(gen: &mut SchemaGenerator) -> Vec<Operation> {
+    vec![
+        Operation {
+            id: "listStocks",
+            path: "/v1/stocks",
+            summary: "Every listed security and index",
+            parameters: vec![],
+            response: gen.subschema_for::<Stocks>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getStock",
+            path: "/v1/stocks/{symbol}",
+            summary: "The latest quote of a security",
+            parameters: vec![SYMBOL],
+            response: gen.subschema_for::<Stock>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getHistory",
+            path: "/v1/stocks/{symbol}/history",
+            summary: "Daily bars between two dates",
+            parameters: vec![
+                SYMBOL,
+                date("from", "query", "First day, `YYYY-MM-DD`"),
+                date("to", "query", "Last day, `YYYY-MM-DD`"),
+            ],
+            response: gen.subschema_for::<History>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getIndicators",
+            path: "/v1/stocks/{symbol}/indicators",
+            summary: "Indicators over the last year of history",
+            parameters: vec![
+                SYMBOL,
+                Parameter {
+                    name: "spec",
+                    location: "query",
+                    required: true,
+                    kind: "string",
+                    description: "Comma separated indicators such as `ema:12,rsi:14`",
+                },
+            ],
+            response: gen.subschema_for::<Indicators>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getArchivedStock",
+            path: "/v1/stocks/{symbol}/snapshots/{date}",
+            summary: "A security as archived at the close of a trading day",
+            parameters: vec![SYMBOL, date("date", "path", "Trading day, `YYYY-MM-DD`")],
+            response: gen.subschema_for::<Stock>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "search",
+            path: "/v1/search",
+            summary: "Securities matching a symbol or name",
+            parameters: vec![
+                Parameter {
+                    name: "q",
+                    location: "query",
+                    required: true,
+                    kind: "string",
+                    description: "Symbol or company name, typos allowed",
+                },
+                LIMIT,
+            ],
+            response: gen.subschema_for::<Vec<SearchHit>>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getMarketSummary",
+            path: "/v1/market/summary",
+            summary: "Movers and advance/decline counts",
+            parameters: vec![BOARD, LIMIT],
+            response: gen.subschema_for::<MarketSummary>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "listGainers",
+            path: "/v1/market/gainers",
+            summary: "Biggest gainers",
+            parameters: vec![BOARD, LIMIT],
+            response: gen.subschema_for::<Vec<Stock>>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "listLosers",
+            path: "/v1/market/losers",
+            summary: "Biggest losers",
+            parameters: vec![BOARD, LIMIT],
+            response: gen.subschema_for::<Vec<Stock>>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "listMostActive",
+            path: "/v1/market/active",
+            summary: "Most traded by value",
+            parameters: vec![BOARD, LIMIT],
+            response: gen.subschema_for::<Vec<Stock>>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "listIndices",
+            path: "/v1/market/indices",
+            summary: "Market indices, relayed from PSE as is",
+            parameters: vec![],
+            response: gen.subschema_for::<Value>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getDailySummary",
+            path: "/v1/summaries/{date}",
+            summary: "Movers stored by the snapshot job for a trading day",
+            parameters: vec![date("date", "path", "Trading day, `YYYY-MM-DD`")],
+            response: gen.subschema_for::<DailySummary>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "getJobStatus",
+            path: "/v1/job",
+            summary: "The last run of the snapshot job",
+            parameters: vec![],
+            response: gen.subschema_for::<JobStatus>(),
+            ..Operation::default()
+        },
+        Operation {
+            id: "listWatchlists",
+            path: "/v1/watchlists",
+            summary: "The token's watchlists",
+            response: gen.subschema_for::<Vec<Watchlist>>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "createWatchlist",
+            method: "post",
+            path: "/v1/watchlists",
+            summary: "Creates a watchlist of listed securities",
+            request: Some(gen.subschema_for::<NewWatchlist>()),
+            status: 201,
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "getWatchlist",
+            path: "/v1/watchlists/{id}",
+            summary: "A watchlist",
+            parameters: vec![WATCHLIST_ID],
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "renameWatchlist",
+            method: "patch",
+            path: "/v1/watchlists/{id}",
+            summary: "Renames a watchlist",
+            parameters: vec![WATCHLIST_ID],
+            request: Some(gen.subschema_for::<WatchlistRename>()),
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "deleteWatchlist",
+            method: "delete",
+            path: "/v1/watchlists/{id}",
+            summary: "Deletes a watchlist, answering with what it held",
+            parameters: vec![WATCHLIST_ID],
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "addWatchlistSymbol",
+            method: "put",
+            path: "/v1/watchlists/{id}/symbols/{symbol}",
+            summary: "Adds a listed security to a watchlist",
+            parameters: vec![WATCHLIST_ID, SYMBOL],
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "removeWatchlistSymbol",
+            method: "delete",
+            path: "/v1/watchlists/{id}/symbols/{symbol}",
+            summary: "Removes a security from a watchlist",
+            parameters: vec![WATCHLIST_ID, SYMBOL],
+            response: gen.subschema_for::<Watchlist>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+        Operation {
+            id: "getWatchlistQuotes",
+            path: "/v1/watchlists/{id}/quotes",
+            summary: "Latest quotes of a watchlist's securities",
+            parameters: vec![WATCHLIST_ID],
+            response: gen.subschema_for::<WatchlistQuotes>(),
+            authenticated: true,
+            ..Operation::default()
+        },
+    ]
+}
+
+fn content<T: serde::Serialize>(description: &str, schema: &T) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+/// The OpenAPI description of the versioned API.
+pub fn document() -> Value {
+    let mut gen = generator();
+    let operations = operations(&mut gen);
+    let error = gen.subschema_for::<ErrorBody>();
+
+    let mut paths = Map::new();
+    for operation in operations {
+        let parameters: Vec<Value> = operation
+            .parameters
+            .iter()
+            .map(|parameter| {
+                json!({
+                    "name": parameter.name,
+                    "in": parameter.location,
+                    "required": parameter.required,
+                    "description": parameter.description,
+                    "schema": { "type": parameter.kind },
+                })
+            })
+            .collect();
+
+        let mut item = json!({
+            "operationId": operation.id,
+            "summary": operation.summary,
+            "parameters": parameters,
+            "responses": {
+                operation.status.to_string(): content("OK", &operation.response),
+                "default": content("Error", &error),
+            },
+        });
+        if let Some(request) = &operation.request {
+            item["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": request } },
+            });
+        }
+        if operation.authenticated {
+            item["security"] = json!([{ TOKEN_SCHEME: [] }]);
+        }
+
+        let path = paths
+            .entry(operation.path.to_string())
+            .or_insert_with(|| json!({}));
+        path[operation.method] = item;
+    }
+
+    paths.insert(
+        STREAM_PATH.to_string(),
+        json!({
+            "get": {
+                "operationId": "streamQuotes",
+                "summary": "Server-sent events of changed quotes while the market is open",
+                "parameters": [{
+                    "name": "symbols",
+                    "in": "query",
+                    "required": true,
+                    "description": "Comma separated symbols to follow",
+                    "schema": { "type": "string" },
+                }],
+                "responses": {
+                    "200": {
+                        "description": "`quote` events carry a `Stock`, `error` events an \
+                            `ErrorBody`. `heartbeat` events are sent on polls without \
+                            changes and a `closed` event ends the stream once the market \
+                            closes.",
+                        "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                    },
+                    "default": content("Error", &error),
+                },
+            },
+        }),
+    );
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "tote_worker",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.definitions(),
+            "securitySchemes": {
+                TOKEN_SCHEME: {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Any token of 16 to 128 printable characters. Each \
+                        token has watchlists of its own, so keep it secret.",
+                },
+            },
+        },
+    })
+}
+
+/// Validates a response body against a schema from [`document`].
+#[cfg(test)]
+pub(crate) fn validate(document: &Value, schema: &Value, instance: &Value) -> Result<(), String> {
+    let root = json!({
+        "allOf": [schema],
+        "components": document["components"],
+    });
+    let compiled = jsonschema::JSONSchema::options()
+        .with_draft(jsonschema::Draft::Draft201909)
+        .compile(&root)
+        .map_err(|err| err.to_string())?;
+    let result = compiled.validate(instance).map_err(|errors| {
+        errors
+            .map(|err| format!("{} at {}", err, err.instance_path))
+            .collect::<Vec<_>>()
+            .join("; ")
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::FixedClock;
+    use crate::fixtures::FixtureFetcher;
+    use crate::http::{HttpRequest, Method};
+    use crate::job::{run_snapshot_job, Schedule};
+    use crate::router::Router;
+    use crate::storage::MemoryStorage;
+    use chrono::{TimeZone, Utc};
+    use futures::executor::block_on;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    /// A request exercising each documented path.
+    const EXAMPLES: &[(&str, &str)] = &[
+        ("/v1/stocks", "/v1/stocks"),
+        ("/v1/stocks/{symbol}", "/v1/stocks/sm"),
+        (
+            "/v1/stocks/{symbol}/history",
+            "/v1/stocks/SM/history?from=2020-10-12&to=2020-10-16",
+        ),
+        (
+            "/v1/stocks/{symbol}/indicators",
+            "/v1/stocks/SM/indicators?spec=ema:3,rsi:2",
+        ),
+        (
+            "/v1/stocks/{symbol}/snapshots/{date}",
+            "/v1/stocks/SM/snapshots/2020-10-16",
+        ),
+        ("/v1/search", "/v1/search?q=ayala&limit=5"),
+        ("/v1/market/summary", "/v1/market/summary?board=main"),
+        ("/v1/market/gainers", "/v1/market/gainers?limit=3"),
+        ("/v1/market/losers", "/v1/market/losers"),
+        ("/v1/market/active", "/v1/market/active?board=sme"),
+        ("/v1/market/indices", "/v1/market/indices"),
+        ("/v1/summaries/{date}", "/v1/summaries/2020-10-16"),
+        ("/v1/job", "/v1/job"),
+    ];
+
+    /// A router over the recorded fixtures, after the snapshot job ran on
+    /// 2020-10-16.
+    fn router() -> Router<FixtureFetcher> {
+        let fetcher = FixtureFetcher::from_dir(FIXTURES);
+        let clock = FixedClock(Utc.with_ymd_and_hms(2020, 10, 16, 7, 5, 0).unwrap());
+        let storage = MemoryStorage::new();
+        block_on(run_snapshot_job(
+            &fetcher,
+            &storage,
+            &clock,
+            &Schedule::default(),
+            true,
+        ))
+        .unwrap();
+
+        Router::new(fetcher).with_storage(storage).with_clock(clock)
+    }
+
+    fn get(router: &Router<FixtureFetcher>, url: &str) -> (u16, Value) {
+        let response = block_on(router.handle(&HttpRequest::new(Method::Get, url)));
+        let body = serde_json::from_str(response.body.as_ref().unwrap()).unwrap();
+        (response.status, body)
+    }
+
+    fn schema<'a>(document: &'a Value, path: &str, status: &str) -> &'a Value {
+        operation_schema(document, path, "get", status)
+    }
+
+    fn operation_schema<'a>(
+        document: &'a Value,
+        path: &str,
+        method: &str,
+        status: &str,
+    ) -> &'a Value {
+        &document["paths"][path][method]["responses"][status]["content"]["application/json"]
+            ["schema"]
+    }
+
+    #[test]
+    fn responses_match_the_document() {
+        let document = document();
+        let router = router();
+
+        let documented: Vec<&str> = operations(&mut generator())
+            .iter()
+            .filter(|operation| !operation.authenticated)
+            .map(|operation| operation.path)
+            .collect();
+        let examples: Vec<&str> = EXAMPLES.iter().map(|(path, _)| *path).collect();
+        assert_eq!(documented.len(), examples.len());
+        assert!(documented.iter().all(|path| examples.contains(path)));
+
+        for (path, url) in EXAMPLES {
+            let (status, body) = get(&router, url);
+            assert_eq!(status, 200, "{}: {}", url, body);
+            if let Err(err) = validate(&document, schema(&document, path, "200"), &body) {
+                panic!("{} does not match {}: {}", url, path, err);
+            }
+        }
+
+        for url in &[
+            "/v1/stocks/NOPE",
+            "/v1/stocks/S M/history",
+            "/v1/stocks/SM/history?from=2020-10-12",
+            "/v1/search",
+            "/v1/market/summary?board=pink",
+            "/v1/summaries/2020-10-15",
+            "/v1/unknown",
+        ] {
+            let (status, body) = get(&router, url);
+            assert!(status >= 400, "{}", url);
+            validate(&document, schema(&document, "/v1/job", "default"), &body).unwrap();
+        }
+    }
+
+    #[test]
+    fn serves_the_document() {
+        let router = router();
+        let (status, body) = get(&router, OPENAPI_PATH);
+        assert_eq!(status, 200);
+        assert_eq!(body, document());
+        assert_eq!(get(&router, "/v1/openapi.json").1, body);
+
+        let (status, body) = get(&Router::new(FixtureFetcher::from_dir(FIXTURES)), "/v1/job");
+        assert_eq!(status, 503);
+        assert_eq!(body["code"], "storage_unavailable");
+    }
+
+    #[test]
+    fn describes_every_operation() {
+        let document = document();
+        assert_eq!(document["openapi"], OPENAPI_VERSION);
+
+        let operations = operations(&mut generator());
+        let paths = document["paths"].as_object().unwrap();
+        let described: usize = paths
+            .values()
+            .map(|path| path.as_object().unwrap().len())
+            .sum();
+        assert_eq!(described, operations.len() + 1);
+        assert!(paths.contains_key(STREAM_PATH));
+        assert!(paths.keys().all(|path| path.starts_with(API_PREFIX)));
+
+        let indicator = &document["components"]["schemas"]["Indicator"];
+        assert_eq!(indicator["enum"], json!(["up", "down", "unchanged"]));
+        assert_eq!(
+            document["paths"]["/v1/stocks/{symbol}"]["get"]["responses"]["200"]["content"]
+                ["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Stock"
+        );
+
+        let create = &document["paths"]["/v1/watchlists"]["post"];
+        assert_eq!(create["security"], json!([{ "token": [] }]));
+        assert_eq!(
+            create["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/NewWatchlist"
+        );
+        assert!(create["responses"]["201"].is_object());
+        assert_eq!(
+            document["components"]["securitySchemes"]["token"]["scheme"],
+            "bearer"
+        );
+        assert!(document["paths"]["/v1/stocks"]["get"]["security"].is_null());
+    }
+
+    #[test]
+    fn watchlist_responses_match_the_document() {
+        let document = document();
+        let router = router();
+        let token = "Bearer 0123456789abcdef";
+
+        let requests = [
+            (
+                Method::Post,
+                "/v1/watchlists",
+                Some(r#"{"name":"Malls","symbols":["sm","SMPH"]}"#),
+                201,
+            ),
+            (Method::Get, "/v1/watchlists", None, 200),
+            (Method::Get, "/v1/watchlists/{id}", None, 200),
+            (
+                Method::Patch,
+                "/v1/watchlists/{id}",
+                Some(r#"{"name":"Retail"}"#),
+                200,
+            ),
+            (
+                Method::Put,
+                "/v1/watchlists/{id}/symbols/{symbol}",
+                None,
+                200,
+            ),
+            (
+                Method::Delete,
+                "/v1/watchlists/{id}/symbols/{symbol}",
+                None,
+                200,
+            ),
+            (Method::Get, "/v1/watchlists/{id}/quotes", None, 200),
+            (Method::Delete, "/v1/watchlists/{id}", None, 200),
+        ];
+
+        let documented = operations(&mut generator())
+            .iter()
+            .filter(|operation| operation.authenticated)
+            .count();
+        assert_eq!(documented, requests.len());
+
+        for (method, path, body, status) in requests.iter() {
+            let url = path.replace("{id}", "1").replace("{symbol}", "ali");
+            let mut request = HttpRequest::new(*method, &url).with_header("Authorization", token);
+            request.body = body.map(str::to_string);
+            let response = block_on(router.handle(&request));
+            let json: Value = serde_json::from_str(response.body.as_ref().unwrap()).unwrap();
+            assert_eq!(response.status, *status, "{:?} {}: {}", method, url, json);
+
+            let method = format!("{:?}", method).to_lowercase();
+            let schema = operation_schema(&document, path, &method, &status.to_string());
+            if let Err(err) = validate(&document, schema, &json) {
+                panic!("{} {} does not match: {}", method, url, err);
+            }
+        }
+    }
+
+    #[test]
+    fn validates_against_components() {
+        let document = document();
+        let schema = json!({ "$ref": "#/components/schemas/ErrorBody" });
+        assert!(validate(
+            &document,
+            &schema,
+            &json!({"status": 404, "code": "not_found", "message": "gone"})
+        )
+        .is_ok());
+        assert!(validate(&document, &schema, &json!({"status": "404"})).is_err());
+    }
+}
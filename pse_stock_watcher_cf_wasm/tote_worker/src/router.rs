@@ -0,0 +1,802 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::archive;
+use crate::cache::{Cached, ResponseCache};
+use crate::calendar::{self, Clock, SystemClock};
+use crate::errors::{FetchError, WorkerError};
+use crate::history;
+use crate::http::{HttpRequest, HttpResponse, Method};
+use crate::indicators;
+use crate::job;
+use crate::openapi::{self, API_PREFIX, OPENAPI_PATH};
+use crate::search::{self, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT};
+use crate::storage::Storage;
+use crate::summary::{summary_key, Board, Boards, DailySummary, MarketSummary};
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+use crate::validate;
+use crate::watchlist::{self, NewWatchlist, WatchlistQuotes, WatchlistRename, Watchlists};
+use crate::Stocks;
+
+pub const ALLOWED_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
+/// Rows returned by `get_top_security` and the movers routes when the request
+/// has no `limit`.
+pub const DEFAULT_TOP_LIMIT: usize = 10;
+pub const MAX_TOP_LIMIT: usize = 100;
+
+const CORS_MAX_AGE: &str = "86400";
+
+/// Serves the versioned API under `/v1` and its OpenAPI description. The
+/// legacy `/stocks/*` routes map onto PSE endpoints and relay the upstream
+/// responses with CORS headers, the way `tote_proxy` does; movers and market
+/// breadth are worked out from the securities list instead.
+pub struct Router<F> {
+    fetcher: F,
+    cache: Option<ResponseCache>,
+    storage: Option<Box<dyn Storage>>,
+    clock: Box<dyn Clock>,
+    boards: Boards,
+}
+
+/// What a request asks for.
+#[derive(Debug, Clone, PartialEq)]
+enum Route {
+    Proxy(Endpoint),
+    Market {
+        view: MarketView,
+        board: Option<Board>,
+        limit: usize,
+    },
+    OpenApi,
+    Stocks,
+    Stock(String),
+    History {
+        symbol: String,
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+    Indicators {
+        symbol: String,
+        spec: String,
+    },
+    Snapshot {
+        symbol: String,
+        date: String,
+    },
+    Search {
+        query: String,
+        limit: usize,
+    },
+    Summary(NaiveDate),
+    JobStatus,
+    Watchlist {
+        token: String,
+        action: WatchlistAction,
+    },
+}
+
+/// What a `/v1/watchlists` request does to the token's watchlists.
+#[derive(Debug, Clone, PartialEq)]
+enum WatchlistAction {
+    List,
+    Create(NewWatchlist),
+    Get(u32),
+    Rename(u32, String),
+    Delete(u32),
+    Add(u32, String),
+    Remove(u32, String),
+    Quotes(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarketView {
+    Summary,
+    Gainers,
+    Losers,
+    MostActive,
+}
+
+impl<F: Fetcher> Router<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            cache: None,
+            storage: None,
+            clock: Box::new(SystemClock),
+            boards: Boards::default(),
+        }
+    }
+
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// The archive behind history, snapshots, summaries, job status and
+    /// watchlists.
+    pub fn with_storage<S: Storage + 'static>(mut self, storage: S) -> Self {
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn with_boards(mut self, boards: Boards) -> Self {
+        self.boards = boards;
+        self
+    }
+
+    pub async fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        let route = if request.path == OPENAPI_PATH {
+            Ok(Route::OpenApi)
+        } else if let Some(path) = versioned(&request.path) {
+            api_route(path, request)
+        } else if request.path.starts_with("/stocks") {
+            stocks_route(request)
+        } else {
+            return HttpResponse::text(200, "Tote");
+        };
+
+        match request.method {
+            Method::Options => preflight(request),
+            Method::Get
+            | Method::Head
+            | Method::Post
+            | Method::Put
+            | Method::Patch
+            | Method::Delete => {
+                let result = match route {
+                    Ok(route) => self.respond(route).await,
+                    Err(err) => Err(err),
+                };
+                let response = cors(result.unwrap_or_else(|err| HttpResponse::error(&err)));
+                if request.method == Method::Head {
+                    HttpResponse {
+                        body: None,
+                        ..response
+                    }
+                } else {
+                    response
+                }
+            }
+            Method::Other => HttpResponse::error(&WorkerError::MethodNotAllowed)
+                .with_header("Allow", ALLOWED_METHODS),
+        }
+    }
+
+    async fn respond(&self, route: Route) -> Result<HttpResponse, WorkerError> {
+        match route {
+            Route::Proxy(endpoint) => {
+                let (body, cached) = self.fetch(&endpoint).await?;
+                Ok(json(body, cached.as_ref()))
+            }
+            Route::Market { view, board, limit } => self.market(view, board, limit).await,
+            Route::OpenApi => to_json(&openapi::document(), None),
+            Route::Stocks => {
+                let (stocks, cached) = self.stocks().await?;
+                to_json(&stocks, cached.as_ref())
+            }
+            Route::Stock(symbol) => {
+                let (stocks, cached) = self.stocks().await?;
+                let stock = stocks
+                    .stocks
+                    .into_iter()
+                    .find(|stock| stock.security_symbol.eq_ignore_ascii_case(&symbol))
+                    .ok_or_else(|| WorkerError::NotFound(format!("no security `{}`", symbol)))?;
+                to_json(&stock, cached.as_ref())
+            }
+            Route::History { symbol, from, to } => {
+                let history =
+                    history::get_history(&self.fetcher, self.storage()?, &symbol, from, to).await?;
+                to_json(&history, None)
+            }
+            Route::Indicators { symbol, spec } => {
+                let today = calendar::trading_date(&self.clock.now());
+                let indicators = indicators::get_indicators(
+                    &self.fetcher,
+                    self.storage()?,
+                    &symbol,
+                    &spec,
+                    today,
+                )
+                .await?;
+                to_json(&indicators, None)
+            }
+            Route::Snapshot { symbol, date } => {
+                let stock = archive::find_stock_by_date(self.storage()?, &symbol, &date).await?;
+                to_json(&stock, None)
+            }
+            Route::Search { query, limit } => {
+                let hits =
+                    search::search(&self.fetcher, self.cache.as_ref(), &query, Some(limit)).await?;
+                to_json(&hits, None)
+            }
+            Route::Summary(date) => {
+                let data = self
+                    .storage()?
+                    .get(&summary_key(date))
+                    .await?
+                    .ok_or_else(|| WorkerError::NotFound(format!("no summary for {}", date)))?;
+                let summary: DailySummary = serde_json::from_str(&data)
+                    .map_err(|err| WorkerError::Internal(err.to_string()))?;
+                to_json(&summary, None)
+            }
+            Route::JobStatus => {
+                let status = job::load_status(self.storage()?).await?.ok_or_else(|| {
+                    WorkerError::NotFound("the snapshot job has not run yet".to_string())
+                })?;
+                to_json(&status, None)
+            }
+            Route::Watchlist { token, action } => self.watchlist(&token, action).await,
+        }
+    }
+
+    async fn watchlist(
+        &self,
+        token: &str,
+        action: WatchlistAction,
+    ) -> Result<HttpResponse, WorkerError> {
+        let storage = self.storage()?;
+        let mut lists = Watchlists::load(storage, token).await?;
+        let now = self.clock.now();
+
+        let (status, response) = match action {
+            WatchlistAction::List => (200, to_json(&lists.lists(), None)?),
+            WatchlistAction::Get(id) => (200, to_json(lists.get(id)?, None)?),
+            WatchlistAction::Quotes(id) => {
+                let list = lists.get(id)?.clone();
+                let (stocks, _) = self.stocks().await?;
+                (200, to_json(&WatchlistQuotes::new(list, stocks), None)?)
+            }
+            WatchlistAction::Create(new) => {
+                let (stocks, _) = self.stocks().await?;
+                watchlist::ensure_listed(&stocks, &new.symbols)?;
+                let response = to_json(&lists.create(new, now)?, None)?;
+                lists.save(storage, token).await?;
+                (201, response)
+            }
+            WatchlistAction::Rename(id, name) => {
+                let response = to_json(&lists.rename(id, &name, now)?, None)?;
+                lists.save(storage, token).await?;
+                (200, response)
+            }
+            WatchlistAction::Delete(id) => {
+                let response = to_json(&lists.delete(id)?, None)?;
+                lists.save(storage, token).await?;
+                (200, response)
+            }
+            WatchlistAction::Add(id, symbol) => {
+                lists.get(id)?;
+                let (stocks, _) = self.stocks().await?;
+                watchlist::ensure_listed(&stocks, std::slice::from_ref(&symbol))?;
+                let response = to_json(&lists.add(id, &symbol, now)?, None)?;
+                lists.save(storage, token).await?;
+                (200, response)
+            }
+            WatchlistAction::Remove(id, symbol) => {
+                let response = to_json(&lists.remove(id, &symbol, now)?, None)?;
+                lists.save(storage, token).await?;
+                (200, response)
+            }
+        };
+
+        Ok(HttpResponse { status, ..response }.with_header("Cache-Control", "no-store"))
+    }
+
+    fn storage(&self) -> Result<&dyn Storage, WorkerError> {
+        self.storage.as_deref().ok_or_else(|| {
+            WorkerError::Unavailable("archive storage is not configured".to_string())
+        })
+    }
+
+    async fn fetch(&self, endpoint: &Endpoint) -> Result<(String, Option<Cached>), FetchError> {
+        match &self.cache {
+            Some(cache) => {
+                let cached = cache.fetch(&self.fetcher, endpoint).await?;
+                Ok((cached.body.clone(), Some(cached)))
+            }
+            None => Ok((self.fetcher.fetch(endpoint).await?, None)),
+        }
+    }
+
+    async fn stocks(&self) -> Result<(Stocks, Option<Cached>), WorkerError> {
+        let endpoint = Endpoint::SecuritiesAndIndices;
+        match &self.cache {
+            Some(cache) => {
+                let (stocks, cached) = cache.fetch_json(&self.fetcher, &endpoint).await?;
+                Ok((stocks, Some(cached)))
+            }
+            None => Ok((fetch_json(&self.fetcher, &endpoint).await?, None)),
+        }
+    }
+
+    async fn market(
+        &self,
+        view: MarketView,
+        board: Option<Board>,
+        limit: usize,
+    ) -> Result<HttpResponse, WorkerError> {
+        let (stocks, cached) = self.stocks().await?;
+        let summary = MarketSummary::new(&stocks, &self.boards, board, limit);
+
+        match view {
+            MarketView::Summary => to_json(&summary, cached.as_ref()),
+            MarketView::Gainers => to_json(&summary.top_gainers, cached.as_ref()),
+            MarketView::Losers => to_json(&summary.top_losers, cached.as_ref()),
+            MarketView::MostActive => to_json(&summary.most_active_by_value, cached.as_ref()),
+        }
+    }
+}
+
+fn json(body: String, cached: Option<&Cached>) -> HttpResponse {
+    let response = HttpResponse::json(200, body);
+    match cached {
+        Some(cached) => cached.headers(response),
+        None => response,
+    }
+}
+
+fn to_json<T: Serialize>(value: &T, cached: Option<&Cached>) -> Result<HttpResponse, WorkerError> {
+    let body =
+        serde_json::to_string(value).map_err(|err| WorkerError::Internal(err.to_string()))?;
+    Ok(json(body, cached))
+}
+
+fn cors(response: HttpResponse) -> HttpResponse {
+    response
+        .with_header("Access-Control-Allow-Origin", "*")
+        .append_header("Vary", "Origin")
+}
+
+fn limit(request: &HttpRequest) -> Result<usize, WorkerError> {
+    validate::limit(
+        request.query_param("limit"),
+        DEFAULT_TOP_LIMIT,
+        MAX_TOP_LIMIT,
+    )
+}
+
+/// The rest of `path` after [`API_PREFIX`], if it is versioned.
+fn versioned(path: &str) -> Option<&str> {
+    let rest = path.strip_prefix(API_PREFIX)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(rest.trim_matches('/'))
+    } else {
+        None
+    }
+}
+
+fn market_route(view: MarketView, request: &HttpRequest) -> Result<Route, WorkerError> {
+    Ok(Route::Market {
+        view,
+        board: validate::board(request.query_param("board"))?,
+        limit: limit(request)?,
+    })
+}
+
+/// Routes of the versioned API, as described by [`openapi::operations`].
+/// Only the watchlists take anything but `GET` and `HEAD`.
+fn api_route(path: &str, request: &HttpRequest) -> Result<Route, WorkerError> {
+    let segments: Vec<&str> = path.split('/').collect();
+    if let ["watchlists", rest @ ..] = segments.as_slice() {
+        return watchlist_route(rest, request);
+    }
+
+    let route = read_route(&segments, request);
+    match (request.method, route) {
+        (Method::Get, route) | (Method::Head, route) => route,
+        (_, Err(WorkerError::NotFound(message))) => Err(WorkerError::NotFound(message)),
+        (_, _) => Err(WorkerError::MethodNotAllowed),
+    }
+}
+
+fn read_route(segments: &[&str], request: &HttpRequest) -> Result<Route, WorkerError> {
+    let query = |name| validate::required(name, request.query_param(name));
+
+    match segments {
+        ["openapi.json"] => Ok(Route::OpenApi),
+        ["stocks"] => Ok(Route::Stocks),
+        ["stocks", symbol] => Ok(Route::Stock(validate::symbol(symbol)?)),
+        ["stocks", symbol, "history"] => Ok(Route::History {
+            symbol: validate::symbol(symbol)?,
+            from: validate::date(query("from")?)?,
+            to: validate::date(query("to")?)?,
+        }),
+        ["stocks", symbol, "indicators"] => Ok(Route::Indicators {
+            symbol: validate::symbol(symbol)?,
+            spec: query("spec")?.to_string(),
+        }),
+        ["stocks", symbol, "snapshots", date] => Ok(Route::Snapshot {
+            symbol: validate::symbol(symbol)?,
+            date: validate::date(date)?.to_string(),
+        }),
+        ["search"] => Ok(Route::Search {
+            query: query("q")?.to_string(),
+            limit: validate::limit(
+                request.query_param("limit"),
+                DEFAULT_SEARCH_LIMIT,
+                MAX_SEARCH_LIMIT,
+            )?,
+        }),
+        ["market", "summary"] => market_route(MarketView::Summary, request),
+        ["market", "gainers"] => market_route(MarketView::Gainers, request),
+        ["market", "losers"] => market_route(MarketView::Losers, request),
+        ["market", "active"] => market_route(MarketView::MostActive, request),
+        ["market", "indices"] => Ok(Route::Proxy(Endpoint::MarketIndices)),
+        ["summaries", date] => Ok(Route::Summary(validate::date(date)?)),
+        ["job"] => Ok(Route::JobStatus),
+        _ => Err(WorkerError::NotFound(format!(
+            "no route for `{}`",
+            request.path
+        ))),
+    }
+}
+
+/// Routes under `/v1/watchlists`. Unlike the read-only routes, these care
+/// about the method, and all of them need a bearer token.
+fn watchlist_route(segments: &[&str], request: &HttpRequest) -> Result<Route, WorkerError> {
+    let token = validate::token(request.header("Authorization"))?;
+    let method = match request.method {
+        Method::Head => Method::Get,
+        method => method,
+    };
+
+    let action = match (segments, method) {
+        ([], Method::Get) => WatchlistAction::List,
+        ([], Method::Post) => {
+            let new: NewWatchlist = validate::body(request.body.as_deref())?;
+            let symbols = new
+                .symbols
+                .iter()
+                .map(|symbol| validate::symbol(symbol))
+                .collect::<Result<_, _>>()?;
+            WatchlistAction::Create(NewWatchlist { symbols, ..new })
+        }
+        ([id], Method::Get) => WatchlistAction::Get(validate::id(id)?),
+        ([id], Method::Patch) => {
+            let rename: WatchlistRename = validate::body(request.body.as_deref())?;
+            WatchlistAction::Rename(validate::id(id)?, rename.name)
+        }
+        ([id], Method::Delete) => WatchlistAction::Delete(validate::id(id)?),
+        ([id, "quotes"], Method::Get) => WatchlistAction::Quotes(validate::id(id)?),
+        ([id, "symbols", symbol], Method::Put) => {
+            WatchlistAction::Add(validate::id(id)?, validate::symbol(symbol)?)
+        }
+        ([id, "symbols", symbol], Method::Delete) => {
+            WatchlistAction::Remove(validate::id(id)?, validate::symbol(symbol)?)
+        }
+        ([], _) | ([_], _) | ([_, "quotes"], _) | ([_, "symbols", _], _) => {
+            return Err(WorkerError::MethodNotAllowed)
+        }
+        _ => {
+            return Err(WorkerError::NotFound(format!(
+                "no route for `{}`",
+                request.path
+            )))
+        }
+    };
+
+    Ok(Route::Watchlist { token, action })
+}
+
+/// Unknown `/stocks` paths fall back to the market indices, as in
+/// `tote_proxy`.
+fn stocks_route(request: &HttpRequest) -> Result<Route, WorkerError> {
+    let name = request.path.trim_end_matches('/').rsplit('/').next();
+    let view = match name.unwrap_or("") {
+        "get_all_stocks" => return Ok(Route::Proxy(Endpoint::SecuritiesAndIndices)),
+        "get_top_security" => {
+            return Ok(Route::Proxy(Endpoint::TopSecurity {
+                limit: limit(request)?,
+            }))
+        }
+        "get_market_summary" => MarketView::Summary,
+        "get_top_active_stocks" => MarketView::MostActive,
+        "get_advanced_security" => MarketView::Gainers,
+        "get_declines_security" => MarketView::Losers,
+        _ => return Ok(Route::Proxy(Endpoint::MarketIndices)),
+    };
+
+    market_route(view, request)
+}
+
+fn preflight(request: &HttpRequest) -> HttpResponse {
+    let is_cors = request.header("Origin").is_some()
+        && request.header("Access-Control-Request-Method").is_some();
+
+    match request.header("Access-Control-Request-Headers") {
+        Some(allow_headers) if is_cors => HttpResponse::new(200)
+            .with_header("Access-Control-Allow-Origin", "*")
+            .with_header(
+                "Access-Control-Allow-Methods",
+                "GET,HEAD,POST,PUT,PATCH,DELETE,OPTIONS",
+            )
+            .with_header("Access-Control-Max-Age", CORS_MAX_AGE)
+            .with_header("Access-Control-Allow-Headers", allow_headers),
+        _ => HttpResponse::new(200).with_header("Allow", ALLOWED_METHODS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryCache;
+    use crate::calendar::FixedClock;
+    use crate::errors::{ErrorBody, FetchError};
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct RecordingFetcher {
+        requests: RefCell<Vec<Endpoint>>,
+        fail: bool,
+    }
+
+    #[async_trait(?Send)]
+    impl Fetcher for RecordingFetcher {
+        async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError> {
+            self.requests.borrow_mut().push(endpoint.clone());
+            if self.fail {
+                Err(FetchError::Status {
+                    status: 503,
+                    url: endpoint.url("https://pse.test"),
+                })
+            } else if *endpoint == Endpoint::SecuritiesAndIndices {
+                Ok(LIST.to_string())
+            } else {
+                Ok(format!("{{\"method\":\"{}\"}}", endpoint.method()))
+            }
+        }
+    }
+
+    const LIST: &str = r#"[
+        {"totalVolume":"1,000","indicator":"U","percChangeClose":"1.25","lastTradedPrice":"1,010.00","securityAlias":"SM Investments Corporation","securitySymbol":"SM"},
+        {"totalVolume":"3,200","indicator":"D","percChangeClose":"-0.50","lastTradedPrice":"35.50","securityAlias":"Ayala Land, Inc.","securitySymbol":"ALI"},
+        {"totalVolume":"90,000","indicator":"U","percChangeClose":"4.00","lastTradedPrice":"1.50","securityAlias":"MerryMart Consumer Corp.","securitySymbol":"MM"},
+        {"totalVolume":"0","indicator":"U","percChangeClose":"0.80","lastTradedPrice":"6,000.00","securityAlias":"PSEi","securitySymbol":"PSEi"}
+    ]"#;
+
+    fn get(router: &Router<RecordingFetcher>, url: &str) -> HttpResponse {
+        block_on(router.handle(&HttpRequest::new(Method::Get, url)))
+    }
+
+    #[test]
+    fn routes_stocks_paths() {
+        let router = Router::new(RecordingFetcher::default());
+        for path in &[
+            "/stocks/get_all_stocks",
+            "/stocks/get_top_security?limit=5",
+            "/stocks/get_market_indices",
+            "/stocks/unknown",
+        ] {
+            let response = get(&router, path);
+            assert_eq!(response.status, 200);
+            assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+            assert_eq!(response.header("Vary"), Some("Origin"));
+        }
+
+        assert_eq!(
+            *router.fetcher.requests.borrow(),
+            vec![
+                Endpoint::SecuritiesAndIndices,
+                Endpoint::TopSecurity { limit: 5 },
+                Endpoint::MarketIndices,
+                Endpoint::MarketIndices,
+            ]
+        );
+
+        let response = get(&router, "/stocks/get_top_security");
+        assert_eq!(response.body.unwrap(), r#"{"method":"getTopSecurity"}"#);
+        assert_eq!(
+            router.fetcher.requests.borrow().last(),
+            Some(&Endpoint::TopSecurity {
+                limit: DEFAULT_TOP_LIMIT
+            })
+        );
+    }
+
+    fn symbols(response: HttpResponse) -> Vec<String> {
+        let stocks: Vec<crate::Stock> = serde_json::from_str(&response.body.unwrap()).unwrap();
+        stocks
+            .into_iter()
+            .map(|stock| stock.security_symbol)
+            .collect()
+    }
+
+    #[test]
+    fn computes_movers_from_the_securities_list() {
+        let router = Router::new(RecordingFetcher::default());
+        assert_eq!(
+            symbols(get(&router, "/stocks/get_advanced_security")),
+            vec!["MM", "SM"]
+        );
+        assert_eq!(
+            symbols(get(&router, "/stocks/get_declines_security")),
+            vec!["ALI"]
+        );
+        assert_eq!(
+            symbols(get(
+                &router,
+                "/stocks/get_top_active_stocks?board=main&limit=1"
+            )),
+            vec!["SM"]
+        );
+
+        let response = get(&router, "/stocks/get_market_summary?board=sme");
+        let summary: MarketSummary = serde_json::from_str(&response.body.unwrap()).unwrap();
+        assert_eq!(summary.board, Some(Board::Sme));
+        assert_eq!(summary.advance_decline.advances, 1);
+        assert_eq!(summary.most_active_by_volume[0].security_symbol, "MM");
+
+        let router = router.with_boards(Boards::parse(""));
+        let summary: MarketSummary =
+            serde_json::from_str(&get(&router, "/stocks/get_market_summary").body.unwrap())
+                .unwrap();
+        assert_eq!(summary.advance_decline.total(), 3);
+        assert!(router
+            .fetcher
+            .requests
+            .borrow()
+            .iter()
+            .all(|endpoint| *endpoint == Endpoint::SecuritiesAndIndices));
+
+        let response = get(&router, "/stocks/get_market_summary?board=pink");
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn answers_other_paths_and_methods_locally() {
+        let router = Router::new(RecordingFetcher::default());
+        assert_eq!(get(&router, "/").body.unwrap(), "Tote");
+
+        let request = HttpRequest::new(Method::Other, "/stocks/get_all_stocks");
+        let response = block_on(router.handle(&request));
+        assert_eq!(response.status, 405);
+        assert!(response.body.unwrap().contains("method_not_allowed"));
+
+        let response = get(&router, "/stocks/get_top_security?limit=lots");
+        assert_eq!(response.status, 400);
+        assert!(response.body.unwrap().contains("invalid_argument"));
+
+        let request = HttpRequest::new(Method::Head, "/stocks/get_all_stocks");
+        let response = block_on(router.handle(&request));
+        assert_eq!(response.status, 200);
+        assert!(response.body.is_none());
+        assert_eq!(router.fetcher.requests.borrow().len(), 1);
+    }
+
+    #[test]
+    fn handles_preflight() {
+        let router = Router::new(RecordingFetcher::default());
+        let request = HttpRequest::new(Method::Options, "/stocks/get_all_stocks")
+            .with_header("Origin", "https://example.com")
+            .with_header("Access-Control-Request-Method", "GET")
+            .with_header("Access-Control-Request-Headers", "X-Custom");
+        let response = block_on(router.handle(&request));
+        assert_eq!(
+            response.header("Access-Control-Allow-Headers"),
+            Some("X-Custom")
+        );
+        assert_eq!(response.header("Access-Control-Max-Age"), Some("86400"));
+
+        let request = HttpRequest::new(Method::Options, "/stocks/get_all_stocks");
+        let response = block_on(router.handle(&request));
+        assert_eq!(response.header("Allow"), Some(ALLOWED_METHODS));
+        assert!(router.fetcher.requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn caches_upstream_responses() {
+        let now = chrono::Utc.with_ymd_and_hms(2020, 10, 16, 2, 0, 0).unwrap();
+        let router = Router::new(RecordingFetcher::default())
+            .with_cache(ResponseCache::new(MemoryCache::new(), FixedClock(now)));
+
+        let first = get(&router, "/stocks/get_all_stocks");
+        assert_eq!(first.header("X-Cache"), Some("MISS"));
+        let second = get(&router, "/stocks/get_all_stocks");
+        assert_eq!(second.header("X-Cache"), Some("HIT"));
+        assert_eq!(second.header("Cache-Control"), Some("public, max-age=60"));
+        assert_eq!(second.body, first.body);
+        assert_eq!(router.fetcher.requests.borrow().len(), 1);
+    }
+
+    #[test]
+    fn reports_upstream_failures() {
+        let router = Router::new(RecordingFetcher {
+            fail: true,
+            ..Default::default()
+        });
+        let response = get(&router, "/stocks/get_market_indices");
+        assert_eq!(response.status, 502);
+        assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+        let body: ErrorBody = serde_json::from_str(&response.body.unwrap()).unwrap();
+        assert_eq!(body.code, "upstream_unavailable");
+        assert!(body.message.contains("HTTP 503"));
+    }
+
+    #[test]
+    fn keeps_watchlists_per_token() {
+        let router = Router::new(RecordingFetcher::default())
+            .with_storage(crate::storage::MemoryStorage::new());
+        let send = |method, url: &str, token: Option<&str>, body: Option<&str>| {
+            let mut request = HttpRequest::new(method, url);
+            if let Some(token) = token {
+                request = request.with_header("Authorization", &format!("Bearer {}", token));
+            }
+            request.body = body.map(str::to_string);
+            block_on(router.handle(&request))
+        };
+        let (alice, bob) = (Some("alice-token-0001"), Some("bob-token-000001"));
+
+        let response = send(Method::Get, "/v1/watchlists", None, None);
+        assert_eq!(response.status, 401);
+        let response = send(Method::Post, "/v1/watchlists", None, Some("{}"));
+        assert_eq!(response.status, 401);
+        let response = send(
+            Method::Post,
+            "/v1/watchlists",
+            alice,
+            Some(r#"{"name":"x","symbols":["JFC"]}"#),
+        );
+        assert_eq!(response.status, 400);
+        assert!(response
+            .body
+            .unwrap()
+            .contains("`JFC` is not in the security list"));
+        let response = send(Method::Post, "/v1/watchlists", alice, Some("{}"));
+        assert_eq!(response.status, 400);
+
+        let response = send(
+            Method::Post,
+            "/v1/watchlists",
+            alice,
+            Some(r#"{"name":"Mine","symbols":["sm"]}"#),
+        );
+        assert_eq!(response.status, 201);
+        assert_eq!(response.header("Cache-Control"), Some("no-store"));
+        let response = send(Method::Put, "/v1/watchlists/1/symbols/mm", alice, None);
+        assert!(response.body.unwrap().contains(r#""symbols":["SM","MM"]"#));
+
+        assert_eq!(send(Method::Get, "/v1/watchlists/1", bob, None).status, 404);
+        assert_eq!(
+            send(Method::Get, "/v1/watchlists", bob, None).body.unwrap(),
+            "[]"
+        );
+        assert_eq!(
+            send(Method::Post, "/v1/watchlists/1", alice, None).status,
+            405
+        );
+        assert_eq!(
+            send(Method::Get, "/v1/watchlists/one", alice, None).status,
+            400
+        );
+        assert_eq!(
+            send(Method::Get, "/v1/watchlists/1/other", alice, None).status,
+            404
+        );
+
+        let response = send(Method::Get, "/v1/watchlists/1/quotes", alice, None);
+        let quotes: crate::watchlist::WatchlistQuotes =
+            serde_json::from_str(&response.body.unwrap()).unwrap();
+        assert_eq!(quotes.quotes[1].security_symbol, "MM");
+    }
+
+    #[test]
+    fn only_reads_the_read_only_routes() {
+        let router = Router::new(RecordingFetcher::default());
+        let send = |method, url: &str| block_on(router.handle(&HttpRequest::new(method, url)));
+
+        assert_eq!(send(Method::Delete, "/v1/stocks").status, 405);
+        assert_eq!(send(Method::Post, "/v1/stocks/sm/history").status, 405);
+        assert_eq!(send(Method::Put, "/v1/market/summary").status, 405);
+        assert_eq!(send(Method::Patch, "/v1/nowhere").status, 404);
+        assert_eq!(send(Method::Head, "/v1/stocks").status, 200);
+        assert_eq!(router.fetcher.requests.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,278 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{cached_json, ResponseCache};
+use crate::errors::SearchError;
+use crate::security::SearchResponse;
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+use crate::{Stock, Stocks};
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 10;
+pub const MAX_SEARCH_LIMIT: usize = 50;
+
+/// How a result matched the query, best first.
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Contains,
+    Fuzzy,
+    /// Returned by PSE's `findSecurityOrCompany` rather than matched locally.
+    Upstream,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub symbol: String,
+    pub name: String,
+    pub kind: MatchKind,
+    /// Edits needed to turn the query into the matched text; 0 unless fuzzy.
+    pub distance: usize,
+}
+
+/// Levenshtein distance over characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Typos tolerated for a query of `len` characters.
+fn max_distance(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+fn match_stock(stock: &Stock, query: &str) -> Option<(MatchKind, usize)> {
+    let symbol = stock.security_symbol.to_lowercase();
+    let alias = stock.security_alias.to_lowercase();
+    let words: Vec<&str> = alias
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    if symbol == query {
+        return Some((MatchKind::Exact, 0));
+    }
+    if symbol.starts_with(query) || alias.starts_with(query) {
+        return Some((MatchKind::Prefix, 0));
+    }
+    if symbol.contains(query)
+        || alias.contains(query)
+        || words.iter().any(|word| word.starts_with(query))
+    {
+        return Some((MatchKind::Contains, 0));
+    }
+
+    let limit = max_distance(query.chars().count());
+    if limit == 0 {
+        return None;
+    }
+    std::iter::once(symbol.as_str())
+        .chain(words.iter().cloned())
+        .map(|text| edit_distance(query, text))
+        .min()
+        .filter(|distance| *distance <= limit)
+        .map(|distance| (MatchKind::Fuzzy, distance))
+}
+
+/// Matches `query` against symbols and names, best matches first: exact
+/// symbols, then prefixes, substrings and finally near misses, shorter
+/// symbols first within each group.
+pub fn rank(stocks: &[Stock], query: &str, limit: usize) -> Vec<SearchHit> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<SearchHit> = stocks
+        .iter()
+        .filter_map(|stock| {
+            match_stock(stock, &query).map(|(kind, distance)| SearchHit {
+                symbol: stock.security_symbol.clone(),
+                name: stock.security_alias.clone(),
+                kind,
+                distance,
+            })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| {
+        (a.kind, a.distance, a.symbol.len(), &a.symbol).cmp(&(
+            b.kind,
+            b.distance,
+            b.symbol.len(),
+            &b.symbol,
+        ))
+    });
+    hits.truncate(limit);
+    hits
+}
+
+/// Searches the (cached) list of securities, asking PSE's own search when
+/// nothing matches locally.
+pub async fn search<F>(
+    fetcher: &F,
+    cache: Option<&ResponseCache>,
+    query: &str,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, SearchError>
+where
+    F: Fetcher + ?Sized,
+{
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(SearchError::EmptyQuery);
+    }
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let stocks: Stocks = cached_json(cache, fetcher, &Endpoint::SecuritiesAndIndices).await?;
+    let hits = rank(&stocks.stocks, query, limit);
+    if !hits.is_empty() {
+        return Ok(hits);
+    }
+
+    let endpoint = Endpoint::FindSecurityOrCompany {
+        query: query.to_string(),
+        start: 0,
+        limit,
+    };
+    let response: SearchResponse = fetch_json(fetcher, &endpoint).await?;
+    Ok(response
+        .records
+        .into_iter()
+        .map(|security| SearchHit {
+            symbol: security.security_symbol,
+            name: security.security_name,
+            kind: MatchKind::Upstream,
+            distance: 0,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::FetchError;
+    use crate::Indicator;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use std::cell::RefCell;
+
+    fn stock(symbol: &str, alias: &str) -> Stock {
+        Stock {
+            total_volume: 0,
+            indicator: Indicator::Unchanged,
+            perc_change_close: 0.0,
+            last_traded_price: 1.0,
+            security_alias: alias.to_string(),
+            security_symbol: symbol.to_string(),
+        }
+    }
+
+    fn stocks() -> Vec<Stock> {
+        vec![
+            stock("SMPH", "SM Prime Holdings, Inc."),
+            stock("SM", "SM Investments Corporation"),
+            stock("ALI", "Ayala Land, Inc."),
+            stock("AC", "Ayala Corporation"),
+            stock("BDO", "BDO Unibank, Inc."),
+            stock("JFC", "Jollibee Foods Corporation"),
+        ]
+    }
+
+    fn symbols(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn ranks_exact_then_prefix_then_substring() {
+        let hits = rank(&stocks(), "sm", 10);
+        assert_eq!(symbols(&hits), vec!["SM", "SMPH"]);
+        assert_eq!(hits[0].kind, MatchKind::Exact);
+        assert_eq!(hits[1].kind, MatchKind::Prefix);
+
+        let hits = rank(&stocks(), "ayala", 10);
+        assert_eq!(symbols(&hits), vec!["AC", "ALI"]);
+
+        let hits = rank(&stocks(), "corp", 2);
+        assert_eq!(symbols(&hits), vec!["AC", "SM"]);
+        assert_eq!(hits[0].kind, MatchKind::Contains);
+    }
+
+    #[test]
+    fn tolerates_typos() {
+        let hits = rank(&stocks(), "jolibee", 10);
+        assert_eq!(symbols(&hits), vec!["JFC"]);
+        assert_eq!(hits[0].kind, MatchKind::Fuzzy);
+        assert_eq!(hits[0].distance, 1);
+
+        assert!(rank(&stocks(), "xy", 10).is_empty());
+        assert!(rank(&stocks(), "  ", 10).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    struct SearchFetcher {
+        requests: RefCell<Vec<Endpoint>>,
+    }
+
+    #[async_trait(?Send)]
+    impl Fetcher for SearchFetcher {
+        async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError> {
+            self.requests.borrow_mut().push(endpoint.clone());
+            match endpoint {
+                Endpoint::SecuritiesAndIndices => Ok(serde_json::to_string(&Stocks {
+                    as_of: None,
+                    stocks: stocks(),
+                })
+                .unwrap()),
+                _ => Ok(r#"{"records":[{"securityId":1,"companyId":2,"securitySymbol":"MWP","securityName":"Megawide Preferred"}]}"#.to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_pse_search() {
+        let fetcher = SearchFetcher {
+            requests: RefCell::new(Vec::new()),
+        };
+        let hits = block_on(search(&fetcher, None, "BDO", None)).unwrap();
+        assert_eq!(symbols(&hits), vec!["BDO"]);
+        assert_eq!(fetcher.requests.borrow().len(), 1);
+
+        let hits = block_on(search(&fetcher, None, "megawide", Some(500))).unwrap();
+        assert_eq!(symbols(&hits), vec!["MWP"]);
+        assert_eq!(hits[0].kind, MatchKind::Upstream);
+        assert_eq!(
+            fetcher.requests.borrow().last(),
+            Some(&Endpoint::FindSecurityOrCompany {
+                query: "megawide".to_string(),
+                start: 0,
+                limit: MAX_SEARCH_LIMIT,
+            })
+        );
+
+        assert!(matches!(
+            block_on(search(&fetcher, None, " ", None)),
+            Err(SearchError::EmptyQuery)
+        ));
+    }
+}
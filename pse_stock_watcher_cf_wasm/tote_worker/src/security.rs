@@ -0,0 +1,58 @@
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+use tote::stock::de_number;
+
+use crate::errors::HistoryError;
+use crate::upstream::{fetch_json, Endpoint, Fetcher};
+
+/// Matches requested when resolving a symbol to its PSE ids.
+const RESOLVE_LIMIT: usize = 10;
+
+/// A listed security and the ids PSE's company pages are keyed by.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Security {
+    #[serde(deserialize_with = "de_id")]
+    pub security_id: u32,
+    #[serde(deserialize_with = "de_id")]
+    pub company_id: u32,
+    pub security_symbol: String,
+    #[serde(default)]
+    pub security_name: String,
+}
+
+/// The response of `findSecurityOrCompany`.
+#[derive(Debug, Deserialize)]
+pub struct SearchResponse {
+    #[serde(default)]
+    pub records: Vec<Security>,
+}
+
+fn de_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let value = de_number(deserializer)?;
+    if value >= 0.0 && value <= u32::MAX as f64 && value.fract() == 0.0 {
+        Ok(value as u32)
+    } else {
+        Err(de::Error::custom(format!("expected an id, got {}", value)))
+    }
+}
+
+/// Looks up the security listed under exactly `symbol`.
+pub async fn resolve_security<F>(fetcher: &F, symbol: &str) -> Result<Security, HistoryError>
+where
+    F: Fetcher + ?Sized,
+{
+    let symbol = symbol.trim();
+    let endpoint = Endpoint::FindSecurityOrCompany {
+        query: symbol.to_string(),
+        start: 0,
+        limit: RESOLVE_LIMIT,
+    };
+    let response: SearchResponse = fetch_json(fetcher, &endpoint).await?;
+
+    response
+        .records
+        .into_iter()
+        .find(|security| security.security_symbol.eq_ignore_ascii_case(symbol))
+        .ok_or_else(|| HistoryError::UnknownSymbol(symbol.to_string()))
+}
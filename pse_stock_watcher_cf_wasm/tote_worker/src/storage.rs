@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::errors::StorageError;
+
+/// Key-value storage used to persist archived market data.
+#[async_trait(?Send)]
+pub trait Storage {
+    async fn get(&self, key: &str) -> Result<Option<String>, StorageError>;
+    async fn put(&self, key: &str, value: &str) -> Result<(), StorageError>;
+    /// Stores `value` for at least `ttl_seconds`, after which it may be
+    /// dropped.
+    async fn put_expiring(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: u64,
+    ) -> Result<(), StorageError>;
+}
+
+/// Storage kept in memory for the lifetime of the value, used in tests.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    items: RefCell<HashMap<String, String>>,
+    ttls: RefCell<HashMap<String, u64>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+
+    /// The TTL `key` was last stored with, if it was stored expiring.
+    pub fn ttl(&self, key: &str) -> Option<u64> {
+        self.ttls.borrow().get(key).copied()
+    }
+}
+
+#[async_trait(?Send)]
+impl Storage for MemoryStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.items.borrow().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.items
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+        self.ttls.borrow_mut().remove(key);
+        Ok(())
+    }
+
+    async fn put_expiring(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: u64,
+    ) -> Result<(), StorageError> {
+        self.put(key, value).await?;
+        self.ttls.borrow_mut().insert(key.to_string(), ttl_seconds);
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// A Workers KV namespace bound to the worker's global scope.
+    #[derive(Debug, Clone)]
+    pub type KvNamespace;
+
+    #[wasm_bindgen(method, catch, js_name = get)]
+    fn get_text(this: &KvNamespace, key: &str) -> Result<js_sys::Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = put)]
+    fn put_text(this: &KvNamespace, key: &str, value: &str) -> Result<js_sys::Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = put)]
+    fn put_text_with_options(
+        this: &KvNamespace,
+        key: &str,
+        value: &str,
+        options: &JsValue,
+    ) -> Result<js_sys::Promise, JsValue>;
+}
+
+/// Storage backed by the Workers KV namespace bound as `binding` in
+/// `wrangler.toml`.
+#[derive(Debug, Clone)]
+pub struct KvStorage {
+    namespace: KvNamespace,
+}
+
+impl KvStorage {
+    pub fn from_binding(binding: &str) -> Result<Self, StorageError> {
+        let namespace = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(binding))
+            .ok()
+            .filter(|value| value.is_object())
+            .ok_or_else(|| StorageError::Unavailable(binding.to_string()))?;
+
+        Ok(Self {
+            namespace: namespace.unchecked_into(),
+        })
+    }
+}
+
+fn request_error(err: JsValue) -> StorageError {
+    StorageError::Request(err.as_string().unwrap_or_else(|| format!("{:?}", err)))
+}
+
+#[async_trait(?Send)]
+impl Storage for KvStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let promise = self.namespace.get_text(key).map_err(request_error)?;
+        let value = JsFuture::from(promise).await.map_err(request_error)?;
+        Ok(value.as_string())
+    }
+
+    async fn put(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        let promise = self.namespace.put_text(key, value).map_err(request_error)?;
+        JsFuture::from(promise).await.map_err(request_error)?;
+        Ok(())
+    }
+
+    async fn put_expiring(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: u64,
+    ) -> Result<(), StorageError> {
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(
+            &options,
+            &JsValue::from_str("expirationTtl"),
+            &JsValue::from_f64(ttl_seconds as f64),
+        )
+        .map_err(request_error)?;
+        let promise = self
+            .namespace
+            .put_text_with_options(key, value, &options)
+            .map_err(request_error)?;
+        JsFuture::from(promise).await.map_err(request_error)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,496 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::cache::{cached_json, ResponseCache};
+use crate::calendar::{self, Clock};
+use crate::errors::{ErrorBody, StreamError, WorkerError};
+use crate::http::HttpResponse;
+use crate::upstream::{js_message, Endpoint, Fetcher};
+use crate::validate;
+use crate::{Stock, Stocks};
+
+pub const STREAM_PATH: &str = "/v1/stream";
+
+/// Most symbols a single stream may follow.
+pub const MAX_STREAM_SYMBOLS: usize = 20;
+
+/// Most streams a worker instance serves at once.
+pub const MAX_SUBSCRIPTIONS: usize = 50;
+
+/// How a stream polls upstream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamConfig {
+    /// Time between polls. Quotes are cached for a minute while the market is
+    /// open, so shorter intervals mostly produce heartbeats.
+    pub poll_interval: Duration,
+    /// How long a stream stays open before the client is asked to reconnect.
+    pub max_duration: Duration,
+    /// Reconnection delay suggested to `EventSource` clients.
+    pub retry: Duration,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::seconds(20),
+            max_duration: Duration::minutes(10),
+            retry: Duration::seconds(5),
+        }
+    }
+}
+
+/// Parses the comma separated `symbols` of a stream request.
+pub fn parse_symbols(value: Option<&str>) -> Result<Vec<String>, WorkerError> {
+    let mut symbols: Vec<String> = Vec::new();
+    for symbol in validate::required("symbols", value)?.split(',') {
+        let symbol = validate::symbol(symbol)?;
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+
+    if symbols.len() > MAX_STREAM_SYMBOLS {
+        return Err(WorkerError::InvalidArgument(format!(
+            "a stream follows at most {} symbols, got {}",
+            MAX_STREAM_SYMBOLS,
+            symbols.len()
+        )));
+    }
+    Ok(symbols)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Heartbeat {
+    at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Closed {
+    next_open: DateTime<FixedOffset>,
+}
+
+/// A message sent down a stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Retry(Duration),
+    Quote(Stock),
+    Heartbeat(DateTime<Utc>),
+    /// Upstream could not be read; the stream keeps polling.
+    Error(ErrorBody),
+    /// The market closed and the stream ended.
+    Closed(DateTime<FixedOffset>),
+}
+
+impl StreamEvent {
+    /// The event in `text/event-stream` format.
+    pub fn to_sse(&self) -> String {
+        let (event, data) = match self {
+            StreamEvent::Retry(retry) => {
+                return format!("retry: {}\n\n", retry.num_milliseconds());
+            }
+            StreamEvent::Quote(stock) => ("quote", serde_json::to_string(stock)),
+            StreamEvent::Heartbeat(at) => {
+                ("heartbeat", serde_json::to_string(&Heartbeat { at: *at }))
+            }
+            StreamEvent::Error(body) => ("error", serde_json::to_string(body)),
+            StreamEvent::Closed(next_open) => (
+                "closed",
+                serde_json::to_string(&Closed {
+                    next_open: *next_open,
+                }),
+            ),
+        };
+        format!("event: {}\ndata: {}\n\n", event, data.unwrap_or_default())
+    }
+}
+
+/// Remembers the last quote sent for each symbol.
+#[derive(Debug, Default)]
+pub struct QuoteTracker {
+    last: HashMap<String, Stock>,
+}
+
+impl QuoteTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Quotes of `symbols` that differ from the ones last returned, all of
+    /// them on the first call.
+    pub fn changes(&mut self, stocks: &[Stock], symbols: &[String]) -> Vec<Stock> {
+        let mut changed = Vec::new();
+        for stock in stocks {
+            let symbol = stock.security_symbol.to_ascii_uppercase();
+            if !symbols.contains(&symbol) || self.last.get(&symbol) == Some(stock) {
+                continue;
+            }
+            self.last.insert(symbol, stock.clone());
+            changed.push(stock.clone());
+        }
+        changed
+    }
+}
+
+/// Where stream events are written. Fails once the client has gone away.
+#[async_trait(?Send)]
+pub trait EventSink {
+    async fn send(&mut self, event: &str) -> Result<(), StreamError>;
+}
+
+#[async_trait(?Send)]
+pub trait Timer {
+    async fn sleep(&self, duration: Duration);
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// Sleeps on the worker's `setTimeout`.
+pub struct WebTimer;
+
+#[async_trait(?Send)]
+impl Timer for WebTimer {
+    async fn sleep(&self, duration: Duration) {
+        let timeout = duration.num_milliseconds().clamp(0, i32::MAX as i64) as i32;
+        let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+            set_timeout(&resolve, timeout);
+        });
+        let _ = JsFuture::from(promise).await;
+    }
+}
+
+/// Writes events into the writable side of the `TransformStream` whose
+/// readable side is the response body.
+pub struct WebSink {
+    writer: web_sys::WritableStreamDefaultWriter,
+}
+
+impl WebSink {
+    pub fn new(writer: web_sys::WritableStreamDefaultWriter) -> Self {
+        Self { writer }
+    }
+
+    pub async fn close(self) {
+        let _ = JsFuture::from(self.writer.close()).await;
+    }
+}
+
+#[async_trait(?Send)]
+impl EventSink for WebSink {
+    async fn send(&mut self, event: &str) -> Result<(), StreamError> {
+        let chunk = js_sys::Uint8Array::from(event.as_bytes());
+        JsFuture::from(self.writer.write_with_chunk(&chunk))
+            .await
+            .map(|_| ())
+            .map_err(|e| StreamError::Disconnected(js_message(e)))
+    }
+}
+
+/// Counts open streams against [`MAX_SUBSCRIPTIONS`].
+#[derive(Debug)]
+pub struct Subscriptions {
+    active: Cell<usize>,
+    max: usize,
+}
+
+/// An open stream; dropping it frees the slot.
+#[derive(Debug)]
+pub struct Subscription {
+    subscriptions: Rc<Subscriptions>,
+}
+
+impl Subscriptions {
+    pub fn new(max: usize) -> Rc<Self> {
+        Rc::new(Self {
+            active: Cell::new(0),
+            max,
+        })
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.get()
+    }
+
+    pub fn acquire(self: &Rc<Self>) -> Result<Subscription, WorkerError> {
+        if self.active.get() >= self.max {
+            return Err(WorkerError::TooManyRequests(format!(
+                "too many open streams, at most {} are allowed",
+                self.max
+            )));
+        }
+        self.active.set(self.active.get() + 1);
+        Ok(Subscription {
+            subscriptions: Rc::clone(self),
+        })
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let active = &self.subscriptions.active;
+        active.set(active.get() - 1);
+    }
+}
+
+/// Status and headers of a stream response; the body is written as events.
+pub fn response_head() -> HttpResponse {
+    HttpResponse::new(200)
+        .with_header("Content-Type", "text/event-stream;charset=UTF-8")
+        .with_header("Cache-Control", "no-cache")
+        .with_header("Access-Control-Allow-Origin", "*")
+}
+
+/// Sends the current quotes of `symbols`, then polls upstream and sends the
+/// ones that changed, or a heartbeat when none did. Ends when the market is
+/// closed, after [`StreamConfig::max_duration`] or once the client goes away.
+pub async fn run<F, C, T, W>(
+    fetcher: &F,
+    cache: Option<&ResponseCache>,
+    clock: &C,
+    timer: &T,
+    config: &StreamConfig,
+    symbols: &[String],
+    sink: &mut W,
+) -> Result<(), StreamError>
+where
+    F: Fetcher + ?Sized,
+    C: Clock + ?Sized,
+    T: Timer + ?Sized,
+    W: EventSink + ?Sized,
+{
+    let started = clock.now();
+    let mut tracker = QuoteTracker::new();
+    sink.send(&StreamEvent::Retry(config.retry).to_sse())
+        .await?;
+
+    loop {
+        let now = clock.now();
+        let events =
+            match cached_json::<Stocks, _>(cache, fetcher, &Endpoint::SecuritiesAndIndices).await {
+                Ok(stocks) => {
+                    let changes = tracker.changes(&stocks.stocks, symbols);
+                    if changes.is_empty() {
+                        vec![StreamEvent::Heartbeat(now)]
+                    } else {
+                        changes.into_iter().map(StreamEvent::Quote).collect()
+                    }
+                }
+                Err(err) => vec![StreamEvent::Error(WorkerError::from(err).body())],
+            };
+        for event in events {
+            sink.send(&event.to_sse()).await?;
+        }
+
+        if !calendar::is_market_open(&now) {
+            let closed = StreamEvent::Closed(calendar::next_open(&now));
+            return sink.send(&closed.to_sse()).await;
+        }
+        if now.signed_duration_since(started) >= config.max_duration {
+            return Ok(());
+        }
+        timer.sleep(config.poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::FetchError;
+    use crate::Indicator;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+    use std::cell::RefCell;
+
+    /// A clock that moves only when slept on.
+    struct TestTimer(Cell<DateTime<Utc>>);
+
+    impl Clock for TestTimer {
+        fn now(&self) -> DateTime<Utc> {
+            self.0.get()
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Timer for TestTimer {
+        async fn sleep(&self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    /// Serves one price per poll, failing once prices run out.
+    struct TickFetcher {
+        prices: RefCell<Vec<f64>>,
+    }
+
+    #[async_trait(?Send)]
+    impl Fetcher for TickFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            let mut prices = self.prices.borrow_mut();
+            if prices.is_empty() {
+                return Err(FetchError::Network("offline".to_string()));
+            }
+            let price = prices.remove(0);
+            Ok(serde_json::to_string(&Stocks {
+                as_of: None,
+                stocks: vec![stock("SM", price), stock("ALI", 35.0)],
+            })
+            .unwrap())
+        }
+    }
+
+    #[derive(Default)]
+    struct VecSink {
+        events: Vec<String>,
+        capacity: Option<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl EventSink for VecSink {
+        async fn send(&mut self, event: &str) -> Result<(), StreamError> {
+            if Some(self.events.len()) == self.capacity {
+                return Err(StreamError::Disconnected("client went away".to_string()));
+            }
+            self.events.push(event.to_string());
+            Ok(())
+        }
+    }
+
+    fn stock(symbol: &str, price: f64) -> Stock {
+        Stock {
+            total_volume: 100,
+            indicator: Indicator::Unchanged,
+            perc_change_close: 0.0,
+            last_traded_price: price,
+            security_alias: symbol.to_string(),
+            security_symbol: symbol.to_string(),
+        }
+    }
+
+    fn manila(hour: u32, minute: u32) -> TestTimer {
+        TestTimer(Cell::new(
+            calendar::manila()
+                .with_ymd_and_hms(2020, 10, 16, hour, minute, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+        ))
+    }
+
+    fn kinds(sink: &VecSink) -> Vec<&str> {
+        sink.events
+            .iter()
+            .map(|event| {
+                event
+                    .lines()
+                    .next()
+                    .unwrap()
+                    .trim_start_matches("event: ")
+                    .split(':')
+                    .next()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_symbols() {
+        assert_eq!(
+            parse_symbols(Some("sm, ali,SM")).unwrap(),
+            vec!["SM", "ALI"]
+        );
+        assert_eq!(parse_symbols(None).unwrap_err().code(), "invalid_argument");
+        assert_eq!(
+            parse_symbols(Some("SM,S M")).unwrap_err().code(),
+            "invalid_symbol"
+        );
+
+        let many: Vec<String> = (0..=MAX_STREAM_SYMBOLS)
+            .map(|i| format!("S{}", i))
+            .collect();
+        assert!(parse_symbols(Some(&many.join(","))).is_err());
+    }
+
+    #[test]
+    fn pushes_only_changed_quotes() {
+        let fetcher = TickFetcher {
+            prices: RefCell::new(vec![1000.0, 1000.0, 1010.0]),
+        };
+        let timer = manila(14, 59);
+        let mut sink = VecSink::default();
+        let symbols = vec!["SM".to_string()];
+
+        block_on(run(
+            &fetcher,
+            None,
+            &timer,
+            &timer,
+            &StreamConfig::default(),
+            &symbols,
+            &mut sink,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            kinds(&sink),
+            vec!["retry", "quote", "heartbeat", "quote", "error", "closed"]
+        );
+        assert!(sink.events[3].contains("\"lastTradedPrice\":1010.0"));
+        assert!(sink.events.iter().all(|event| !event.contains("ALI")));
+        assert!(sink.events[4].contains("upstream_unavailable"));
+        assert!(sink.events[5].contains("2020-10-19T09:30:00+08:00"));
+    }
+
+    #[test]
+    fn stops_after_the_maximum_duration_or_disconnect() {
+        let fetcher = TickFetcher {
+            prices: RefCell::new(vec![1000.0; 100]),
+        };
+        let config = StreamConfig {
+            max_duration: Duration::minutes(1),
+            ..StreamConfig::default()
+        };
+        let timer = manila(10, 0);
+        let mut sink = VecSink::default();
+        let symbols = vec!["SM".to_string()];
+        block_on(run(
+            &fetcher, None, &timer, &timer, &config, &symbols, &mut sink,
+        ))
+        .unwrap();
+        assert_eq!(
+            kinds(&sink),
+            vec!["retry", "quote", "heartbeat", "heartbeat", "heartbeat"]
+        );
+
+        let mut sink = VecSink {
+            capacity: Some(2),
+            ..VecSink::default()
+        };
+        let result = block_on(run(
+            &fetcher, None, &timer, &timer, &config, &symbols, &mut sink,
+        ));
+        assert!(matches!(result, Err(StreamError::Disconnected(_))));
+    }
+
+    #[test]
+    fn limits_open_streams() {
+        let subscriptions = Subscriptions::new(1);
+        let first = subscriptions.acquire().unwrap();
+        let err = subscriptions.acquire().unwrap_err();
+        assert_eq!(err.status(), 429);
+
+        drop(first);
+        assert_eq!(subscriptions.active(), 0);
+        assert!(subscriptions.acquire().is_ok());
+    }
+}
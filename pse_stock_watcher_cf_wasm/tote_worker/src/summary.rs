@@ -0,0 +1,335 @@
+use std::cmp::Ordering;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tote::breadth::{AdvanceDecline, SecurityQuote};
+
+use crate::{Stock, Stocks};
+
+/// Rows kept in each list of a [`DailySummary`].
+pub const SUMMARY_LIMIT: usize = 10;
+
+/// The day's biggest movers, as stored alongside each archived snapshot.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub top_gainers: Vec<Stock>,
+    pub top_losers: Vec<Stock>,
+    pub most_active: Vec<Stock>,
+}
+
+pub fn summary_key(date: NaiveDate) -> String {
+    format!("summary:{}", date)
+}
+
+fn top_by<F>(stocks: &[Stock], limit: usize, keep: fn(&Stock) -> bool, compare: F) -> Vec<Stock>
+where
+    F: Fn(&Stock, &Stock) -> Ordering,
+{
+    let mut result: Vec<Stock> = stocks.iter().filter(|stock| keep(stock)).cloned().collect();
+    result.sort_by(|a, b| compare(a, b).then_with(|| a.security_symbol.cmp(&b.security_symbol)));
+    result.truncate(limit);
+    result
+}
+
+pub fn top_gainers(stocks: &[Stock], limit: usize) -> Vec<Stock> {
+    top_by(
+        stocks,
+        limit,
+        |stock| stock.perc_change_close > 0.0,
+        |a, b| b.perc_change_close.total_cmp(&a.perc_change_close),
+    )
+}
+
+pub fn top_losers(stocks: &[Stock], limit: usize) -> Vec<Stock> {
+    top_by(
+        stocks,
+        limit,
+        |stock| stock.perc_change_close < 0.0,
+        |a, b| a.perc_change_close.total_cmp(&b.perc_change_close),
+    )
+}
+
+/// Most traded by value, the last price times the volume.
+pub fn most_active(stocks: &[Stock], limit: usize) -> Vec<Stock> {
+    top_by(
+        stocks,
+        limit,
+        |stock| stock.total_volume > 0,
+        |a, b| b.value().total_cmp(&a.value()),
+    )
+}
+
+/// Most traded by number of shares.
+pub fn most_active_by_volume(stocks: &[Stock], limit: usize) -> Vec<Stock> {
+    top_by(
+        stocks,
+        limit,
+        |stock| stock.total_volume > 0,
+        |a, b| b.total_volume.cmp(&a.total_volume),
+    )
+}
+
+pub fn advance_decline(stocks: &[Stock]) -> AdvanceDecline {
+    let quotes: Vec<SecurityQuote> = stocks.iter().map(SecurityQuote::from).collect();
+    AdvanceDecline::from_quotes(&quotes)
+}
+
+impl DailySummary {
+    /// Ranks the securities in `stocks`, leaving out index rows.
+    pub fn new(date: NaiveDate, stocks: &[Stock]) -> Self {
+        let securities: Vec<Stock> = stocks
+            .iter()
+            .filter(|stock| !is_index(stock))
+            .cloned()
+            .collect();
+        Self {
+            date,
+            top_gainers: top_gainers(&securities, SUMMARY_LIMIT),
+            top_losers: top_losers(&securities, SUMMARY_LIMIT),
+            most_active: most_active(&securities, SUMMARY_LIMIT),
+        }
+    }
+}
+
+/// Worker variable listing the SME board symbols, comma separated, for when
+/// [`DEFAULT_SME_SYMBOLS`] falls behind PSE's listings.
+pub const SME_BOARD_BINDING: &str = "SME_BOARD_SYMBOLS";
+
+/// Securities on PSE's Small, Medium and Emerging board. The securities list
+/// does not say which board a security trades on.
+pub const DEFAULT_SME_SYMBOLS: &[&str] = &["IPM", "MFIN", "MM", "X"];
+
+/// Index rows PSE mixes into the securities list.
+pub const INDEX_SYMBOLS: &[&str] = &["PSEI", "ALL", "FIN", "IND", "HDG", "PRO", "SVC", "M-O"];
+
+/// Whether `stock` is one of the [`INDEX_SYMBOLS`] rather than a security.
+pub fn is_index(stock: &Stock) -> bool {
+    INDEX_SYMBOLS
+        .iter()
+        .any(|symbol| stock.security_symbol.eq_ignore_ascii_case(symbol))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Board {
+    Main,
+    Sme,
+    Etf,
+}
+
+impl Board {
+    pub fn parse(board: &str) -> Option<Self> {
+        match board.trim().to_ascii_lowercase().as_str() {
+            "main" => Some(Board::Main),
+            "sme" => Some(Board::Sme),
+            "etf" => Some(Board::Etf),
+            _ => None,
+        }
+    }
+}
+
+/// Sorts securities onto boards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boards {
+    pub sme: Vec<String>,
+}
+
+impl Default for Boards {
+    fn default() -> Self {
+        Self {
+            sme: DEFAULT_SME_SYMBOLS.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl Boards {
+    /// Reads a comma separated list of SME board symbols.
+    pub fn parse(sme: &str) -> Self {
+        Self {
+            sme: sme
+                .split(',')
+                .map(|symbol| symbol.trim().to_ascii_uppercase())
+                .filter(|symbol| !symbol.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The board `stock` trades on, or `None` for index rows.
+    pub fn board_of(&self, stock: &Stock) -> Option<Board> {
+        let symbol = stock.security_symbol.to_ascii_uppercase();
+        if is_index(stock) {
+            None
+        } else if stock
+            .security_alias
+            .to_ascii_lowercase()
+            .contains("exchange traded fund")
+        {
+            Some(Board::Etf)
+        } else if self.sme.contains(&symbol) {
+            Some(Board::Sme)
+        } else {
+            Some(Board::Main)
+        }
+    }
+
+    /// The securities on `board`, or on any board when `board` is `None`.
+    pub fn filter(&self, stocks: &[Stock], board: Option<Board>) -> Vec<Stock> {
+        stocks
+            .iter()
+            .filter(|stock| match self.board_of(stock) {
+                Some(found) => board.is_none() || board == Some(found),
+                None => false,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Movers and breadth worked out from the securities list, so they do not
+/// depend on PSE's own daily summary pages.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketSummary {
+    pub as_of: Option<NaiveDateTime>,
+    pub board: Option<Board>,
+    #[schemars(with = "AdvanceDeclineSchema")]
+    pub advance_decline: AdvanceDecline,
+    pub top_gainers: Vec<Stock>,
+    pub top_losers: Vec<Stock>,
+    pub most_active_by_value: Vec<Stock>,
+    pub most_active_by_volume: Vec<Stock>,
+}
+
+/// Schema of `tote`'s [`AdvanceDecline`].
+#[derive(JsonSchema)]
+#[schemars(rename = "AdvanceDecline")]
+#[allow(dead_code)]
+struct AdvanceDeclineSchema {
+    advances: usize,
+    declines: usize,
+    unchanged: usize,
+}
+
+impl MarketSummary {
+    pub fn new(stocks: &Stocks, boards: &Boards, board: Option<Board>, limit: usize) -> Self {
+        let stocks_on_board = boards.filter(&stocks.stocks, board);
+        Self {
+            as_of: stocks.as_of,
+            board,
+            advance_decline: advance_decline(&stocks_on_board),
+            top_gainers: top_gainers(&stocks_on_board, limit),
+            top_losers: top_losers(&stocks_on_board, limit),
+            most_active_by_value: most_active(&stocks_on_board, limit),
+            most_active_by_volume: most_active_by_volume(&stocks_on_board, limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Indicator;
+
+    fn stock(symbol: &str, change: f64, price: f64, volume: u64) -> Stock {
+        Stock {
+            total_volume: volume,
+            indicator: Indicator::Unchanged,
+            perc_change_close: change,
+            last_traded_price: price,
+            security_alias: symbol.to_string(),
+            security_symbol: symbol.to_string(),
+        }
+    }
+
+    fn symbols(stocks: &[Stock]) -> Vec<&str> {
+        stocks
+            .iter()
+            .map(|stock| stock.security_symbol.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn ranks_movers() {
+        let stocks = vec![
+            stock("SM", 1.5, 1000.0, 100),
+            stock("ALI", -2.0, 35.0, 10_000),
+            stock("BDO", 3.0, 120.0, 500),
+            stock("AC", 0.0, 700.0, 0),
+            stock("JFC", -0.5, 200.0, 50),
+            stock("MEG", 1.5, 3.0, 1_000_000),
+        ];
+
+        let summary = DailySummary::new(NaiveDate::from_ymd_opt(2020, 10, 16).unwrap(), &stocks);
+        assert_eq!(symbols(&summary.top_gainers), vec!["BDO", "MEG", "SM"]);
+        assert_eq!(symbols(&summary.top_losers), vec!["ALI", "JFC"]);
+        assert_eq!(
+            symbols(&summary.most_active),
+            vec!["MEG", "ALI", "SM", "BDO", "JFC"]
+        );
+        assert_eq!(symbols(&top_gainers(&stocks, 1)), vec!["BDO"]);
+        assert_eq!(
+            symbols(&most_active_by_volume(&stocks, 2)),
+            vec!["MEG", "ALI"]
+        );
+    }
+
+    #[test]
+    fn daily_summary_skips_index_rows() {
+        let stocks = vec![
+            stock("SM", 1.5, 1000.0, 100),
+            stock("PSEi", 0.8, 6000.0, 2_000_000),
+            stock("FIN", -1.2, 1500.0, 0),
+            stock("ALI", -2.0, 35.0, 10_000),
+        ];
+
+        let summary = DailySummary::new(NaiveDate::from_ymd_opt(2020, 10, 16).unwrap(), &stocks);
+        assert_eq!(symbols(&summary.top_gainers), vec!["SM"]);
+        assert_eq!(symbols(&summary.top_losers), vec!["ALI"]);
+        assert_eq!(symbols(&summary.most_active), vec!["ALI", "SM"]);
+    }
+
+    #[test]
+    fn summarizes_by_board() {
+        let mut etf = stock("FMETF", 0.0, 113.0, 200);
+        etf.security_alias = "First Metro Philippine Equity Exchange Traded Fund, Inc.".to_string();
+        let stocks = Stocks {
+            as_of: None,
+            stocks: vec![
+                stock("SM", 1.5, 1000.0, 100),
+                stock("ALI", -2.0, 35.0, 10_000),
+                stock("MM", 4.0, 1.5, 90_000),
+                stock("PSEi", 0.8, 6000.0, 0),
+                etf,
+            ],
+        };
+        let boards = Boards::default();
+        assert_eq!(boards.board_of(&stocks.stocks[3]), None);
+
+        let all = MarketSummary::new(&stocks, &boards, None, 10);
+        assert_eq!(symbols(&all.top_gainers), vec!["MM", "SM"]);
+        assert_eq!(
+            all.advance_decline,
+            AdvanceDecline {
+                advances: 2,
+                declines: 1,
+                unchanged: 1,
+            }
+        );
+
+        let main = MarketSummary::new(&stocks, &boards, Some(Board::Main), 1);
+        assert_eq!(symbols(&main.top_gainers), vec!["SM"]);
+        assert_eq!(symbols(&main.most_active_by_volume), vec!["ALI"]);
+        assert_eq!(main.advance_decline.total(), 2);
+
+        let etfs = MarketSummary::new(&stocks, &boards, Some(Board::Etf), 10);
+        assert_eq!(symbols(&etfs.most_active_by_value), vec!["FMETF"]);
+
+        let custom = Boards::parse(" sm, ");
+        assert_eq!(custom.board_of(&stocks.stocks[0]), Some(Board::Sme));
+        assert_eq!(Board::parse("SME"), Some(Board::Sme));
+        assert_eq!(Board::parse("pink"), None);
+    }
+}
@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, Response};
+
+use crate::errors::FetchError;
+
+/// Where PSE's `stockMarket` pages live unless `PSE_BASE_URL` says otherwise.
+pub const DEFAULT_BASE_URL: &str = "https://www.pse.com.ph/stockMarket";
+
+/// Worker variable (`[vars]` in `wrangler.toml`) overriding the upstream base
+/// URL, e.g. to go through our own proxy or a local fixture server.
+pub const BASE_URL_BINDING: &str = "PSE_BASE_URL";
+
+const PSE_ORIGIN: &str = "https://www.pse.com.ph";
+const PSE_REFERER: &str = "https://www.pse.com.ph/stockMarket/home.html";
+
+/// The PSE `method=` calls the worker makes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    SecuritiesAndIndices,
+    FindSecurityOrCompany {
+        query: String,
+        start: usize,
+        limit: usize,
+    },
+    CompanyInfo {
+        company: u32,
+        security: u32,
+    },
+    RecentSecurityQuoteData {
+        security: u32,
+    },
+    MarketIndices,
+    TopActiveStocks,
+    TopSecurity {
+        limit: usize,
+    },
+    AdvancedSecurity,
+    DeclinesSecurity,
+}
+
+impl Endpoint {
+    pub fn page(&self) -> &'static str {
+        match self {
+            Endpoint::SecuritiesAndIndices | Endpoint::FindSecurityOrCompany { .. } => "home.html",
+            Endpoint::CompanyInfo { .. } => "companyInfo.html",
+            Endpoint::RecentSecurityQuoteData { .. } => "companyInfoHistoricalData.html",
+            Endpoint::MarketIndices
+            | Endpoint::TopActiveStocks
+            | Endpoint::TopSecurity { .. }
+            | Endpoint::AdvancedSecurity
+            | Endpoint::DeclinesSecurity => "dailySummary.html",
+        }
+    }
+
+    pub fn method(&self) -> &'static str {
+        match self {
+            Endpoint::SecuritiesAndIndices => "getSecuritiesAndIndicesForPublic",
+            Endpoint::FindSecurityOrCompany { .. } => "findSecurityOrCompany",
+            Endpoint::CompanyInfo { .. } => "fetchHeaderData",
+            Endpoint::RecentSecurityQuoteData { .. } => "getRecentSecurityQuoteData",
+            Endpoint::MarketIndices => "getMarketIndices",
+            Endpoint::TopActiveStocks => "getTopActiveStocks",
+            Endpoint::TopSecurity { .. } => "getTopSecurity",
+            Endpoint::AdvancedSecurity => "getAdvancedSecurity",
+            Endpoint::DeclinesSecurity => "getDeclinesSecurity",
+        }
+    }
+
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("method", self.method().to_string()),
+            ("ajax", "true".to_string()),
+        ];
+
+        match self {
+            Endpoint::SecuritiesAndIndices
+            | Endpoint::MarketIndices
+            | Endpoint::TopActiveStocks
+            | Endpoint::AdvancedSecurity
+            | Endpoint::DeclinesSecurity => (),
+            Endpoint::FindSecurityOrCompany {
+                query: text,
+                start,
+                limit,
+            } => {
+                query.push(("start", start.to_string()));
+                query.push(("limit", limit.to_string()));
+                query.push(("query", text.clone()));
+            }
+            Endpoint::CompanyInfo { company, security } => {
+                query.push(("company", company.to_string()));
+                query.push(("security", security.to_string()));
+            }
+            Endpoint::RecentSecurityQuoteData { security } => {
+                query.push(("security", security.to_string()));
+            }
+            Endpoint::TopSecurity { limit } => {
+                query.push(("limit", limit.to_string()));
+            }
+        }
+
+        query
+    }
+
+    pub fn url(&self, base_url: &str) -> String {
+        let query: Vec<String> = self
+            .query()
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, encode_component(value)))
+            .collect();
+
+        format!(
+            "{}/{}?{}",
+            base_url.trim_end_matches('/'),
+            self.page(),
+            query.join("&")
+        )
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters.
+pub fn encode_component(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+/// Reverses [`encode_component`], also reading `+` as a space as browsers
+/// send in form encoded query strings.
+pub fn decode_component(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => result.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        result.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => result.push(b'%'),
+                }
+            }
+            byte => result.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Source of raw PSE responses.
+#[async_trait(?Send)]
+pub trait Fetcher {
+    async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError>;
+}
+
+pub async fn fetch_json<T, F>(fetcher: &F, endpoint: &Endpoint) -> Result<T, FetchError>
+where
+    T: DeserializeOwned,
+    F: Fetcher + ?Sized,
+{
+    let body = fetcher.fetch(endpoint).await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Fetches from PSE, or whatever `base_url` points at, through the worker's
+/// global `fetch`.
+#[derive(Debug, Clone)]
+pub struct WorkerFetcher {
+    base_url: String,
+}
+
+impl WorkerFetcher {
+    pub fn new<S: Into<String>>(base_url: S) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    /// Uses the `PSE_BASE_URL` worker variable when it is set.
+    pub fn from_env() -> Self {
+        let base_url =
+            js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(BASE_URL_BINDING))
+                .ok()
+                .and_then(|value| value.as_string())
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        Self::new(base_url)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn fetch_url(&self, url: &str) -> Result<String, FetchError> {
+        let opts = RequestInit::new();
+        opts.set_method("GET");
+
+        let request = Request::new_with_str_and_init(url, &opts)
+            .map_err(|e| FetchError::Request(js_message(e)))?;
+        let headers = request.headers();
+        for (name, value) in [
+            ("Origin", PSE_ORIGIN),
+            ("Referer", PSE_REFERER),
+            ("X-Requested-With", "XMLHttpRequest"),
+        ]
+        .iter()
+        {
+            headers
+                .set(name, value)
+                .map_err(|e| FetchError::Request(js_message(e)))?;
+        }
+
+        let global = crate::worker_global_scope().ok_or(FetchError::NoGlobalScope)?;
+        let resp_value = JsFuture::from(global.fetch_with_request(&request))
+            .await
+            .map_err(|e| FetchError::Network(js_message(e)))?;
+        let resp: Response = resp_value
+            .dyn_into()
+            .map_err(|e| FetchError::Network(js_message(e)))?;
+
+        if !resp.ok() {
+            return Err(FetchError::Status {
+                status: resp.status(),
+                url: url.to_string(),
+            });
+        }
+
+        let text = resp
+            .text()
+            .map_err(|e| FetchError::Network(js_message(e)))?;
+        JsFuture::from(text)
+            .await
+            .map_err(|e| FetchError::Network(js_message(e)))?
+            .as_string()
+            .ok_or_else(|| FetchError::Network("response body is not text".to_string()))
+    }
+}
+
+#[async_trait(?Send)]
+impl Fetcher for WorkerFetcher {
+    async fn fetch(&self, endpoint: &Endpoint) -> Result<String, FetchError> {
+        self.fetch_url(&endpoint.url(&self.base_url)).await
+    }
+}
+
+pub(crate) fn js_message(value: JsValue) -> String {
+    value
+        .dyn_ref::<js_sys::Error>()
+        .map(|err| String::from(err.message()))
+        .or_else(|| value.as_string())
+        .unwrap_or_else(|| format!("{:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    struct StaticFetcher(&'static str);
+
+    #[async_trait(?Send)]
+    impl Fetcher for StaticFetcher {
+        async fn fetch(&self, _endpoint: &Endpoint) -> Result<String, FetchError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[test]
+    fn builds_urls() {
+        assert_eq!(
+            Endpoint::SecuritiesAndIndices.url(DEFAULT_BASE_URL),
+            "https://www.pse.com.ph/stockMarket/home.html?method=getSecuritiesAndIndicesForPublic&ajax=true"
+        );
+        assert_eq!(
+            Endpoint::FindSecurityOrCompany {
+                query: "sm prime".to_string(),
+                start: 0,
+                limit: 5,
+            }
+            .url("http://localhost:8787/"),
+            "http://localhost:8787/home.html?method=findSecurityOrCompany&ajax=true&start=0&limit=5&query=sm%20prime"
+        );
+        assert_eq!(
+            Endpoint::RecentSecurityQuoteData { security: 520 }.url(DEFAULT_BASE_URL),
+            "https://www.pse.com.ph/stockMarket/companyInfoHistoricalData.html?method=getRecentSecurityQuoteData&ajax=true&security=520"
+        );
+    }
+
+    #[test]
+    fn decodes_json() {
+        let value: Vec<u32> = block_on(fetch_json(
+            &StaticFetcher("[1, 2]"),
+            &Endpoint::SecuritiesAndIndices,
+        ))
+        .unwrap();
+        assert_eq!(value, vec![1, 2]);
+
+        let result: Result<Vec<u32>, _> = block_on(fetch_json(
+            &StaticFetcher("<html>"),
+            &Endpoint::SecuritiesAndIndices,
+        ));
+        assert!(matches!(result, Err(FetchError::Decode(_))));
+    }
+}
@@ -1,4 +1,6 @@
 use cfg_if::cfg_if;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
 
 cfg_if! {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -15,3 +17,8 @@ cfg_if! {
         pub fn set_panic_hook() {}
     }
 }
+
+pub fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(value).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+
+use crate::archive;
+use crate::errors::WorkerError;
+use crate::summary::Board;
+
+/// Bearer tokens are opaque, but must be long enough not to be guessed.
+pub const MIN_TOKEN_LENGTH: usize = 16;
+pub const MAX_TOKEN_LENGTH: usize = 128;
+
+/// PSE symbols are short runs of letters and digits, e.g. `SM` or `MWP2B`.
+pub const MAX_SYMBOL_LENGTH: usize = 10;
+
+/// Trims and upper-cases `symbol`, rejecting anything that cannot be a PSE
+/// symbol.
+pub fn symbol(symbol: &str) -> Result<String, WorkerError> {
+    let trimmed = symbol.trim();
+    let valid = !trimmed.is_empty()
+        && trimmed.len() <= MAX_SYMBOL_LENGTH
+        && trimmed.chars().all(|c| c.is_ascii_alphanumeric());
+
+    if valid {
+        Ok(trimmed.to_ascii_uppercase())
+    } else {
+        Err(WorkerError::InvalidSymbol(format!(
+            "invalid symbol `{}`, expected 1 to {} letters or digits",
+            symbol, MAX_SYMBOL_LENGTH
+        )))
+    }
+}
+
+/// Parses an ISO 8601 date, or an RFC 3339 timestamp resolved to its Manila
+/// date.
+pub fn date(value: &str) -> Result<NaiveDate, WorkerError> {
+    archive::parse_date(value).map_err(WorkerError::from)
+}
+
+/// Parses an optional positive count no larger than `max`.
+pub fn limit(value: Option<&str>, default: usize, max: usize) -> Result<usize, WorkerError> {
+    match value {
+        None => Ok(default),
+        Some(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|limit| *limit > 0 && *limit <= max)
+            .ok_or_else(|| {
+                WorkerError::InvalidArgument(format!(
+                    "invalid limit `{}`, expected a number from 1 to {}",
+                    value, max
+                ))
+            }),
+    }
+}
+
+/// Requires the query parameter `name` to be present and not blank.
+pub fn required<'a>(name: &str, value: Option<&'a str>) -> Result<&'a str, WorkerError> {
+    value
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| WorkerError::InvalidArgument(format!("missing `{}`", name)))
+}
+
+/// Parses an optional board filter, `main`, `sme` or `etf`.
+pub fn board(value: Option<&str>) -> Result<Option<Board>, WorkerError> {
+    match value {
+        None => Ok(None),
+        Some(value) => Board::parse(value).map(Some).ok_or_else(|| {
+            WorkerError::InvalidArgument(format!(
+                "invalid board `{}`, expected main, sme or etf",
+                value
+            ))
+        }),
+    }
+}
+
+/// Takes the token out of an `Authorization: Bearer <token>` header.
+pub fn token(authorization: Option<&str>) -> Result<String, WorkerError> {
+    let token = authorization
+        .and_then(|value| {
+            let (scheme, token) = value.trim().split_once(' ')?;
+            Some(token.trim()).filter(|_| scheme.eq_ignore_ascii_case("bearer"))
+        })
+        .ok_or_else(|| WorkerError::Unauthorized("missing bearer token".to_string()))?;
+
+    let valid = (MIN_TOKEN_LENGTH..=MAX_TOKEN_LENGTH).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_graphic());
+    if valid {
+        Ok(token.to_string())
+    } else {
+        Err(WorkerError::Unauthorized(format!(
+            "bearer tokens must be {} to {} printable characters",
+            MIN_TOKEN_LENGTH, MAX_TOKEN_LENGTH
+        )))
+    }
+}
+
+/// Parses a numeric id from a path segment.
+pub fn id(value: &str) -> Result<u32, WorkerError> {
+    value
+        .parse()
+        .map_err(|_| WorkerError::InvalidArgument(format!("invalid id `{}`", value)))
+}
+
+/// Decodes a JSON request body.
+pub fn body<T: DeserializeOwned>(body: Option<&str>) -> Result<T, WorkerError> {
+    let body = required("body", body)?;
+    serde_json::from_str(body)
+        .map_err(|err| WorkerError::InvalidArgument(format!("invalid body: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_symbols() {
+        assert_eq!(symbol(" smph ").unwrap(), "SMPH");
+        assert_eq!(symbol("MWP2B").unwrap(), "MWP2B");
+        for bad in &["", "SM PH", "SM;DROP", "ABCDEFGHIJK", "ÑÑ"] {
+            let err = symbol(bad).unwrap_err();
+            assert_eq!(err.code(), "invalid_symbol");
+            assert_eq!(err.status(), 400);
+        }
+    }
+
+    #[test]
+    fn validates_dates_and_limits() {
+        assert_eq!(
+            date("2020-10-16").unwrap(),
+            NaiveDate::from_ymd_opt(2020, 10, 16).unwrap()
+        );
+        assert_eq!(
+            date("2020-10-16T20:00:00Z").unwrap(),
+            NaiveDate::from_ymd_opt(2020, 10, 17).unwrap()
+        );
+        assert_eq!(date("16/10/2020").unwrap_err().code(), "invalid_date");
+
+        assert_eq!(limit(None, 10, 50).unwrap(), 10);
+        assert_eq!(limit(Some("5"), 10, 50).unwrap(), 5);
+        for bad in &["0", "51", "-1", "ten"] {
+            assert_eq!(
+                limit(Some(bad), 10, 50).unwrap_err().code(),
+                "invalid_argument"
+            );
+        }
+
+        assert_eq!(board(None).unwrap(), None);
+        assert_eq!(board(Some("ETF")).unwrap(), Some(Board::Etf));
+        assert_eq!(board(Some("pink")).unwrap_err().code(), "invalid_argument");
+    }
+
+    #[test]
+    fn validates_tokens_and_bodies() {
+        let token_header = "Bearer 0123456789abcdef";
+        assert_eq!(token(Some(token_header)).unwrap(), "0123456789abcdef");
+        assert_eq!(
+            token(Some("bearer  0123456789abcdef ")).unwrap(),
+            "0123456789abcdef"
+        );
+        for bad in &[
+            None,
+            Some("0123456789abcdef"),
+            Some("Basic 0123456789abcdef"),
+            Some("Bearer short"),
+        ] {
+            assert_eq!(token(*bad).unwrap_err().status(), 401);
+        }
+
+        assert_eq!(id("7").unwrap(), 7);
+        assert_eq!(id("-1").unwrap_err().code(), "invalid_argument");
+
+        let names: Vec<String> = body(Some(r#"["a"]"#)).unwrap();
+        assert_eq!(names, vec!["a"]);
+        assert_eq!(
+            body::<Vec<String>>(None).unwrap_err().code(),
+            "invalid_argument"
+        );
+        assert_eq!(
+            body::<Vec<String>>(Some("{")).unwrap_err().code(),
+            "invalid_argument"
+        );
+    }
+}
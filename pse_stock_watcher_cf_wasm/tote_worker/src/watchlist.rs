@@ -0,0 +1,365 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::WatchlistError;
+use crate::storage::Storage;
+use crate::{Stock, Stocks};
+
+pub const MAX_WATCHLISTS: usize = 20;
+pub const MAX_WATCHLIST_SYMBOLS: usize = 50;
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Symbols followed together, owned by the token that created them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Watchlist {
+    pub id: u32,
+    pub name: String,
+    pub symbols: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of a request creating a watchlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct NewWatchlist {
+    pub name: String,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+/// Body of a request renaming a watchlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct WatchlistRename {
+    pub name: String,
+}
+
+/// The latest quotes of a watchlist's symbols, in its order. Symbols that
+/// have since left the security list are skipped.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistQuotes {
+    pub watchlist: Watchlist,
+    pub as_of: Option<NaiveDateTime>,
+    pub quotes: Vec<Stock>,
+}
+
+impl WatchlistQuotes {
+    pub fn new(watchlist: Watchlist, stocks: Stocks) -> Self {
+        let quotes = watchlist
+            .symbols
+            .iter()
+            .filter_map(|symbol| find(&stocks, symbol).cloned())
+            .collect();
+
+        Self {
+            watchlist,
+            as_of: stocks.as_of,
+            quotes,
+        }
+    }
+}
+
+/// Keys are derived from a hash of the token so storage never holds it.
+pub fn watchlists_key(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("watchlists:{}", hex)
+}
+
+fn find<'a>(stocks: &'a Stocks, symbol: &str) -> Option<&'a Stock> {
+    stocks
+        .stocks
+        .iter()
+        .find(|stock| stock.security_symbol.eq_ignore_ascii_case(symbol))
+}
+
+/// Fails on the first of `symbols` missing from the security list.
+pub fn ensure_listed(stocks: &Stocks, symbols: &[String]) -> Result<(), WatchlistError> {
+    match symbols.iter().find(|symbol| find(stocks, symbol).is_none()) {
+        Some(symbol) => Err(WatchlistError::UnknownSymbol(symbol.clone())),
+        None => Ok(()),
+    }
+}
+
+fn name(name: &str) -> Result<String, WatchlistError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        Err(WatchlistError::InvalidName(MAX_NAME_LENGTH))
+    } else {
+        Ok(name.to_string())
+    }
+}
+
+/// Every watchlist of a token, stored as one document. Concurrent writes
+/// with the same token can overwrite each other.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Watchlists {
+    next_id: u32,
+    lists: Vec<Watchlist>,
+}
+
+impl Watchlists {
+    pub async fn load<S>(storage: &S, token: &str) -> Result<Self, WatchlistError>
+    where
+        S: Storage + ?Sized,
+    {
+        match storage.get(&watchlists_key(token)).await? {
+            Some(data) => Ok(serde_json::from_str(&data)?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub async fn save<S>(&self, storage: &S, token: &str) -> Result<(), WatchlistError>
+    where
+        S: Storage + ?Sized,
+    {
+        storage
+            .put(&watchlists_key(token), &serde_json::to_string(self)?)
+            .await?;
+        Ok(())
+    }
+
+    pub fn lists(&self) -> &[Watchlist] {
+        &self.lists
+    }
+
+    pub fn get(&self, id: u32) -> Result<&Watchlist, WatchlistError> {
+        self.lists
+            .iter()
+            .find(|list| list.id == id)
+            .ok_or(WatchlistError::NotFound(id))
+    }
+
+    fn get_mut(&mut self, id: u32) -> Result<&mut Watchlist, WatchlistError> {
+        self.lists
+            .iter_mut()
+            .find(|list| list.id == id)
+            .ok_or(WatchlistError::NotFound(id))
+    }
+
+    /// Adds a watchlist of `symbols`, which should already be validated and
+    /// upper-cased. Repeated symbols are kept once.
+    pub fn create(
+        &mut self,
+        new: NewWatchlist,
+        now: DateTime<Utc>,
+    ) -> Result<&Watchlist, WatchlistError> {
+        if self.lists.len() >= MAX_WATCHLISTS {
+            return Err(WatchlistError::TooManyWatchlists(MAX_WATCHLISTS));
+        }
+
+        let name = name(&new.name)?;
+        let mut symbols: Vec<String> = Vec::with_capacity(new.symbols.len());
+        for symbol in new.symbols {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        if symbols.len() > MAX_WATCHLIST_SYMBOLS {
+            return Err(WatchlistError::TooManySymbols(MAX_WATCHLIST_SYMBOLS));
+        }
+
+        self.next_id += 1;
+        self.lists.push(Watchlist {
+            id: self.next_id,
+            name,
+            symbols,
+            updated_at: now,
+        });
+        Ok(&self.lists[self.lists.len() - 1])
+    }
+
+    pub fn rename(
+        &mut self,
+        id: u32,
+        new_name: &str,
+        now: DateTime<Utc>,
+    ) -> Result<&Watchlist, WatchlistError> {
+        let new_name = name(new_name)?;
+        let list = self.get_mut(id)?;
+        list.name = new_name;
+        list.updated_at = now;
+        Ok(list)
+    }
+
+    /// Appends `symbol` unless the watchlist already has it.
+    pub fn add(
+        &mut self,
+        id: u32,
+        symbol: &str,
+        now: DateTime<Utc>,
+    ) -> Result<&Watchlist, WatchlistError> {
+        let list = self.get_mut(id)?;
+        if !list.symbols.iter().any(|item| item == symbol) {
+            if list.symbols.len() >= MAX_WATCHLIST_SYMBOLS {
+                return Err(WatchlistError::TooManySymbols(MAX_WATCHLIST_SYMBOLS));
+            }
+            list.symbols.push(symbol.to_string());
+            list.updated_at = now;
+        }
+        Ok(list)
+    }
+
+    /// Drops `symbol`, if the watchlist has it.
+    pub fn remove(
+        &mut self,
+        id: u32,
+        symbol: &str,
+        now: DateTime<Utc>,
+    ) -> Result<&Watchlist, WatchlistError> {
+        let list = self.get_mut(id)?;
+        if list.symbols.iter().any(|item| item == symbol) {
+            list.symbols.retain(|item| item != symbol);
+            list.updated_at = now;
+        }
+        Ok(list)
+    }
+
+    pub fn delete(&mut self, id: u32) -> Result<Watchlist, WatchlistError> {
+        let index = self
+            .lists
+            .iter()
+            .position(|list| list.id == id)
+            .ok_or(WatchlistError::NotFound(id))?;
+        Ok(self.lists.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::Indicator;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2020, 10, 16, 7, 0, 0).unwrap()
+    }
+
+    fn new(name: &str, symbols: &[&str]) -> NewWatchlist {
+        NewWatchlist {
+            name: name.to_string(),
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+        }
+    }
+
+    fn stocks(symbols: &[&str]) -> Stocks {
+        Stocks {
+            as_of: None,
+            stocks: symbols
+                .iter()
+                .map(|symbol| Stock {
+                    total_volume: 1000,
+                    indicator: Indicator::Up,
+                    perc_change_close: 1.0,
+                    last_traded_price: 10.0,
+                    security_alias: symbol.to_string(),
+                    security_symbol: symbol.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn edits_watchlists() {
+        let mut lists = Watchlists::default();
+        let list = lists
+            .create(new(" Banks ", &["BDO", "BDO"]), now())
+            .unwrap();
+        assert_eq!((list.id, list.name.as_str()), (1, "Banks"));
+        assert_eq!(list.symbols, vec!["BDO"]);
+
+        lists.create(new("Malls", &["SM"]), now()).unwrap();
+        lists.add(1, "BPI", now()).unwrap();
+        lists.add(1, "BDO", now()).unwrap();
+        assert_eq!(lists.get(1).unwrap().symbols, vec!["BDO", "BPI"]);
+        lists.remove(1, "BDO", now()).unwrap();
+        assert_eq!(
+            lists.rename(1, "Lenders", now()).unwrap().symbols,
+            vec!["BPI"]
+        );
+
+        assert_eq!(lists.delete(1).unwrap().name, "Lenders");
+        assert!(matches!(lists.get(1), Err(WatchlistError::NotFound(1))));
+        // Ids are not reused after a delete.
+        assert_eq!(lists.create(new("Banks", &[]), now()).unwrap().id, 3);
+    }
+
+    #[test]
+    fn rejects_invalid_edits() {
+        let mut lists = Watchlists::default();
+        assert!(matches!(
+            lists.create(new("  ", &[]), now()),
+            Err(WatchlistError::InvalidName(_))
+        ));
+        assert!(matches!(
+            lists.rename(9, "Banks", now()),
+            Err(WatchlistError::NotFound(9))
+        ));
+
+        let symbols: Vec<String> = (0..=MAX_WATCHLIST_SYMBOLS)
+            .map(|i| format!("S{}", i))
+            .collect();
+        let too_many = NewWatchlist {
+            name: "All".to_string(),
+            symbols,
+        };
+        assert!(matches!(
+            lists.create(too_many, now()),
+            Err(WatchlistError::TooManySymbols(_))
+        ));
+
+        for i in 0..MAX_WATCHLISTS {
+            lists.create(new(&i.to_string(), &[]), now()).unwrap();
+        }
+        assert!(matches!(
+            lists.create(new("One more", &[]), now()),
+            Err(WatchlistError::TooManyWatchlists(_))
+        ));
+    }
+
+    #[test]
+    fn stores_watchlists_per_token() {
+        let storage = MemoryStorage::new();
+        let mut lists = Watchlists::default();
+        lists.create(new("Banks", &["BDO"]), now()).unwrap();
+        block_on(lists.save(&storage, "first-token-0001")).unwrap();
+
+        let loaded = block_on(Watchlists::load(&storage, "first-token-0001")).unwrap();
+        assert_eq!(loaded, lists);
+        let other = block_on(Watchlists::load(&storage, "other-token-0001")).unwrap();
+        assert!(other.lists().is_empty());
+
+        let key = watchlists_key("first-token-0001");
+        assert!(key.starts_with("watchlists:"));
+        assert!(!key.contains("first-token"));
+        assert!(block_on(storage.get(&key)).unwrap().is_some());
+    }
+
+    #[test]
+    fn quotes_listed_symbols() {
+        let listed = stocks(&["ALI", "SM"]);
+        assert!(ensure_listed(&listed, &["SM".to_string()]).is_ok());
+        assert!(matches!(
+            ensure_listed(&listed, &["SM".to_string(), "NOPE".to_string()]),
+            Err(WatchlistError::UnknownSymbol(symbol)) if symbol == "NOPE"
+        ));
+
+        let mut lists = Watchlists::default();
+        let list = lists
+            .create(new("Mine", &["SM", "GONE", "ALI"]), now())
+            .unwrap()
+            .clone();
+        let quotes = WatchlistQuotes::new(list, listed);
+        let symbols: Vec<&str> = quotes
+            .quotes
+            .iter()
+            .map(|stock| stock.security_symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["SM", "ALI"]);
+    }
+}
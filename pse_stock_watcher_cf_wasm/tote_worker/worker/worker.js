@@ -17,14 +17,30 @@ addEventListener('fetch', event => {
     ) {
       event.respondWith(handleNonPreflightRequest(request));
     }
+  } else if (
+    url.pathname.startsWith("/v1/")
+    || url.pathname === "/openapi.json"
+    || url.pathname.startsWith("/stocks")
+  ) {
+    event.respondWith(handleApiRequest(request));
   } else if (url.pathname.startsWith("/test")) {
     event.respondWith(handleTestRequest(request));
   } else if (url.pathname.startsWith("/get_all_stocks")) {
     event.respondWith(handleGetAllStocksRequest(request));
-  } else if (url.pathname.startsWith("/get_stock")) {
-    event.respondWith(handleGetStockRequest(request));
   } else if (url.pathname.startsWith("/get_stock_by_date")) {
     event.respondWith(handleGetStockByDateRequest(request));
+  } else if (url.pathname.startsWith("/get_stock")) {
+    event.respondWith(handleGetStockRequest(request));
+  } else if (url.pathname.startsWith("/market_summary")) {
+    event.respondWith(handleMarketSummaryRequest(request));
+  } else if (url.pathname.startsWith("/search")) {
+    event.respondWith(handleSearchRequest(request));
+  } else if (url.pathname.startsWith("/get_history")) {
+    event.respondWith(handleGetHistoryRequest(request));
+  } else if (url.pathname.startsWith("/get_indicators")) {
+    event.respondWith(handleGetIndicatorsRequest(request));
+  } else if (url.pathname.startsWith("/job_status")) {
+    event.respondWith(handleJobStatusRequest(request));
   } else if (url.pathname.startsWith("/archive")) {
     event.respondWith(handleArchiveRequest(request));         
   } else {
@@ -32,6 +48,44 @@ addEventListener('fetch', event => {
   }  
 });
 
+addEventListener('scheduled', event => {
+  event.waitUntil(handleScheduled(event));
+});
+
+async function handleScheduled(event) {
+  const { scheduled } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return scheduled(event.scheduledTime);
+}
+
+const jsonHeaders = {
+  'Content-Type': 'application/json;charset=UTF-8',
+};
+
+// Runs a wasm export and answers with its result as JSON. Exports reject
+// with `{ status, code, message }`, which is passed on as the error response.
+async function jsonResponse(call) {
+  try {
+    const data = await call();
+    return new Response(JSON.stringify(data), {
+      headers: jsonHeaders,
+      status: 200,
+    });
+  } catch (err) {
+    const status = err && err.status ? err.status : 500;
+    const body = err && err.code ? err : {
+      status,
+      code: "internal",
+      message: String(err),
+    };
+    return new Response(JSON.stringify(body), {
+      headers: jsonHeaders,
+      status,
+    });
+  }
+}
+
 function handleRequest() {
   return new Response("Tote", {
     headers: {
@@ -78,30 +132,25 @@ function handlePreflightRequest(request) {
   }
 }
 
+async function handleApiRequest(request) {
+  const { handle_request } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return handle_request(request);
+}
+
 async function handleTestRequest(request) {
   const { test } = wasm_bindgen;
   await wasm_bindgen(wasm);
 
-  const data = await test();
-  return new Response(JSON.stringify(data), {
-    headers: {
-      'Content-Type': 'application/json;charset=UTF-8',
-    },
-    status: 200,
-  });
+  return jsonResponse(() => test());
 }
 
 async function handleGetAllStocksRequest(request) {
   const { get_all_stocks } = wasm_bindgen;
   await wasm_bindgen(wasm);
 
-  const data = await get_all_stocks();
-  return new Response(JSON.stringify(data), {
-    headers: {
-      'Content-Type': 'application/json;charset=UTF-8',
-    },
-    status: 200,
-  });
+  return jsonResponse(() => get_all_stocks());
 }
 
 async function handleGetStockRequest(request) {
@@ -111,13 +160,7 @@ async function handleGetStockRequest(request) {
   const { get_stock } = wasm_bindgen;
   await wasm_bindgen(wasm);
 
-  const data = await get_stock(symbol);
-  return new Response(JSON.stringify(data), {
-    headers: {
-      'Content-Type': 'application/json;charset=UTF-8',
-    },
-    status: 200,
-  });
+  return jsonResponse(() => get_stock(symbol));
 }
 
 async function handleGetStockByDateRequest(request) {
@@ -128,24 +171,67 @@ async function handleGetStockByDateRequest(request) {
   const { get_stock_by_date } = wasm_bindgen;
   await wasm_bindgen(wasm);
 
-  const data = await get_stock_by_date(symbol, timestamp);
-  return new Response(JSON.stringify(data), {
-    headers: {
-      'Content-Type': 'application/json;charset=UTF-8',
-    },
-    status: 200,
-  });
+  return jsonResponse(() => get_stock_by_date(symbol, timestamp));
+}
+
+async function handleMarketSummaryRequest(request) {
+  const url = new URL(request.url);
+  let board = url.searchParams.get("board");
+  let limit = url.searchParams.get("limit");
+
+  const { get_market_summary } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return jsonResponse(() => get_market_summary(
+    board === null ? undefined : board,
+    limit === null ? undefined : limit,
+  ));
+}
+
+async function handleSearchRequest(request) {
+  const url = new URL(request.url);
+  let query = url.searchParams.get("q");
+  let limit = url.searchParams.get("limit");
+
+  const { search } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return jsonResponse(() => search(query, limit === null ? undefined : Number(limit)));
+}
+
+async function handleGetHistoryRequest(request) {
+  const url = new URL(request.url);
+  let symbol = url.searchParams.get("symbol");
+  let from = url.searchParams.get("from");
+  let to = url.searchParams.get("to");
+
+  const { get_history } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return jsonResponse(() => get_history(symbol, from, to));
+}
+
+async function handleGetIndicatorsRequest(request) {
+  const url = new URL(request.url);
+  let symbol = url.searchParams.get("symbol");
+  let spec = url.searchParams.get("spec");
+
+  const { get_indicators } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return jsonResponse(() => get_indicators(symbol, spec));
+}
+
+async function handleJobStatusRequest(request) {
+  const { get_job_status } = wasm_bindgen;
+  await wasm_bindgen(wasm);
+
+  return jsonResponse(() => get_job_status());
 }
 
 async function handleArchiveRequest(request) {
   const { archive } = wasm_bindgen;
   await wasm_bindgen(wasm);
 
-  const data = await archive();
-  return new Response(JSON.stringify(data), {
-    headers: {
-      'Content-Type': 'application/json;charset=UTF-8',
-    },
-    status: 200,
-  });
+  return jsonResponse(() => archive());
 }
\ No newline at end of file